@@ -0,0 +1,390 @@
+use crate::{
+    exporter_metrics::ExporterMetrics,
+    log_metrics::LogMetrics,
+    log_sources::{FileFilter, LogRoot},
+    trace_format::TraceFormat,
+    watch_logs::{handle_log_line, trace_file_process},
+};
+use anyhow::{bail, Context, Result};
+use flate2::read::MultiGzDecoder;
+use opentelemetry::KeyValue;
+use std::{
+    cell::Cell,
+    fs::File,
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
+
+// Lines between progress updates while a bundle member is read.
+const PROGRESS_INTERVAL_LINES: u64 = 10_000;
+
+// Read support bundles in place and push every trace event through the configured gauges. Each
+// path may be a `.tar`, `.tar.gz` or `.tgz` archive of a log directory, a gzipped trace file, or a
+// plain trace file. Archive members and gzipped files are selected with the default
+// `trace.*.json`/`trace.*.xml` rules (ignoring any `.gz` suffix). Each one is identified as
+// `<bundle>:<member>`, which labels both its backfill progress and its events as `file`, so the
+// same process's events from different bundles or files stay in separate series. A member that
+// cannot be read is logged and skipped without giving up on the rest of its archive.
+pub async fn ingest_bundles(
+    paths: &[PathBuf],
+    metrics: LogMetrics,
+    exporter_metrics: ExporterMetrics,
+) -> Result<()> {
+    let ingester = BundleIngester {
+        filter: LogRoot::new(PathBuf::new()).filter,
+        metrics,
+        exporter_metrics,
+    };
+
+    let paths = paths.to_vec();
+    tokio::task::spawn_blocking(move || {
+        let mut failed = 0;
+        for path in paths.iter() {
+            if let Err(error) = ingester.ingest_path(path) {
+                tracing::error!(?error, bundle = %path.display(), "failed to ingest bundle");
+                failed += 1;
+            }
+        }
+        if failed > 0 {
+            bail!("failed to ingest {failed} of {} bundles", paths.len());
+        }
+        Ok(())
+    })
+    .await?
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BundleKind {
+    Tar,
+    TarGz,
+    Gz,
+    Plain,
+}
+
+impl BundleKind {
+    fn from_path(path: &Path) -> Self {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        if name.ends_with(".tar") {
+            Self::Tar
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Self::TarGz
+        } else if name.ends_with(".gz") {
+            Self::Gz
+        } else {
+            Self::Plain
+        }
+    }
+}
+
+struct BundleIngester {
+    filter: FileFilter,
+    metrics: LogMetrics,
+    exporter_metrics: ExporterMetrics,
+}
+
+impl BundleIngester {
+    fn ingest_path(&self, path: &Path) -> Result<()> {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open bundle {}", path.display()))?;
+        let len = file.metadata()?.len();
+        tracing::info!(bundle = %path.display(), "ingesting bundle");
+
+        match BundleKind::from_path(path) {
+            BundleKind::Tar => self.ingest_archive(path, file),
+            BundleKind::TarGz => {
+                self.ingest_archive(path, MultiGzDecoder::new(BufReader::new(file)))
+            }
+            BundleKind::Gz | BundleKind::Plain => {
+                let identity = path.display().to_string();
+                self.ingest_member(&identity, path, file, len)
+            }
+        }
+    }
+
+    fn ingest_archive(&self, path: &Path, reader: impl Read) -> Result<()> {
+        let mut archive = tar::Archive::new(reader);
+        let entries = archive
+            .entries()
+            .with_context(|| format!("failed to read archive {}", path.display()))?;
+
+        let mut skipped = 0;
+        for entry in entries {
+            let entry =
+                entry.with_context(|| format!("failed to read archive {}", path.display()))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+
+            let member = match entry.path() {
+                Ok(member) => member.into_owned(),
+                Err(error) => {
+                    tracing::warn!(?error, bundle = %path.display(), "skipping archive member with an unreadable name");
+                    skipped += 1;
+                    continue;
+                }
+            };
+            if !self.filter.matches(&trace_file_name(&member)) {
+                continue;
+            }
+
+            let identity = format!("{}:{}", path.display(), member.display());
+            let size = entry.size();
+            if let Err(error) = self.ingest_member(&identity, &member, entry, size) {
+                tracing::warn!(
+                    ?error,
+                    file = identity,
+                    "skipping unreadable archive member"
+                );
+                skipped += 1;
+            }
+        }
+        if skipped > 0 {
+            tracing::warn!(bundle = %path.display(), skipped, "skipped unreadable archive members");
+        }
+        Ok(())
+    }
+
+    // Read one trace file, gunzipping it if its name ends in `.gz`. `raw_len` is the size of
+    // `raw` before decompression, which progress is measured against.
+    fn ingest_member(
+        &self,
+        identity: &str,
+        member: &Path,
+        raw: impl Read,
+        raw_len: u64,
+    ) -> Result<()> {
+        let name = trace_file_name(member);
+        let bytes_read = Rc::new(Cell::new(0));
+        let counted = CountingReader {
+            inner: raw,
+            bytes_read: Rc::clone(&bytes_read),
+        };
+        let mut reader: Box<dyn BufRead> = if name != member {
+            Box::new(BufReader::new(MultiGzDecoder::new(counted)))
+        } else {
+            Box::new(BufReader::new(counted))
+        };
+
+        // Ingestion has no log roots, so the file label is the only static label.
+        let metrics = self
+            .metrics
+            .with_static_labels(&[KeyValue::new("file", identity.to_string())]);
+        let progress = self
+            .exporter_metrics
+            .track_file(Path::new(identity), &trace_file_process(&name));
+        let mut format = TraceFormat::from_path(&name);
+        let mut line = Vec::new();
+        let mut lines = 0u64;
+        progress.record_backfill(0, raw_len);
+
+        loop {
+            line.clear();
+            let bytes = reader
+                .read_until(b'\n', &mut line)
+                .with_context(|| format!("failed to read {identity}"))?;
+            if bytes == 0 {
+                break;
+            }
+
+            let text = String::from_utf8_lossy(&line);
+            let trimmed = text.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            handle_log_line(
+                trimmed,
+                TraceFormat::resolve(&mut format, trimmed),
+                &metrics,
+                &self.exporter_metrics,
+                None,
+            );
+
+            lines += 1;
+            if lines.is_multiple_of(PROGRESS_INTERVAL_LINES) {
+                progress.record_backfill(bytes_read.get(), raw_len);
+            }
+        }
+
+        progress.record_backfill(raw_len, raw_len);
+        tracing::info!(file = identity, lines, "ingested trace file");
+        Ok(())
+    }
+}
+
+// The name a trace file had before it was gzipped.
+pub(crate) fn trace_file_name(path: &Path) -> PathBuf {
+    match path.to_str().and_then(|path| path.strip_suffix(".gz")) {
+        Some(stripped) => PathBuf::from(stripped),
+        None => path.to_path_buf(),
+    }
+}
+
+// Counts the bytes read from a (possibly compressed) source so progress can be reported against
+// its on-disk size.
+struct CountingReader<R> {
+    inner: R,
+    bytes_read: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let bytes = self.inner.read(buf)?;
+        self.bytes_read.set(self.bytes_read.get() + bytes as u64);
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::metrics::{find_metric_in, prometheus_meter};
+    use crate::test_helpers::recording::RecordingMetric;
+    use flate2::{write::GzEncoder, Compression};
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use std::io::Write;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn ingester(
+        recorder: &RecordingMetric,
+    ) -> (BundleIngester, SdkMeterProvider, prometheus::Registry) {
+        let (provider, meter, registry) = prometheus_meter();
+        let ingester = BundleIngester {
+            filter: LogRoot::new(PathBuf::new()).filter,
+            metrics: LogMetrics::from_metrics(vec![Arc::new(recorder.clone())]),
+            exporter_metrics: ExporterMetrics::new(&meter),
+        };
+        (ingester, provider, registry)
+    }
+
+    fn json_event(event_type: &str) -> String {
+        format!("{{\"Type\":\"{event_type}\",\"Machine\":\"10.0.0.1:4500\"}}\n")
+    }
+
+    fn gzip(contents: &str) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(contents.as_bytes()).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn append_member(builder: &mut tar::Builder<impl Write>, name: &str, contents: &[u8]) {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        builder.append_data(&mut header, name, contents).unwrap();
+    }
+
+    #[test]
+    fn bundle_kind_follows_file_name() {
+        assert_eq!(BundleKind::from_path(Path::new("b.tar")), BundleKind::Tar);
+        assert_eq!(
+            BundleKind::from_path(Path::new("b.tar.gz")),
+            BundleKind::TarGz
+        );
+        assert_eq!(BundleKind::from_path(Path::new("b.tgz")), BundleKind::TarGz);
+        assert_eq!(
+            BundleKind::from_path(Path::new("trace.1.json.gz")),
+            BundleKind::Gz
+        );
+        assert_eq!(
+            BundleKind::from_path(Path::new("trace.1.json")),
+            BundleKind::Plain
+        );
+    }
+
+    #[test]
+    fn ingests_gzipped_trace_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("trace.10.0.0.1.4500.json.gz");
+        std::fs::write(&path, gzip(&(json_event("First") + &json_event("Second")))).unwrap();
+
+        let recorder = RecordingMetric::default();
+        let (ingester, _provider, registry) = ingester(&recorder);
+        ingester.ingest_path(&path).unwrap();
+
+        assert_eq!(recorder.types(), ["First", "Second"]);
+        let families = registry.gather();
+        let progress = find_metric_in(
+            &families,
+            "fdb_exporter_backfill_progress_ratio",
+            "file",
+            &path.display().to_string(),
+        )
+        .expect("progress gauge");
+        assert_eq!(progress.get_gauge().get_value(), 1.0);
+    }
+
+    #[test]
+    fn ingests_matching_members_of_compressed_archive() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("bundle.tar.gz");
+
+        let mut builder = tar::Builder::new(GzEncoder::new(
+            File::create(&path).unwrap(),
+            Compression::default(),
+        ));
+        append_member(
+            &mut builder,
+            "logs/trace.1.json",
+            json_event("Json").as_bytes(),
+        );
+        append_member(
+            &mut builder,
+            "logs/trace.2.xml",
+            b"<?xml version=\"1.0\"?>\n<Trace>\n<Event Type=\"Xml\" Machine=\"10.0.0.1:4500\" />\n</Trace>\n",
+        );
+        append_member(
+            &mut builder,
+            "logs/trace.3.json.gz",
+            &gzip(&json_event("Nested")),
+        );
+        append_member(&mut builder, "logs/notes.txt", b"not a trace file\n");
+        builder.into_inner().unwrap().finish().unwrap();
+
+        let recorder = RecordingMetric::default();
+        let (ingester, _provider, registry) = ingester(&recorder);
+        ingester.ingest_path(&path).unwrap();
+
+        assert_eq!(recorder.types(), ["Json", "Xml", "Nested"]);
+        let files: Vec<Option<String>> = ["trace.1.json", "trace.2.xml", "trace.3.json.gz"]
+            .iter()
+            .map(|member| Some(format!("{}:logs/{member}", path.display())))
+            .collect();
+        assert_eq!(recorder.label_values("file"), files);
+        let families = registry.gather();
+        let member = format!("{}:logs/trace.3.json.gz", path.display());
+        assert!(find_metric_in(
+            &families,
+            "fdb_exporter_backfill_progress_ratio",
+            "file",
+            &member
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn skips_unreadable_archive_members() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("bundle.tar");
+
+        let mut builder = tar::Builder::new(File::create(&path).unwrap());
+        append_member(&mut builder, "logs/trace.1.json.gz", b"not gzipped\n");
+        append_member(
+            &mut builder,
+            "logs/trace.2.json",
+            json_event("Readable").as_bytes(),
+        );
+        builder.into_inner().unwrap();
+
+        let recorder = RecordingMetric::default();
+        let (ingester, _provider, _registry) = ingester(&recorder);
+        ingester.ingest_path(&path).unwrap();
+
+        assert_eq!(recorder.types(), ["Readable"]);
+    }
+}
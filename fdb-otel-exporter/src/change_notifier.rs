@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use notify::{event::ModifyKind, Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tokio::sync::Notify;
+
+// Kinds of file system change the log watchers care about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    // A file appeared, disappeared or was renamed, so the directory listing changed.
+    Listing,
+    // A file's contents changed.
+    Contents,
+}
+
+// Fans file system notifications out to the directory watcher and to per-file tailers. Wakeups are
+// remembered until consumed (see `tokio::sync::Notify`), so a change that happens while nobody is
+// waiting is not lost.
+#[derive(Clone, Default)]
+pub struct ChangeNotifier {
+    inner: Arc<ChangeNotifierInner>,
+}
+
+#[derive(Default)]
+struct ChangeNotifierInner {
+    directory: Notify,
+    files: Mutex<HashMap<PathBuf, Arc<Notify>>>,
+    watcher: Mutex<Option<RecommendedWatcher>>,
+}
+
+impl ChangeNotifier {
+    // Subscribe to inotify (or the platform equivalent) events for `dir`, and for everything below
+    // it when `recursive` is set.
+    pub fn watch_directory(dir: &Path, recursive: bool) -> Result<Self> {
+        let notifier = Self::default();
+        let handler = notifier.clone();
+        let mut watcher =
+            notify::recommended_watcher(move |result: notify::Result<Event>| match result {
+                Ok(event) => handler.handle_event(&event),
+                Err(error) => tracing::warn!(?error, "file system notification error"),
+            })
+            .context("failed to create file system watcher")?;
+        watcher
+            .watch(
+                dir,
+                if recursive {
+                    RecursiveMode::Recursive
+                } else {
+                    RecursiveMode::NonRecursive
+                },
+            )
+            .with_context(|| format!("failed to watch log directory {}", dir.display()))?;
+        *notifier
+            .inner
+            .watcher
+            .lock()
+            .expect("change notifier watcher poisoned") = Some(watcher);
+        Ok(notifier)
+    }
+
+    fn handle_event(&self, event: &Event) {
+        let kind = match event.kind {
+            EventKind::Create(_)
+            | EventKind::Remove(_)
+            | EventKind::Modify(ModifyKind::Name(_)) => ChangeKind::Listing,
+            EventKind::Modify(_) | EventKind::Any | EventKind::Other => ChangeKind::Contents,
+            // Reads, including the exporter's own, must not wake the readers.
+            EventKind::Access(_) => return,
+        };
+        for path in event.paths.iter() {
+            self.notify_path(path, kind);
+        }
+    }
+
+    pub fn notify_path(&self, path: &Path, kind: ChangeKind) {
+        if kind == ChangeKind::Listing {
+            self.inner.directory.notify_one();
+        }
+        if let Some(file) = self
+            .inner
+            .files
+            .lock()
+            .expect("change notifier files poisoned")
+            .get(path)
+        {
+            file.notify_one();
+        }
+    }
+
+    // Register interest in `path`, returning the handle a tailer waits on.
+    pub fn file(&self, path: &Path) -> Arc<Notify> {
+        Arc::clone(
+            self.inner
+                .files
+                .lock()
+                .expect("change notifier files poisoned")
+                .entry(path.to_path_buf())
+                .or_default(),
+        )
+    }
+
+    pub fn forget(&self, path: &Path) {
+        self.inner
+            .files
+            .lock()
+            .expect("change notifier files poisoned")
+            .remove(path);
+    }
+
+    pub async fn directory_changed(&self) {
+        self.inner.directory.notified().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+    use tempfile::tempdir;
+    use tokio::time::timeout;
+
+    #[tokio::test]
+    async fn notify_path_wakes_directory_and_file_waiters() {
+        let notifier = ChangeNotifier::default();
+        let path = PathBuf::from("/logs/trace.1.json");
+        let file = notifier.file(&path);
+
+        notifier.notify_path(&path, ChangeKind::Contents);
+        timeout(Duration::from_millis(100), file.notified())
+            .await
+            .expect("contents change should wake the file waiter");
+        assert!(
+            timeout(Duration::from_millis(20), notifier.directory_changed())
+                .await
+                .is_err(),
+            "contents changes should not wake the directory watcher"
+        );
+
+        notifier.notify_path(&path, ChangeKind::Listing);
+        timeout(Duration::from_millis(100), notifier.directory_changed())
+            .await
+            .expect("listing change should wake the directory watcher");
+    }
+
+    #[tokio::test]
+    async fn forget_drops_file_registration() {
+        let notifier = ChangeNotifier::default();
+        let path = PathBuf::from("/logs/trace.1.json");
+        let file = notifier.file(&path);
+
+        notifier.forget(&path);
+        notifier.notify_path(&path, ChangeKind::Contents);
+        assert!(timeout(Duration::from_millis(20), file.notified())
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn watch_directory_reports_real_file_changes() {
+        let dir = tempdir().expect("tempdir");
+        let notifier = ChangeNotifier::watch_directory(dir.path(), false).expect("watch temp dir");
+        let path = dir.path().join("trace.1.json");
+        let file = notifier.file(&path);
+
+        std::fs::write(&path, "{}\n").expect("create trace file");
+        timeout(Duration::from_secs(5), notifier.directory_changed())
+            .await
+            .expect("file creation should be reported");
+        timeout(Duration::from_secs(5), file.notified())
+            .await
+            .expect("file write should be reported");
+    }
+}
@@ -0,0 +1,254 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+const CHECKPOINT_FILE_NAME: &str = "checkpoints.json";
+
+// Last read position of a trace file, plus enough identity to tell whether the file at the same
+// path is still the one that was being read when the checkpoint was taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileCheckpoint {
+    pub device: u64,
+    pub inode: u64,
+    pub offset: u64,
+    pub first_line_hash: Option<u64>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckpointFile {
+    files: BTreeMap<PathBuf, FileCheckpoint>,
+}
+
+// Per-file read offsets persisted under the configured state directory so a restarted exporter
+// resumes where it left off. Updates are kept in memory and written out by `flush`.
+#[derive(Clone, Default)]
+pub struct CheckpointStore {
+    path: Option<PathBuf>,
+    entries: Arc<Mutex<BTreeMap<PathBuf, FileCheckpoint>>>,
+    // Held for a whole flush, so concurrent flushes cannot interleave their writes and renames.
+    flush_lock: Arc<Mutex<()>>,
+}
+
+// Distinguishes the temporary files of flushes, including ones from other exporter processes.
+static FLUSH_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+impl CheckpointStore {
+    // A store that never reads or writes anything, used when no state directory is configured.
+    pub fn disabled() -> Self {
+        Self::default()
+    }
+
+    // Load checkpoints from `state_dir`, creating the directory if needed. An unreadable or corrupt
+    // checkpoint file is discarded so the exporter still starts.
+    pub fn load(state_dir: &Path) -> Result<Self> {
+        fs::create_dir_all(state_dir)
+            .with_context(|| format!("failed to create state directory {}", state_dir.display()))?;
+
+        let path = state_dir.join(CHECKPOINT_FILE_NAME);
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<CheckpointFile>(&contents) {
+                Ok(file) => file.files,
+                Err(error) => {
+                    tracing::warn!(?error, path = %path.display(), "ignoring corrupt checkpoint file");
+                    BTreeMap::new()
+                }
+            },
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(error) => {
+                tracing::warn!(?error, path = %path.display(), "ignoring unreadable checkpoint file");
+                BTreeMap::new()
+            }
+        };
+
+        Ok(Self {
+            path: Some(path),
+            entries: Arc::new(Mutex::new(entries)),
+            flush_lock: Arc::default(),
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.path.is_some()
+    }
+
+    pub fn get(&self, file: &Path) -> Option<FileCheckpoint> {
+        self.entries
+            .lock()
+            .expect("checkpoint cache poisoned")
+            .get(file)
+            .copied()
+    }
+
+    pub fn update(&self, file: &Path, checkpoint: FileCheckpoint) {
+        if self.path.is_none() {
+            return;
+        }
+        self.entries
+            .lock()
+            .expect("checkpoint cache poisoned")
+            .insert(file.to_path_buf(), checkpoint);
+    }
+
+    pub fn remove(&self, file: &Path) {
+        self.entries
+            .lock()
+            .expect("checkpoint cache poisoned")
+            .remove(file);
+    }
+
+    // Write the current checkpoints to disk, replacing the previous file atomically.
+    pub fn flush(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+
+        let _flushing = self.flush_lock.lock().expect("checkpoint flush poisoned");
+        let contents = {
+            let entries = self.entries.lock().expect("checkpoint cache poisoned");
+            serde_json::to_string(&CheckpointFile {
+                files: entries.clone(),
+            })?
+        };
+
+        let temp_path = path.with_extension(format!(
+            "json.{}.{}.tmp",
+            std::process::id(),
+            FLUSH_SEQUENCE.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&temp_path, contents)
+            .with_context(|| format!("failed to write checkpoint file {}", temp_path.display()))?;
+        fs::rename(&temp_path, path).with_context(|| {
+            let _ = fs::remove_file(&temp_path);
+            format!("failed to replace checkpoint file {}", path.display())
+        })?;
+        Ok(())
+    }
+
+    // `flush` on the blocking thread pool, so the file system calls do not stall async tasks.
+    pub async fn flush_in_background(&self) -> Result<()> {
+        let store = self.clone();
+        tokio::task::spawn_blocking(move || store.flush()).await?
+    }
+}
+
+// Stable 64-bit FNV-1a hash of a trace file's first line, used as part of the file identity since
+// inode numbers are reused once a rotated file is deleted.
+pub fn hash_first_line(line: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    line.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn checkpoint(offset: u64) -> FileCheckpoint {
+        FileCheckpoint {
+            device: 1,
+            inode: 42,
+            offset,
+            first_line_hash: Some(hash_first_line(b"{\"Type\":\"First\"}\n")),
+        }
+    }
+
+    #[test]
+    fn flush_and_load_round_trip() {
+        let dir = tempdir().expect("tempdir");
+        let trace_path = PathBuf::from("/logs/trace.1.json");
+
+        let store = CheckpointStore::load(dir.path()).expect("load empty store");
+        assert!(store.get(&trace_path).is_none());
+
+        store.update(&trace_path, checkpoint(128));
+        store.flush().expect("flush checkpoints");
+
+        let reloaded = CheckpointStore::load(dir.path()).expect("reload store");
+        assert_eq!(reloaded.get(&trace_path), Some(checkpoint(128)));
+
+        reloaded.remove(&trace_path);
+        reloaded.flush().expect("flush after remove");
+        let emptied = CheckpointStore::load(dir.path()).expect("reload emptied store");
+        assert!(emptied.get(&trace_path).is_none());
+    }
+
+    #[test]
+    fn concurrent_flushes_keep_file_whole() {
+        let dir = tempdir().expect("tempdir");
+        let store = CheckpointStore::load(dir.path()).expect("load empty store");
+
+        std::thread::scope(|scope| {
+            for thread in 0..8u64 {
+                let store = store.clone();
+                scope.spawn(move || {
+                    for offset in 0..20 {
+                        let trace_path = PathBuf::from(format!("/logs/trace.{thread}.json"));
+                        store.update(&trace_path, checkpoint(offset));
+                        store.flush().expect("concurrent flush");
+                    }
+                });
+            }
+        });
+
+        let reloaded = CheckpointStore::load(dir.path()).expect("reload store");
+        for thread in 0..8u64 {
+            let trace_path = PathBuf::from(format!("/logs/trace.{thread}.json"));
+            assert_eq!(reloaded.get(&trace_path), Some(checkpoint(19)));
+        }
+        let leftovers: Vec<_> = fs::read_dir(dir.path())
+            .expect("read state dir")
+            .map(|entry| entry.expect("state dir entry").file_name())
+            .filter(|name| name != CHECKPOINT_FILE_NAME)
+            .collect();
+        assert!(leftovers.is_empty(), "temporary files left: {leftovers:?}");
+    }
+
+    #[test]
+    fn load_discards_corrupt_file() {
+        let dir = tempdir().expect("tempdir");
+        fs::write(dir.path().join(CHECKPOINT_FILE_NAME), "not json").expect("write corrupt file");
+
+        let store = CheckpointStore::load(dir.path()).expect("corrupt file should not fail load");
+        assert!(store.get(Path::new("/logs/trace.1.json")).is_none());
+    }
+
+    #[test]
+    fn load_creates_missing_state_dir() {
+        let dir = tempdir().expect("tempdir");
+        let state_dir = dir.path().join("nested/state");
+
+        let store = CheckpointStore::load(&state_dir).expect("load should create state dir");
+        store.flush().expect("flush into created dir");
+        assert!(state_dir.join(CHECKPOINT_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn disabled_store_ignores_updates() {
+        let store = CheckpointStore::disabled();
+        assert!(!store.is_enabled());
+        let trace_path = PathBuf::from("/logs/trace.1.json");
+
+        store.update(&trace_path, checkpoint(10));
+        assert!(store.get(&trace_path).is_none());
+        store.flush().expect("flush is a no-op");
+    }
+
+    #[test]
+    fn hash_first_line_is_stable() {
+        assert_eq!(hash_first_line(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(hash_first_line(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_ne!(hash_first_line(b"line one"), hash_first_line(b"line two"));
+    }
+}
@@ -1,4 +1,5 @@
-use anyhow::{anyhow, Context, Result};
+use crate::log_sources::{read_log_sources_file, LogRoot};
+use anyhow::{anyhow, bail, Context, Result};
 use std::{
     env::{self, VarError},
     net::SocketAddr,
@@ -7,20 +8,241 @@ use std::{
 };
 
 pub const LOG_DIR_ENV: &str = "LOG_DIR";
+pub const LOG_SOURCES_FILE_ENV: &str = "LOG_SOURCES_FILE";
 pub const TRACE_LOG_FILE_ENV: &str = "TRACE_LOG_FILE";
 pub const LISTEN_ADDR_ENV: &str = "LISTEN_ADDR";
 pub const LOG_POLL_INTERVAL_ENV: &str = "LOG_POLL_INTERVAL_SECS";
+pub const STATE_DIR_ENV: &str = "STATE_DIR";
+pub const START_MODE_ENV: &str = "START_MODE";
+pub const BACKFILL_SINCE_ENV: &str = "BACKFILL_SINCE";
+pub const BACKFILL_LINES_PER_SEC_ENV: &str = "BACKFILL_LINES_PER_SEC";
+pub const TAILER_IDLE_TIMEOUT_ENV: &str = "TAILER_IDLE_TIMEOUT_SECS";
+pub const WATCH_MODE_ENV: &str = "WATCH_MODE";
+pub const MAX_LINE_BYTES_ENV: &str = "MAX_LINE_BYTES";
+pub const PIPELINE_WORKERS_ENV: &str = "PIPELINE_WORKERS";
+pub const PIPELINE_QUEUE_CAPACITY_ENV: &str = "PIPELINE_QUEUE_CAPACITY";
+pub const PIPELINE_OVERFLOW_ENV: &str = "PIPELINE_OVERFLOW";
+pub const INGEST_TOKEN_ENV: &str = "INGEST_TOKEN";
+pub const INGEST_MAX_BODY_BYTES_ENV: &str = "INGEST_MAX_BODY_BYTES";
 const DEFAULT_LOG_DIR: &str = "logs";
 const DEFAULT_TRACE_LOG_FILE: &str = "logs/tracing.log";
 const DEFAULT_LISTEN_ADDR: &str = "0.0.0.0:9200";
 const DEFAULT_POLL_INTERVAL_SECS: f64 = 2.0;
+const DEFAULT_BACKFILL_LINES_PER_SEC: f64 = 5000.0;
+const DEFAULT_TAILER_IDLE_TIMEOUT_SECS: f64 = 60.0;
+const DEFAULT_MAX_LINE_BYTES: usize = 1024 * 1024;
+const DEFAULT_PIPELINE_QUEUE_CAPACITY: usize = 10_000;
+const DEFAULT_INGEST_MAX_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+const DEFAULT_REPLAY_STEP_SECS: f64 = 1.0;
+const DEFAULT_REPLAY_JOB: &str = "fdb-otel-exporter";
+const REPLAY_USAGE: &str =
+    "usage: fdb-otel-exporter replay <LOG_DIR> [--output FILE] [--step SECONDS] [--job NAME]";
+const DEFAULT_PLAYBACK_SPEED: f64 = 1.0;
+const PLAYBACK_USAGE: &str = "usage: fdb-otel-exporter playback <LOG_DIR> [--speed FACTOR]";
+
+// What the exporter was asked to do on the command line: serve `/metrics` from one source of
+// trace events, or replay historical trace files into a file and exit.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Serve(EventSource),
+    // Convert historical trace files into timestamped OpenMetrics samples.
+    Replay(ReplayOptions),
+}
+
+// Where the trace events behind the served metrics come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum EventSource {
+    // Follow the trace files under the configured log roots.
+    Watch,
+    // Read the given support bundles (archives or gzipped trace files) instead of log roots.
+    Ingest(Vec<PathBuf>),
+    // Feed historical trace files into the live metrics, paced by their `Time` field.
+    Playback(PlaybackOptions),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayOptions {
+    pub input: PathBuf,
+    // Where to write the OpenMetrics text; stdout when unset.
+    pub output: Option<PathBuf>,
+    // Trace-time resolution of the emitted samples, in seconds.
+    pub step: f64,
+    // `job` label added to every sample, matching the scrape job the dashboards query.
+    pub job: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaybackOptions {
+    pub input: PathBuf,
+    // How many seconds of trace time pass per wall-clock second.
+    pub speed: f64,
+}
+
+impl Command {
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Self> {
+        match args.next().as_deref() {
+            None | Some("watch") => Ok(Self::Serve(EventSource::Watch)),
+            Some("ingest") => {
+                let paths: Vec<PathBuf> = args.map(PathBuf::from).collect();
+                if paths.is_empty() {
+                    bail!("usage: fdb-otel-exporter ingest <BUNDLE>...");
+                }
+                Ok(Self::Serve(EventSource::Ingest(paths)))
+            }
+            Some("replay") => parse_replay_args(args).map(Self::Replay),
+            Some("playback") => {
+                parse_playback_args(args).map(|options| Self::Serve(EventSource::Playback(options)))
+            }
+            Some(other) => {
+                bail!("unknown command {other}, expected `watch`, `ingest`, `replay` or `playback`")
+            }
+        }
+    }
+}
+
+fn parse_replay_args(mut args: impl Iterator<Item = String>) -> Result<ReplayOptions> {
+    let mut input = None;
+    let mut output = None;
+    let mut step = DEFAULT_REPLAY_STEP_SECS;
+    let mut job = DEFAULT_REPLAY_JOB.to_string();
+
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .with_context(|| format!("{arg} requires a value"))
+        };
+        match arg.as_str() {
+            "--output" => output = Some(PathBuf::from(value()?)),
+            "--step" => step = parse_positive(&arg, &value()?)?,
+            "--job" => job = value()?,
+            flag if flag.starts_with("--") => bail!("unknown replay option {flag}"),
+            path if input.is_none() => input = Some(PathBuf::from(path)),
+            extra => bail!("unexpected argument {extra}; {REPLAY_USAGE}"),
+        }
+    }
+
+    Ok(ReplayOptions {
+        input: input.context(REPLAY_USAGE)?,
+        output,
+        step,
+        job,
+    })
+}
+
+fn parse_playback_args(mut args: impl Iterator<Item = String>) -> Result<PlaybackOptions> {
+    let mut input = None;
+    let mut speed = DEFAULT_PLAYBACK_SPEED;
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--speed" => {
+                let raw = args
+                    .next()
+                    .with_context(|| format!("{arg} requires a value"))?;
+                speed = parse_positive(&arg, &raw)?;
+            }
+            flag if flag.starts_with("--") => bail!("unknown playback option {flag}"),
+            path if input.is_none() => input = Some(PathBuf::from(path)),
+            extra => bail!("unexpected argument {extra}; {PLAYBACK_USAGE}"),
+        }
+    }
+
+    Ok(PlaybackOptions {
+        input: input.context(PLAYBACK_USAGE)?,
+        speed,
+    })
+}
+
+fn parse_positive(flag: &str, raw: &str) -> Result<f64> {
+    raw.parse::<f64>()
+        .ok()
+        .filter(|value| value.is_finite() && *value > 0.0)
+        .with_context(|| format!("{flag} must be a positive number, got {raw}"))
+}
+
+// How tailers position themselves in trace files that have no saved checkpoint.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StartMode {
+    // Skip existing contents and follow new lines only.
+    Tail,
+    // Ingest existing contents from the start (optionally from a trace `Time` cutoff), throttled,
+    // then keep following new lines.
+    Backfill(BackfillOptions),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BackfillOptions {
+    pub since: Option<f64>,
+    pub max_lines_per_sec: f64,
+}
+
+// How the exporter learns that trace files were created, written or deleted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchMode {
+    // React to inotify notifications, falling back to polling if they are unavailable.
+    Notify,
+    // Rescan the directory and re-read files on a timer only.
+    Poll,
+}
 
 #[derive(Debug, Clone)]
 pub struct AppConfig {
     pub listen_addr: SocketAddr,
-    pub log_dir: PathBuf,
+    pub log_roots: Vec<LogRoot>,
     pub trace_log_file: PathBuf,
     pub log_poll_interval: Duration,
+    pub state_dir: Option<PathBuf>,
+    pub start_mode: StartMode,
+    pub tailer_idle_timeout: Duration,
+    pub watch_mode: WatchMode,
+    pub max_line_bytes: usize,
+    pub pipeline: PipelineOptions,
+    // `POST /ingest` is only served when a token is configured.
+    pub ingest: Option<IngestOptions>,
+}
+
+// Bearer token pushed trace events must present, and the largest request body accepted.
+#[derive(Clone, PartialEq, Eq)]
+pub struct IngestOptions {
+    pub token: String,
+    pub max_body_bytes: usize,
+}
+
+// Keep the token out of logged configuration.
+impl std::fmt::Debug for IngestOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IngestOptions")
+            .field("token", &"<redacted>")
+            .field("max_body_bytes", &self.max_body_bytes)
+            .finish()
+    }
+}
+
+// What a tailer does with a line when its worker's queue is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    // Wait for room, slowing the tailer down.
+    Block,
+    // Discard the line.
+    Drop,
+}
+
+impl OverflowPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Block => "block",
+            Self::Drop => "drop",
+        }
+    }
+}
+
+// Size of the worker pool that parses and records trace lines, and of each worker's queue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PipelineOptions {
+    pub workers: usize,
+    pub queue_capacity: usize,
+    pub overflow: OverflowPolicy,
 }
 
 impl AppConfig {
@@ -33,8 +255,13 @@ impl AppConfig {
             )
         })?;
 
-        let log_dir =
-            PathBuf::from(env::var(LOG_DIR_ENV).unwrap_or_else(|_| DEFAULT_LOG_DIR.to_string()));
+        // A log sources file replaces the single `LOG_DIR` root.
+        let log_roots = match env::var_os(LOG_SOURCES_FILE_ENV).filter(|value| !value.is_empty()) {
+            Some(path) => read_log_sources_file(&PathBuf::from(path))?,
+            None => vec![LogRoot::new(PathBuf::from(
+                env::var(LOG_DIR_ENV).unwrap_or_else(|_| DEFAULT_LOG_DIR.to_string()),
+            ))],
+        };
 
         let trace_log_file = PathBuf::from(
             env::var(TRACE_LOG_FILE_ENV).unwrap_or_else(|_| DEFAULT_TRACE_LOG_FILE.to_string()),
@@ -45,15 +272,150 @@ impl AppConfig {
             DEFAULT_POLL_INTERVAL_SECS,
         )?);
 
+        let state_dir = env::var_os(STATE_DIR_ENV)
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from);
+
+        let start_mode = parse_start_mode()?;
+
+        let tailer_idle_timeout = Duration::from_secs_f64(parse_f64_env(
+            TAILER_IDLE_TIMEOUT_ENV,
+            DEFAULT_TAILER_IDLE_TIMEOUT_SECS,
+        )?);
+
+        let watch_mode = parse_watch_mode()?;
+
+        let max_line_bytes = parse_usize_env(MAX_LINE_BYTES_ENV, DEFAULT_MAX_LINE_BYTES)?;
+        if max_line_bytes == 0 {
+            bail!("environment variable {MAX_LINE_BYTES_ENV} must be greater than zero");
+        }
+
+        let pipeline = parse_pipeline_options()?;
+
+        let ingest = parse_ingest_options()?;
+
         Ok(Self {
             listen_addr,
-            log_dir,
+            log_roots,
             trace_log_file,
             log_poll_interval,
+            state_dir,
+            start_mode,
+            tailer_idle_timeout,
+            watch_mode,
+            max_line_bytes,
+            pipeline,
+            ingest,
         })
     }
 }
 
+fn parse_start_mode() -> Result<StartMode> {
+    let mode = env::var(START_MODE_ENV).unwrap_or_else(|_| String::from("tail"));
+    match mode.to_ascii_lowercase().as_str() {
+        "tail" => Ok(StartMode::Tail),
+        "backfill" => {
+            let since = parse_optional_f64_env(BACKFILL_SINCE_ENV)?;
+            let max_lines_per_sec =
+                parse_f64_env(BACKFILL_LINES_PER_SEC_ENV, DEFAULT_BACKFILL_LINES_PER_SEC)?;
+            if !max_lines_per_sec.is_finite() || max_lines_per_sec <= 0.0 {
+                bail!(
+                    "environment variable {BACKFILL_LINES_PER_SEC_ENV} must be a positive number, got {max_lines_per_sec}"
+                );
+            }
+            Ok(StartMode::Backfill(BackfillOptions {
+                since,
+                max_lines_per_sec,
+            }))
+        }
+        _ => bail!(
+            "environment variable {START_MODE_ENV} expected to be `tail` or `backfill`, got {mode}"
+        ),
+    }
+}
+
+fn parse_watch_mode() -> Result<WatchMode> {
+    let mode = env::var(WATCH_MODE_ENV).unwrap_or_else(|_| String::from("inotify"));
+    match mode.to_ascii_lowercase().as_str() {
+        "inotify" => Ok(WatchMode::Notify),
+        "poll" => Ok(WatchMode::Poll),
+        _ => bail!(
+            "environment variable {WATCH_MODE_ENV} expected to be `inotify` or `poll`, got {mode}"
+        ),
+    }
+}
+
+fn parse_pipeline_options() -> Result<PipelineOptions> {
+    let default_workers = std::thread::available_parallelism()
+        .map(|workers| workers.get())
+        .unwrap_or(1);
+    let workers = parse_usize_env(PIPELINE_WORKERS_ENV, default_workers)?;
+    let queue_capacity =
+        parse_usize_env(PIPELINE_QUEUE_CAPACITY_ENV, DEFAULT_PIPELINE_QUEUE_CAPACITY)?;
+    for (key, value) in [
+        (PIPELINE_WORKERS_ENV, workers),
+        (PIPELINE_QUEUE_CAPACITY_ENV, queue_capacity),
+    ] {
+        if value == 0 {
+            bail!("environment variable {key} must be greater than zero");
+        }
+    }
+
+    let overflow = env::var(PIPELINE_OVERFLOW_ENV).unwrap_or_else(|_| String::from("block"));
+    let overflow = match overflow.to_ascii_lowercase().as_str() {
+        "block" => OverflowPolicy::Block,
+        "drop" => OverflowPolicy::Drop,
+        _ => bail!(
+            "environment variable {PIPELINE_OVERFLOW_ENV} expected to be `block` or `drop`, got {overflow}"
+        ),
+    };
+
+    Ok(PipelineOptions {
+        workers,
+        queue_capacity,
+        overflow,
+    })
+}
+
+fn parse_ingest_options() -> Result<Option<IngestOptions>> {
+    let Some(token) = env::var(INGEST_TOKEN_ENV)
+        .ok()
+        .filter(|token| !token.is_empty())
+    else {
+        return Ok(None);
+    };
+    let max_body_bytes = parse_usize_env(INGEST_MAX_BODY_BYTES_ENV, DEFAULT_INGEST_MAX_BODY_BYTES)?;
+    if max_body_bytes == 0 {
+        bail!("environment variable {INGEST_MAX_BODY_BYTES_ENV} must be greater than zero");
+    }
+    Ok(Some(IngestOptions {
+        token,
+        max_body_bytes,
+    }))
+}
+
+fn parse_optional_f64_env(key: &str) -> Result<Option<f64>> {
+    match env::var(key) {
+        Ok(_) => parse_f64_env(key, 0.0).map(Some),
+        Err(VarError::NotPresent) => Ok(None),
+        Err(VarError::NotUnicode(_)) => {
+            Err(anyhow!("environment variable {key} must be valid UTF-8"))
+        }
+    }
+}
+
+fn parse_usize_env(key: &str, default: usize) -> Result<usize> {
+    match env::var(key) {
+        Ok(value) => value.parse::<usize>().with_context(|| {
+            format!("environment variable {key} expected to be a whole number, got {value}")
+        }),
+        Err(VarError::NotPresent) => Ok(default),
+        Err(VarError::NotUnicode(_)) => {
+            Err(anyhow!("environment variable {key} must be valid UTF-8"))
+        }
+    }
+}
+
 fn parse_f64_env(key: &str, default: f64) -> Result<f64> {
     match env::var(key) {
         Ok(value) => value.parse::<f64>().with_context(|| {
@@ -113,15 +475,55 @@ mod tests {
             &[
                 (LISTEN_ADDR_ENV, Some("127.0.0.1:1234")),
                 (LOG_DIR_ENV, Some("/tmp/fdb")),
+                (LOG_SOURCES_FILE_ENV, None),
                 (TRACE_LOG_FILE_ENV, Some("/tmp/tracing.log")),
                 (LOG_POLL_INTERVAL_ENV, Some("5")),
+                (STATE_DIR_ENV, Some("/tmp/fdb-state")),
+                (START_MODE_ENV, Some("backfill")),
+                (BACKFILL_SINCE_ENV, Some("1700000000.5")),
+                (BACKFILL_LINES_PER_SEC_ENV, Some("250")),
+                (TAILER_IDLE_TIMEOUT_ENV, Some("30")),
+                (WATCH_MODE_ENV, Some("poll")),
+                (MAX_LINE_BYTES_ENV, Some("4096")),
+                (PIPELINE_WORKERS_ENV, Some("3")),
+                (PIPELINE_QUEUE_CAPACITY_ENV, Some("100")),
+                (PIPELINE_OVERFLOW_ENV, Some("drop")),
+                (INGEST_TOKEN_ENV, Some("push-secret")),
+                (INGEST_MAX_BODY_BYTES_ENV, Some("2048")),
             ],
             || {
                 let config = AppConfig::from_env().expect("config should load with overrides");
                 assert_eq!(config.listen_addr, "127.0.0.1:1234".parse().unwrap());
-                assert_eq!(config.log_dir, PathBuf::from("/tmp/fdb"));
+                assert_eq!(config.log_roots.len(), 1);
+                assert_eq!(config.log_roots[0].path, PathBuf::from("/tmp/fdb"));
                 assert_eq!(config.trace_log_file, PathBuf::from("/tmp/tracing.log"));
                 assert_eq!(config.log_poll_interval, Duration::from_secs_f64(5.0));
+                assert_eq!(config.state_dir, Some(PathBuf::from("/tmp/fdb-state")));
+                assert_eq!(
+                    config.start_mode,
+                    StartMode::Backfill(BackfillOptions {
+                        since: Some(1_700_000_000.5),
+                        max_lines_per_sec: 250.0,
+                    })
+                );
+                assert_eq!(config.tailer_idle_timeout, Duration::from_secs(30));
+                assert_eq!(config.watch_mode, WatchMode::Poll);
+                assert_eq!(config.max_line_bytes, 4096);
+                assert_eq!(
+                    config.pipeline,
+                    PipelineOptions {
+                        workers: 3,
+                        queue_capacity: 100,
+                        overflow: OverflowPolicy::Drop,
+                    }
+                );
+                assert_eq!(
+                    config.ingest,
+                    Some(IngestOptions {
+                        token: "push-secret".to_string(),
+                        max_body_bytes: 2048,
+                    })
+                );
             },
         );
     }
@@ -132,18 +534,211 @@ mod tests {
             &[
                 (LISTEN_ADDR_ENV, None),
                 (LOG_DIR_ENV, None),
+                (LOG_SOURCES_FILE_ENV, None),
                 (TRACE_LOG_FILE_ENV, None),
                 (LOG_POLL_INTERVAL_ENV, None),
+                (STATE_DIR_ENV, None),
+                (START_MODE_ENV, None),
+                (BACKFILL_SINCE_ENV, None),
+                (BACKFILL_LINES_PER_SEC_ENV, None),
+                (TAILER_IDLE_TIMEOUT_ENV, None),
+                (WATCH_MODE_ENV, None),
+                (MAX_LINE_BYTES_ENV, None),
+                (PIPELINE_WORKERS_ENV, None),
+                (PIPELINE_QUEUE_CAPACITY_ENV, None),
+                (PIPELINE_OVERFLOW_ENV, None),
+                (INGEST_TOKEN_ENV, None),
+                (INGEST_MAX_BODY_BYTES_ENV, None),
             ],
             || {
                 let config = AppConfig::from_env().expect("config should load with defaults");
                 assert_eq!(config.listen_addr, DEFAULT_LISTEN_ADDR.parse().unwrap());
-                assert_eq!(config.log_dir, PathBuf::from(DEFAULT_LOG_DIR));
+                assert_eq!(config.log_roots.len(), 1);
+                assert_eq!(config.log_roots[0].path, PathBuf::from(DEFAULT_LOG_DIR));
+                assert!(!config.log_roots[0].recursive);
                 assert_eq!(config.trace_log_file, PathBuf::from(DEFAULT_TRACE_LOG_FILE));
                 assert_eq!(
                     config.log_poll_interval,
                     Duration::from_secs_f64(DEFAULT_POLL_INTERVAL_SECS)
                 );
+                assert_eq!(config.state_dir, None);
+                assert_eq!(config.start_mode, StartMode::Tail);
+                assert_eq!(
+                    config.tailer_idle_timeout,
+                    Duration::from_secs_f64(DEFAULT_TAILER_IDLE_TIMEOUT_SECS)
+                );
+                assert_eq!(config.watch_mode, WatchMode::Notify);
+                assert_eq!(config.max_line_bytes, DEFAULT_MAX_LINE_BYTES);
+                assert!(config.pipeline.workers >= 1);
+                assert_eq!(
+                    config.pipeline.queue_capacity,
+                    DEFAULT_PIPELINE_QUEUE_CAPACITY
+                );
+                assert_eq!(config.pipeline.overflow, OverflowPolicy::Block);
+                assert_eq!(config.ingest, None);
+            },
+        );
+    }
+
+    #[test]
+    fn backfill_mode_uses_default_rate_without_cutoff() {
+        with_env(
+            &[
+                (START_MODE_ENV, Some("Backfill")),
+                (BACKFILL_SINCE_ENV, None),
+                (BACKFILL_LINES_PER_SEC_ENV, None),
+            ],
+            || {
+                let mode = parse_start_mode().expect("backfill mode should parse");
+                assert_eq!(
+                    mode,
+                    StartMode::Backfill(BackfillOptions {
+                        since: None,
+                        max_lines_per_sec: DEFAULT_BACKFILL_LINES_PER_SEC,
+                    })
+                );
+            },
+        );
+    }
+
+    #[test]
+    fn start_mode_rejects_unknown_values() {
+        with_env(&[(START_MODE_ENV, Some("rewind"))], || {
+            let error = parse_start_mode().expect_err("unknown start mode should fail");
+            assert!(
+                error
+                    .to_string()
+                    .contains("expected to be `tail` or `backfill`"),
+                "unexpected error message: {error}"
+            );
+        });
+    }
+
+    #[test]
+    fn log_sources_file_replaces_log_dir() {
+        let dir = tempfile::tempdir().expect("tempdir");
+        let sources_path = dir.path().join("log_sources.toml");
+        std::fs::write(
+            &sources_path,
+            "[[log_root]]\npath = \"/fdb/4500\"\n\n[[log_root]]\npath = \"/fdb/backup\"\nrecursive = true\n",
+        )
+        .expect("write log sources");
+
+        with_env(
+            &[
+                (LOG_DIR_ENV, Some("/tmp/ignored")),
+                (LOG_SOURCES_FILE_ENV, sources_path.to_str()),
+            ],
+            || {
+                let config = AppConfig::from_env().expect("config should load log sources");
+                let paths: Vec<_> = config.log_roots.iter().map(|root| &root.path).collect();
+                assert_eq!(
+                    paths,
+                    [&PathBuf::from("/fdb/4500"), &PathBuf::from("/fdb/backup")]
+                );
+                assert!(config.log_roots[1].recursive);
+            },
+        );
+    }
+
+    #[test]
+    fn command_parses_subcommands() {
+        let args = |values: &[&str]| {
+            values
+                .iter()
+                .map(|value| value.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            Command::from_args(args(&[]).into_iter()).unwrap(),
+            Command::Serve(EventSource::Watch)
+        );
+        assert_eq!(
+            Command::from_args(args(&["ingest", "a.tar.gz", "trace.1.json.gz"]).into_iter())
+                .unwrap(),
+            Command::Serve(EventSource::Ingest(vec![
+                PathBuf::from("a.tar.gz"),
+                PathBuf::from("trace.1.json.gz")
+            ]))
+        );
+        assert!(Command::from_args(args(&["ingest"]).into_iter()).is_err());
+        assert_eq!(
+            Command::from_args(args(&["replay", "/incident"]).into_iter()).unwrap(),
+            Command::Replay(ReplayOptions {
+                input: PathBuf::from("/incident"),
+                output: None,
+                step: DEFAULT_REPLAY_STEP_SECS,
+                job: DEFAULT_REPLAY_JOB.to_string(),
+            })
+        );
+        assert_eq!(
+            Command::from_args(
+                args(&[
+                    "replay",
+                    "/incident",
+                    "--output",
+                    "out.om",
+                    "--step",
+                    "15",
+                    "--job",
+                    "old"
+                ])
+                .into_iter()
+            )
+            .unwrap(),
+            Command::Replay(ReplayOptions {
+                input: PathBuf::from("/incident"),
+                output: Some(PathBuf::from("out.om")),
+                step: 15.0,
+                job: "old".to_string(),
+            })
+        );
+        assert!(Command::from_args(args(&["replay"]).into_iter()).is_err());
+        assert!(
+            Command::from_args(args(&["replay", "/incident", "--step", "0"]).into_iter()).is_err()
+        );
+        assert_eq!(
+            Command::from_args(args(&["playback", "/incident", "--speed", "10"]).into_iter())
+                .unwrap(),
+            Command::Serve(EventSource::Playback(PlaybackOptions {
+                input: PathBuf::from("/incident"),
+                speed: 10.0,
+            }))
+        );
+        assert!(
+            Command::from_args(args(&["playback", "/incident", "--speed", "-1"]).into_iter())
+                .is_err()
+        );
+        assert!(Command::from_args(args(&["rewind"]).into_iter()).is_err());
+    }
+
+    #[test]
+    fn watch_mode_rejects_unknown_values() {
+        with_env(&[(WATCH_MODE_ENV, Some("fanotify"))], || {
+            let error = parse_watch_mode().expect_err("unknown watch mode should fail");
+            assert!(
+                error
+                    .to_string()
+                    .contains("expected to be `inotify` or `poll`"),
+                "unexpected error message: {error}"
+            );
+        });
+    }
+
+    #[test]
+    fn backfill_mode_rejects_non_positive_rate() {
+        with_env(
+            &[
+                (START_MODE_ENV, Some("backfill")),
+                (BACKFILL_LINES_PER_SEC_ENV, Some("0")),
+            ],
+            || {
+                let error = parse_start_mode().expect_err("zero rate should fail");
+                assert!(
+                    error.to_string().contains("must be a positive number"),
+                    "unexpected error message: {error}"
+                );
             },
         );
     }
@@ -1,12 +1,41 @@
-use opentelemetry::metrics::{Counter, Meter};
+use crate::watch_logs::unix_now;
+use opentelemetry::metrics::{Counter, Gauge, Histogram, Meter};
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::metrics::{new_view, Aggregation, Instrument, Stream, View};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+
+const EVENT_LATENCY_NAME: &str = "fdb_exporter_event_latency_seconds";
+
+// Bucket boundaries for the event latency histogram. The SDK defaults are sized for milliseconds,
+// while trace lines normally reach the gauges well under a second after being written.
+const EVENT_LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
 
 #[derive(Clone)]
 pub struct ExporterMetrics {
     processed_events: Counter<u64>,
     parse_errors: Counter<u64>,
     record_errors: Counter<u64>,
+    file_rotations: Counter<u64>,
+    tailers_retired: Counter<u64>,
+    active_tailers: Gauge<u64>,
+    event_latency: Histogram<f64>,
+    partial_lines_dropped: Counter<u64>,
+    pipeline_overflows: Counter<u64>,
+    pipeline_dropped_lines: Counter<u64>,
+    files: FileRegistry,
 }
 
+type FileRegistry = Arc<Mutex<HashMap<PathBuf, Arc<FileProgress>>>>;
+
 impl ExporterMetrics {
     pub fn new(meter: &Meter) -> Self {
         let processed_events = meter
@@ -16,7 +45,7 @@ impl ExporterMetrics {
 
         let parse_errors = meter
             .u64_counter("fdb_exporter_parse_errors_total")
-            .with_description("Number of FoundationDB log lines that failed JSON parsing")
+            .with_description("Number of FoundationDB log lines that failed JSON or XML parsing")
             .init();
 
         let record_errors = meter
@@ -24,10 +53,64 @@ impl ExporterMetrics {
             .with_description("Number of FoundationDB log events that failed metric recording")
             .init();
 
+        let file_rotations = meter
+            .u64_counter("fdb_exporter_file_rotations_total")
+            .with_description("Number of trace files reopened after truncation or replacement")
+            .init();
+
+        let tailers_retired = meter
+            .u64_counter("fdb_exporter_tailers_retired_total")
+            .with_description(
+                "Number of trace file tailers stopped because their file was deleted or superseded",
+            )
+            .init();
+
+        let active_tailers = meter
+            .u64_gauge("fdb_exporter_active_tailers")
+            .with_description("Number of trace files currently being tailed under each log root")
+            .init();
+
+        let event_latency = meter
+            .f64_histogram(EVENT_LATENCY_NAME)
+            .with_description(
+                "Seconds between a trace event's Time and the exporter recording it into metrics",
+            )
+            .init();
+
+        let partial_lines_dropped = meter
+            .u64_counter("fdb_exporter_partial_lines_dropped_total")
+            .with_description(
+                "Number of trace lines dropped for exceeding the maximum line length or being left unfinished when their file rotated",
+            )
+            .init();
+
+        let pipeline_overflows = meter
+            .u64_counter("fdb_exporter_pipeline_overflows_total")
+            .with_description(
+                "Number of trace lines that found their worker queue full, by overflow policy",
+            )
+            .init();
+
+        let pipeline_dropped_lines = meter
+            .u64_counter("fdb_exporter_pipeline_dropped_lines_total")
+            .with_description("Number of trace lines discarded because their worker queue was full")
+            .init();
+
+        let files = FileRegistry::default();
+        register_file_metrics(meter, &files);
+
         Self {
             processed_events,
             parse_errors,
             record_errors,
+            file_rotations,
+            tailers_retired,
+            active_tailers,
+            event_latency,
+            partial_lines_dropped,
+            pipeline_overflows,
+            pipeline_dropped_lines,
+            files,
         }
     }
 
@@ -42,11 +125,258 @@ impl ExporterMetrics {
     pub fn record_record_error(&self) {
         self.record_errors.add(1, &[]);
     }
+
+    pub fn record_file_rotation(&self, reason: &'static str) {
+        self.file_rotations
+            .add(1, &[KeyValue::new("reason", reason)]);
+    }
+
+    pub fn record_tailer_retired(&self, reason: &'static str) {
+        self.tailers_retired
+            .add(1, &[KeyValue::new("reason", reason)]);
+    }
+
+    pub fn record_partial_line_dropped(&self, reason: &'static str) {
+        self.partial_lines_dropped
+            .add(1, &[KeyValue::new("reason", reason)]);
+    }
+
+    pub fn record_pipeline_overflow(&self, policy: &'static str) {
+        self.pipeline_overflows
+            .add(1, &[KeyValue::new("policy", policy)]);
+    }
+
+    pub fn record_pipeline_dropped(&self) {
+        self.pipeline_dropped_lines.add(1, &[]);
+    }
+
+    pub fn record_active_tailers(&self, root: &Path, count: u64) {
+        self.active_tailers
+            .record(count, &[KeyValue::new("root", root.display().to_string())]);
+    }
+
+    pub fn record_event_latency(&self, seconds: f64) {
+        self.event_latency.record(seconds.max(0.0), &[]);
+    }
+
+    // Start reporting progress for a tailed file written by `process`, replacing any earlier
+    // tailer's numbers for the same path.
+    pub fn track_file(&self, path: &Path, process: &str) -> Arc<FileProgress> {
+        let progress = Arc::new(FileProgress::new(path, process));
+        self.files
+            .lock()
+            .expect("file metrics poisoned")
+            .insert(path.to_path_buf(), Arc::clone(&progress));
+        progress
+    }
+
+    pub fn forget_file(&self, path: &Path) {
+        self.files
+            .lock()
+            .expect("file metrics poisoned")
+            .remove(path);
+    }
+}
+
+// Read progress of one tailed trace file. Its tailer and worker update it as lines are read and
+// recorded, and it is observed whenever metrics are collected, so a file that stops producing
+// events keeps reporting its growing lag instead of disappearing.
+pub struct FileProgress {
+    labels: [KeyValue; 2],
+    bytes_read: AtomicU64,
+    lines_read: AtomicU64,
+    offset: AtomicU64,
+    len: AtomicU64,
+    // `f64` bits of the last recorded event's `Time`, NaN until there is one.
+    last_event_time: AtomicU64,
+    // How far the backfill of the file's existing content has got, and where that content ends;
+    // the target is `u64::MAX` until a backfill starts.
+    backfill_offset: AtomicU64,
+    backfill_target: AtomicU64,
+}
+
+impl FileProgress {
+    fn new(path: &Path, process: &str) -> Self {
+        Self {
+            labels: [
+                KeyValue::new("file", path.display().to_string()),
+                KeyValue::new("process", process.to_string()),
+            ],
+            bytes_read: AtomicU64::new(0),
+            lines_read: AtomicU64::new(0),
+            offset: AtomicU64::new(0),
+            len: AtomicU64::new(0),
+            last_event_time: AtomicU64::new(f64::NAN.to_bits()),
+            backfill_offset: AtomicU64::new(0),
+            backfill_target: AtomicU64::new(u64::MAX),
+        }
+    }
+
+    // Position of the reader and size of the file it is reading.
+    pub fn record_position(&self, offset: u64, len: u64) {
+        self.offset.store(offset, Ordering::Relaxed);
+        self.len.store(len.max(offset), Ordering::Relaxed);
+    }
+
+    pub fn record_read(&self, bytes: u64, offset: u64) {
+        self.bytes_read.fetch_add(bytes, Ordering::Relaxed);
+        self.offset.store(offset, Ordering::Relaxed);
+        self.len.fetch_max(offset, Ordering::Relaxed);
+    }
+
+    pub fn record_line(&self) {
+        self.lines_read.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_event_time(&self, time: f64) {
+        self.last_event_time
+            .store(time.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn record_backfill(&self, offset: u64, target: u64) {
+        self.backfill_offset.store(offset, Ordering::Relaxed);
+        self.backfill_target.store(target, Ordering::Relaxed);
+    }
+
+    // Bytes left to backfill and the fraction already backfilled, once a backfill has started.
+    fn backfill(&self) -> Option<(u64, f64)> {
+        let target = self.backfill_target.load(Ordering::Relaxed);
+        if target == u64::MAX {
+            return None;
+        }
+        let offset = self.backfill_offset.load(Ordering::Relaxed);
+        let progress = if target == 0 {
+            1.0
+        } else {
+            (offset as f64 / target as f64).min(1.0)
+        };
+        Some((target.saturating_sub(offset), progress))
+    }
+
+    fn unread_bytes(&self) -> u64 {
+        self.len
+            .load(Ordering::Relaxed)
+            .saturating_sub(self.offset.load(Ordering::Relaxed))
+    }
+
+    fn last_event_time(&self) -> Option<f64> {
+        let time = f64::from_bits(self.last_event_time.load(Ordering::Relaxed));
+        (!time.is_nan()).then_some(time)
+    }
+}
+
+fn register_file_metrics(meter: &Meter, files: &FileRegistry) {
+    // Run `observe` for every tracked file when metrics are collected.
+    fn for_each_file(files: &FileRegistry, mut observe: impl FnMut(&FileProgress)) {
+        for progress in files.lock().expect("file metrics poisoned").values() {
+            observe(progress);
+        }
+    }
+
+    let tracked = Arc::clone(files);
+    meter
+        .u64_observable_counter("fdb_exporter_file_bytes_read")
+        .with_description("Bytes read from each tailed trace file")
+        .with_callback(move |observer| {
+            for_each_file(&tracked, |file| {
+                observer.observe(file.bytes_read.load(Ordering::Relaxed), &file.labels)
+            })
+        })
+        .init();
+
+    let tracked = Arc::clone(files);
+    meter
+        .u64_observable_counter("fdb_exporter_file_lines_read")
+        .with_description("Complete lines read from each tailed trace file")
+        .with_callback(move |observer| {
+            for_each_file(&tracked, |file| {
+                observer.observe(file.lines_read.load(Ordering::Relaxed), &file.labels)
+            })
+        })
+        .init();
+
+    let tracked = Arc::clone(files);
+    meter
+        .u64_observable_gauge("fdb_exporter_file_unread_bytes")
+        .with_description("Bytes between the tailer's position and the end of each trace file")
+        .with_callback(move |observer| {
+            for_each_file(&tracked, |file| {
+                observer.observe(file.unread_bytes(), &file.labels)
+            })
+        })
+        .init();
+
+    let tracked = Arc::clone(files);
+    meter
+        .f64_observable_gauge("fdb_exporter_file_last_event_time_seconds")
+        .with_description("Time field of the last event recorded from each trace file")
+        .with_callback(move |observer| {
+            for_each_file(&tracked, |file| {
+                if let Some(time) = file.last_event_time() {
+                    observer.observe(time, &file.labels);
+                }
+            })
+        })
+        .init();
+
+    let tracked = Arc::clone(files);
+    meter
+        .u64_observable_gauge("fdb_exporter_backfill_remaining_bytes")
+        .with_description("Bytes of existing trace file content still to be backfilled")
+        .with_callback(move |observer| {
+            for_each_file(&tracked, |file| {
+                if let Some((remaining, _)) = file.backfill() {
+                    observer.observe(remaining, &file.labels);
+                }
+            })
+        })
+        .init();
+
+    let tracked = Arc::clone(files);
+    meter
+        .f64_observable_gauge("fdb_exporter_backfill_progress_ratio")
+        .with_description("Fraction of existing trace file content already backfilled")
+        .with_callback(move |observer| {
+            for_each_file(&tracked, |file| {
+                if let Some((_, progress)) = file.backfill() {
+                    observer.observe(progress, &file.labels);
+                }
+            })
+        })
+        .init();
+
+    let tracked = Arc::clone(files);
+    meter
+        .f64_observable_gauge("fdb_exporter_file_lag_seconds")
+        .with_description(
+            "Seconds between the wall clock and the Time of the last event recorded from each trace file",
+        )
+        .with_callback(move |observer| {
+            let now = unix_now();
+            for_each_file(&tracked, |file| {
+                if let Some(time) = file.last_event_time() {
+                    observer.observe((now - time).max(0.0), &file.labels);
+                }
+            })
+        })
+        .init();
+}
+
+// View giving the event latency histogram buckets in seconds; register it on the meter provider.
+pub fn event_latency_view() -> opentelemetry::metrics::Result<Box<dyn View>> {
+    new_view(
+        Instrument::new().name(EVENT_LATENCY_NAME),
+        Stream::new().aggregation(Aggregation::ExplicitBucketHistogram {
+            boundaries: EVENT_LATENCY_BUCKETS.to_vec(),
+            record_min_max: true,
+        }),
+    )
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_helpers::metrics::{find_metric_in, prometheus_meter};
     use opentelemetry::metrics::MeterProvider;
     use opentelemetry_sdk::metrics::{ManualReader, SdkMeterProvider};
 
@@ -64,5 +394,75 @@ mod tests {
         metrics.record_processed();
         metrics.record_parse_error();
         metrics.record_record_error();
+        metrics.record_file_rotation("truncated");
+        metrics.record_tailer_retired("deleted");
+        metrics.record_active_tailers(Path::new("/logs"), 3);
+        metrics.record_event_latency(0.2);
+    }
+
+    #[test]
+    fn active_tailers_are_reported_per_root() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let metrics = ExporterMetrics::new(&meter);
+
+        metrics.record_active_tailers(Path::new("/logs/a"), 3);
+        metrics.record_active_tailers(Path::new("/logs/b"), 1);
+
+        let families = registry.gather();
+        let active = |root: &str| {
+            find_metric_in(&families, "fdb_exporter_active_tailers", "root", root)
+                .expect("active tailers per root")
+                .get_gauge()
+                .get_value()
+        };
+        assert_eq!(active("/logs/a"), 3.0);
+        assert_eq!(active("/logs/b"), 1.0);
+    }
+
+    #[test]
+    fn file_progress_reports_unread_bytes_and_event_time() {
+        let meter = test_meter();
+        let metrics = ExporterMetrics::new(&meter);
+        let progress = metrics.track_file(Path::new("/logs/trace.1.json"), "10.0.0.1:4500");
+
+        progress.record_position(0, 100);
+        progress.record_read(40, 40);
+        progress.record_line();
+        assert_eq!(progress.unread_bytes(), 60);
+        assert_eq!(progress.last_event_time(), None);
+
+        progress.record_read(80, 120);
+        progress.record_event_time(1_700_000_000.5);
+        assert_eq!(progress.unread_bytes(), 0);
+        assert_eq!(progress.bytes_read.load(Ordering::Relaxed), 120);
+        assert_eq!(progress.last_event_time(), Some(1_700_000_000.5));
+
+        metrics.forget_file(Path::new("/logs/trace.1.json"));
+        assert!(metrics.files.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn backfill_progress_is_retired_with_its_file() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let metrics = ExporterMetrics::new(&meter);
+        let path = Path::new("/logs/trace.1.json");
+        let progress = metrics.track_file(path, "10.0.0.1:4500");
+
+        let backfill = |name: &str| {
+            find_metric_in(&registry.gather(), name, "file", "/logs/trace.1.json")
+                .map(|metric| metric.get_gauge().get_value())
+        };
+        assert_eq!(backfill("fdb_exporter_backfill_progress_ratio"), None);
+
+        progress.record_backfill(10, 40);
+        assert_eq!(backfill("fdb_exporter_backfill_progress_ratio"), Some(0.25));
+        assert_eq!(
+            backfill("fdb_exporter_backfill_remaining_bytes"),
+            Some(30.0)
+        );
+
+        metrics.forget_file(path);
+        assert_eq!(backfill("fdb_exporter_backfill_progress_ratio"), None);
+        assert_eq!(backfill("fdb_exporter_backfill_remaining_bytes"), None);
     }
 }
@@ -1,65 +1,323 @@
+use crate::fdb_gauge::LabelKey;
 use crate::fdb_metric::FDBMetric;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use opentelemetry::metrics::{Counter, Meter};
 use opentelemetry::KeyValue;
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{hash_map::Entry, HashMap};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Comparison {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+// Longer operators come first so `>=` is not read as `>` followed by `=`.
+const COMPARISONS: [(&str, Comparison); 6] = [
+    (">=", Comparison::Ge),
+    ("<=", Comparison::Le),
+    ("==", Comparison::Eq),
+    ("!=", Comparison::Ne),
+    (">", Comparison::Gt),
+    ("<", Comparison::Lt),
+];
+
+// A condition on one trace event field, written as `<Field> <op> <value>` such as `Severity >= 30`
+// or `Duration > 0.5`. Ordering comparisons are numeric; `==` and `!=` compare numerically when
+// both sides are numbers and as strings otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldPredicate {
+    field: String,
+    comparison: Comparison,
+    value: String,
+    number: Option<f64>,
+}
+
+impl FieldPredicate {
+    pub fn parse(predicate: &str) -> Result<Self> {
+        let (field, comparison, value) = COMPARISONS
+            .iter()
+            .find_map(|(symbol, comparison)| {
+                predicate
+                    .split_once(symbol)
+                    .map(|(field, value)| (field.trim(), *comparison, value.trim()))
+            })
+            .with_context(|| format!("predicate `{predicate}` has no comparison operator"))?;
+        if field.is_empty() || value.is_empty() {
+            bail!("predicate `{predicate}` must be written as `<Field> <op> <value>`");
+        }
+
+        let value = value.trim_matches('"').to_string();
+        let number = value.parse::<f64>().ok();
+        if number.is_none() && !matches!(comparison, Comparison::Eq | Comparison::Ne) {
+            bail!("predicate `{predicate}` compares against a non-numeric value");
+        }
+
+        Ok(Self {
+            field: field.to_string(),
+            comparison,
+            value,
+            number,
+        })
+    }
+
+    // An event without the field does not match, whatever the comparison, since events of one
+    // type often omit fields that only apply to some of them.
+    fn matches(&self, trace_event: &HashMap<String, Value>) -> Result<bool> {
+        let Some(actual) = trace_event.get(&self.field) else {
+            return Ok(false);
+        };
+        let actual = actual
+            .as_str()
+            .with_context(|| format!("Invalid {} field", self.field))?;
+
+        let Some(expected) = self.number else {
+            let equal = actual == self.value;
+            return Ok(equal == (self.comparison == Comparison::Eq));
+        };
+        let actual = match actual.parse::<f64>() {
+            Ok(actual) => actual,
+            Err(_) if self.comparison == Comparison::Eq => return Ok(false),
+            Err(_) if self.comparison == Comparison::Ne => return Ok(true),
+            Err(_) => bail!("Invalid {} field", self.field),
+        };
+
+        Ok(match self.comparison {
+            Comparison::Eq => actual == expected,
+            Comparison::Ne => actual != expected,
+            Comparison::Gt => actual > expected,
+            Comparison::Ge => actual >= expected,
+            Comparison::Lt => actual < expected,
+            Comparison::Le => actual <= expected,
+        })
+    }
+}
 
-#[derive(Clone)]
-pub struct SevCounter {
-    severity: u64,
+// Count trace events of `trace_type` (any type when unset) that satisfy every predicate.
+pub struct EventCounter {
+    trace_type: Option<String>,
+    predicates: Vec<FieldPredicate>,
     counter: Counter<u64>,
 }
 
-impl SevCounter {
-    pub fn new(severity: u64, meter: &Meter) -> Self {
+impl EventCounter {
+    pub fn new(
+        trace_type: Option<String>,
+        predicates: Vec<FieldPredicate>,
+        counter_name: impl Into<String>,
+        description: impl Into<String>,
+        meter: &Meter,
+    ) -> Self {
         Self {
-            severity,
+            trace_type,
+            predicates,
             counter: meter
-                .u64_counter(format!("process_sev{severity}_counter"))
-                .with_description(format!("Counter of severity {severity} trace events"))
+                .u64_counter(counter_name.into())
+                .with_description(description.into())
                 .init(),
         }
     }
 }
 
-impl FDBMetric for SevCounter {
+impl FDBMetric for EventCounter {
     fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
-        if trace_event
-            .get("Severity")
-            .with_context(|| "Missing Severity field")?
-            .as_str()
-            .with_context(|| "Invalid Severity field")?
-            .parse::<u64>()
-            .with_context(|| "Invalid Severity field")?
-            == self.severity
-        {
-            self.counter.add(1, labels);
+        if let Some(expected_type) = &self.trace_type {
+            let trace_type = trace_event
+                .get("Type")
+                .with_context(|| "Missing Type field")?
+                .as_str()
+                .with_context(|| "Invalid Type field")?;
+            if trace_type != expected_type {
+                return Ok(());
+            }
+        }
+
+        for predicate in &self.predicates {
+            if !predicate.matches(trace_event)? {
+                return Ok(());
+            }
         }
+
+        self.counter.add(1, labels);
         Ok(())
     }
 }
 
-pub struct SlowTaskCounter {
-    threshold_ms: u64,
-    counter: Counter<u64>,
+#[derive(Clone, Copy)]
+struct CounterSeries {
+    last_total: f64,
+    last_time: f64,
+}
+
+// Running totals last seen per label set, used to turn FDB counter totals into increments. Events
+// of one process can arrive out of order (a rotated file and its successor are read side by side),
+// so totals older than the last one seen are dropped. A total from after the process logged a new
+// `ProgramStart`, or a lower total logged later, means the process restarted and its counter began
+// again from zero, so the whole new total is new. Totals are followed per role instance `ID`, so
+// instances sharing a label set each count from their own last total, and a series that gains an
+// `ID` label once instances collide continues from it instead of adding the whole total again.
+#[derive(Default)]
+struct CounterResets {
+    series: HashMap<LabelKey, CounterSeries>,
+    // Trace time of the latest `ProgramStart` seen per `machine` label.
+    restarts: HashMap<String, f64>,
+}
+
+impl CounterResets {
+    fn program_start(&mut self, labels: &[KeyValue], time: f64) {
+        if let Some(machine) = machine_label(labels) {
+            let restart = self.restarts.entry(machine).or_insert(time);
+            *restart = restart.max(time);
+        }
+    }
+
+    // The increase since the previous total of `instance` for `labels`, or all of `total` for a new
+    // series.
+    fn increment(
+        &mut self,
+        labels: &[KeyValue],
+        instance: Option<&str>,
+        time: f64,
+        total: f64,
+    ) -> f64 {
+        let restart =
+            machine_label(labels).and_then(|machine| self.restarts.get(&machine).copied());
+        let current = CounterSeries {
+            last_total: total,
+            last_time: time,
+        };
+        let mut key: Vec<KeyValue> = labels
+            .iter()
+            .filter(|label| label.key.as_str() != "ID")
+            .cloned()
+            .collect();
+        key.extend(instance.map(|id| KeyValue::new("ID", id.to_string())));
+        let previous = match self.series.entry(LabelKey::from_labels(&key)) {
+            Entry::Vacant(entry) => {
+                entry.insert(current);
+                return total;
+            }
+            Entry::Occupied(mut entry) => {
+                let previous = *entry.get();
+                if time < previous.last_time {
+                    return 0.0;
+                }
+                entry.insert(current);
+                previous
+            }
+        };
+
+        let restarted = restart
+            .is_some_and(|restart| previous.last_time < restart && restart <= time)
+            || (total < previous.last_total && time > previous.last_time);
+        if restarted {
+            total
+        } else {
+            (total - previous.last_total).max(0.0)
+        }
+    }
 }
 
-impl SlowTaskCounter {
-    pub fn new(threshold_ms: u64, meter: &Meter) -> Self {
+fn machine_label(labels: &[KeyValue]) -> Option<String> {
+    labels
+        .iter()
+        .find(|label| label.key.as_str() == "machine")
+        .map(|label| label.value.to_string())
+}
+
+// A monotonic OTel sum fed with the totals of an FDB counter field, shared by every metric that
+// exports one.
+pub(crate) struct CounterTotal {
+    counter: Counter<f64>,
+    resets: Mutex<CounterResets>,
+}
+
+impl CounterTotal {
+    pub(crate) fn new(counter: Counter<f64>) -> Self {
         Self {
-            threshold_ms,
-            counter: meter
-                .u64_counter(format!("process_slow_task_{threshold_ms}_ms"))
-                .with_description(format!(
-                    "Counter of slow tasks longer than {threshold_ms} ms"
-                ))
-                .init(),
+            counter,
+            resets: Mutex::new(CounterResets::default()),
+        }
+    }
+
+    // Note a `ProgramStart` event, after which the process's totals start again from zero.
+    pub(crate) fn program_start(
+        &self,
+        trace_event: &HashMap<String, Value>,
+        labels: &[KeyValue],
+    ) -> Result<()> {
+        let time = trace_time(trace_event)?;
+        self.resets
+            .lock()
+            .expect("counter resets poisoned")
+            .program_start(labels, time);
+        Ok(())
+    }
+
+    // Add the increase of `total`, logged by `trace_event`, since the previous total for `labels`.
+    pub(crate) fn record(
+        &self,
+        trace_event: &HashMap<String, Value>,
+        labels: &[KeyValue],
+        total: f64,
+    ) -> Result<()> {
+        let time = trace_time(trace_event)?;
+        let instance = trace_event.get("ID").and_then(|value| value.as_str());
+        let increment = self
+            .resets
+            .lock()
+            .expect("counter resets poisoned")
+            .increment(labels, instance, time, total);
+        if increment > 0.0 {
+            self.counter.add(increment, labels);
+        }
+        Ok(())
+    }
+}
+
+fn trace_time(trace_event: &HashMap<String, Value>) -> Result<f64> {
+    Ok(trace_event
+        .get("Time")
+        .and_then(|value| value.as_str())
+        .with_context(|| "Missing Time field")?
+        .parse::<f64>()?)
+}
+
+// Export the total of an FDB counter field (`"<rate> <roughness> <total>"`) as a monotonic OTel
+// sum, so Prometheus `rate()` works and process restarts do not show up as drops.
+pub struct MonotonicCounter {
+    trace_type: String,
+    field_name: String,
+    total: CounterTotal,
+}
+
+impl MonotonicCounter {
+    pub fn new(
+        trace_type: impl Into<String>,
+        field_name: impl Into<String>,
+        counter_name: impl Into<String>,
+        description: impl Into<String>,
+        meter: &Meter,
+    ) -> Self {
+        Self {
+            trace_type: trace_type.into(),
+            field_name: field_name.into(),
+            total: CounterTotal::new(
+                meter
+                    .f64_counter(counter_name.into())
+                    .with_description(description.into())
+                    .init(),
+            ),
         }
     }
 }
 
-impl FDBMetric for SlowTaskCounter {
+impl FDBMetric for MonotonicCounter {
     fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
         let trace_type = trace_event
             .get("Type")
@@ -67,18 +325,19 @@ impl FDBMetric for SlowTaskCounter {
             .as_str()
             .with_context(|| "Invalid Type field")?;
 
-        if trace_type == "SlowTask" {
-            let duration_sec = trace_event
-                .get("Duration")
-                .with_context(|| "Missing Duration field")?
-                .as_str()
-                .with_context(|| "Invalid Duration field")?
-                .parse::<f64>()
-                .with_context(|| "Invalid Duration field")?;
-
-            if duration_sec > ((self.threshold_ms as f64) / 1000.0) {
-                self.counter.add(1, labels);
-            }
+        if trace_type == "ProgramStart" {
+            self.total.program_start(trace_event, labels)?;
+        } else if trace_type == self.trace_type {
+            let total = trace_event
+                .get(&self.field_name)
+                .and_then(|value| value.as_str())
+                .with_context(|| format!("Missing {} field", self.field_name))?
+                .split(' ')
+                .nth(2)
+                .with_context(|| format!("Malformed {} counter", self.field_name))?
+                .parse::<f64>()?;
+
+            self.total.record(trace_event, labels, total)?;
         }
 
         Ok(())
@@ -88,9 +347,13 @@ impl FDBMetric for SlowTaskCounter {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fdb_metric::{FieldLabel, FieldLabeledMetric};
+    use crate::role_instances::{collision_counter, RoleInstances};
     use crate::test_helpers::metrics::{find_metric, prometheus_meter};
+    use opentelemetry::metrics::Meter;
     use opentelemetry::KeyValue;
     use prometheus::Registry;
+    use std::sync::Arc;
 
     fn counter_value(registry: &Registry, name: &str, label_name: &str, label_value: &str) -> f64 {
         let metric = match find_metric(registry, name, label_name, label_value) {
@@ -100,10 +363,44 @@ mod tests {
         metric.get_counter().get_value()
     }
 
+    fn bytes_input_event(time: &str, total: &str) -> HashMap<String, Value> {
+        HashMap::from([
+            ("Type".into(), Value::String("StorageMetrics".into())),
+            ("Time".into(), Value::String(time.into())),
+            (
+                "BytesInput".into(),
+                Value::String(format!("1.5 1.1 {total}")),
+            ),
+        ])
+    }
+
+    fn sev_counter(severity: u64, meter: &Meter) -> EventCounter {
+        EventCounter::new(
+            None,
+            vec![FieldPredicate::parse(&format!("Severity == {severity}")).unwrap()],
+            format!("process_sev{severity}_counter"),
+            format!("Counter of severity {severity} trace events"),
+            meter,
+        )
+    }
+
+    fn slow_task_counter(threshold_ms: u64, meter: &Meter) -> EventCounter {
+        EventCounter::new(
+            Some("SlowTask".into()),
+            vec![
+                FieldPredicate::parse(&format!("Duration > {}", threshold_ms as f64 / 1000.0))
+                    .unwrap(),
+            ],
+            format!("process_slow_task_{threshold_ms}_ms"),
+            format!("Counter of slow tasks longer than {threshold_ms} ms"),
+            meter,
+        )
+    }
+
     #[test]
     fn record_increments_matching_severity() {
         let (provider, meter, registry) = prometheus_meter();
-        let counter = SevCounter::new(10, &meter);
+        let counter = sev_counter(10, &meter);
 
         let mut event = HashMap::new();
         event.insert("Severity".into(), Value::String("10".into()));
@@ -125,7 +422,7 @@ mod tests {
     #[test]
     fn record_skips_mismatched_severity() {
         let (provider, meter, registry) = prometheus_meter();
-        let counter = SevCounter::new(10, &meter);
+        let counter = sev_counter(10, &meter);
 
         let mut event = HashMap::new();
         event.insert("Severity".into(), Value::String("20".into()));
@@ -145,27 +442,45 @@ mod tests {
     }
 
     #[test]
-    fn record_errors_without_severity() {
-        let (_provider, meter, _registry) = prometheus_meter();
-        let counter = SevCounter::new(10, &meter);
+    fn record_skips_events_without_severity() {
+        let (provider, meter, registry) = prometheus_meter();
+        let counter = sev_counter(10, &meter);
 
         let event = HashMap::new();
         let labels = vec![KeyValue::new("machine", "test")];
 
-        let error = counter
+        counter
             .record(&event, &labels)
-            .expect_err("missing severity should error");
+            .expect("missing severity should not error");
+
+        provider.force_flush().expect("force_flush should succeed");
 
+        let value = counter_value(&registry, "process_sev10_counter", "machine", "test");
         assert!(
-            error.to_string().contains("Missing Severity field"),
-            "unexpected error: {error}"
+            value.abs() < f64::EPSILON,
+            "expected counter value 0.0, got {value}"
         );
     }
 
+    #[test]
+    fn predicate_does_not_match_missing_field() {
+        let event = HashMap::from([("Type".to_string(), Value::String("SlowTask".into()))]);
+
+        for predicate in [
+            "Duration > 0.1",
+            "Duration == 1",
+            "Duration != 1",
+            "Name != x",
+        ] {
+            let predicate = FieldPredicate::parse(predicate).unwrap();
+            assert!(!predicate.matches(&event).unwrap());
+        }
+    }
+
     #[test]
     fn slow_task_counter_increments_above_threshold() {
         let (provider, meter, registry) = prometheus_meter();
-        let counter = SlowTaskCounter::new(100, &meter);
+        let counter = slow_task_counter(100, &meter);
 
         let mut event = HashMap::new();
         event.insert("Type".into(), Value::String("SlowTask".into()));
@@ -188,7 +503,7 @@ mod tests {
     #[test]
     fn slow_task_counter_skips_when_duration_below_threshold() {
         let (provider, meter, registry) = prometheus_meter();
-        let counter = SlowTaskCounter::new(100, &meter);
+        let counter = slow_task_counter(100, &meter);
 
         let mut event = HashMap::new();
         event.insert("Type".into(), Value::String("SlowTask".into()));
@@ -211,7 +526,7 @@ mod tests {
     #[test]
     fn slow_task_counter_skips_non_slow_task_events() {
         let (provider, meter, registry) = prometheus_meter();
-        let counter = SlowTaskCounter::new(100, &meter);
+        let counter = slow_task_counter(100, &meter);
 
         let mut event = HashMap::new();
         event.insert("Type".into(), Value::String("Other".into()));
@@ -234,7 +549,7 @@ mod tests {
     #[test]
     fn slow_task_counter_errors_without_type() {
         let (_provider, meter, _registry) = prometheus_meter();
-        let counter = SlowTaskCounter::new(100, &meter);
+        let counter = slow_task_counter(100, &meter);
 
         let mut event = HashMap::new();
         event.insert("Duration".into(), Value::String("0.150".into()));
@@ -249,4 +564,197 @@ mod tests {
             "unexpected error: {error}"
         );
     }
+
+    #[test]
+    fn event_counter_labels_matching_events_by_field() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let counter = FieldLabeledMetric::new(
+            Arc::new(EventCounter::new(
+                None,
+                vec![FieldPredicate::parse("Severity >= 30").unwrap()],
+                "process_warnings",
+                "Warnings and errors",
+                &meter,
+            )),
+            vec![FieldLabel {
+                field: "Type".into(),
+                name: "Type".into(),
+                allow: Vec::new(),
+            }],
+        );
+        let labels = vec![KeyValue::new("machine", "test")];
+
+        for (trace_type, severity) in [
+            ("TLogError", "40"),
+            ("TLogError", "30"),
+            ("Ping", "10"),
+            ("SlowSSLoop", "30"),
+        ] {
+            let mut event = HashMap::new();
+            event.insert("Type".into(), Value::String(trace_type.into()));
+            event.insert("Severity".into(), Value::String(severity.into()));
+            counter.record(&event, &labels).expect("record event");
+        }
+
+        assert_eq!(
+            counter_value(&registry, "process_warnings", "Type", "TLogError"),
+            2.0
+        );
+        assert_eq!(
+            counter_value(&registry, "process_warnings", "Type", "SlowSSLoop"),
+            1.0
+        );
+        assert_eq!(
+            counter_value(&registry, "process_warnings", "Type", "Ping"),
+            0.0
+        );
+    }
+
+    #[test]
+    fn field_predicate_parses_comparisons() {
+        let predicate = FieldPredicate::parse("Duration >= 0.5").unwrap();
+        assert_eq!(predicate.field, "Duration");
+        assert_eq!(predicate.comparison, Comparison::Ge);
+        assert_eq!(predicate.number, Some(0.5));
+
+        let predicate = FieldPredicate::parse(r#"Roles == "SS""#).unwrap();
+        assert_eq!(predicate.value, "SS");
+        assert_eq!(predicate.comparison, Comparison::Eq);
+
+        for invalid in ["Severity", "> 30", "Severity >", "Roles > SS"] {
+            assert!(
+                FieldPredicate::parse(invalid).is_err(),
+                "`{invalid}` should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn monotonic_counter_carries_total_across_restarts() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let counter = MonotonicCounter::new(
+            "StorageMetrics",
+            "BytesInput",
+            "ss_bytes_input",
+            "Bytes input",
+            &meter,
+        );
+        let labels = vec![
+            KeyValue::new("machine", "10.0.0.1:4500"),
+            KeyValue::new("Roles", "SS"),
+        ];
+        let record = |time: &str, total: &str| {
+            counter
+                .record(&bytes_input_event(time, total), &labels)
+                .expect("record total");
+        };
+        let total = || counter_value(&registry, "ss_bytes_input", "machine", "10.0.0.1:4500");
+
+        record("10.0", "100");
+        record("15.0", "150");
+        assert_eq!(total(), 150.0);
+
+        // The total dropping later means the process restarted from zero.
+        record("20.0", "20");
+        assert_eq!(total(), 170.0);
+
+        // A restart noticed through `ProgramStart`, which carries no `Roles`, before the new
+        // total has caught up with the old one.
+        let mut program_start = HashMap::new();
+        program_start.insert("Type".into(), Value::String("ProgramStart".into()));
+        program_start.insert("Time".into(), Value::String("22.0".into()));
+        counter
+            .record(&program_start, &[KeyValue::new("machine", "10.0.0.1:4500")])
+            .expect("record program start");
+        record("25.0", "30");
+        assert_eq!(total(), 200.0);
+
+        record("30.0", "45");
+        assert_eq!(total(), 215.0);
+    }
+
+    #[test]
+    fn monotonic_counter_ignores_totals_read_out_of_order() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let counter = MonotonicCounter::new(
+            "StorageMetrics",
+            "BytesInput",
+            "ss_bytes_input_interleaved",
+            "Bytes input",
+            &meter,
+        );
+        let labels = vec![KeyValue::new("machine", "10.0.0.1:4500")];
+
+        // A rotated trace file and its successor read side by side.
+        let rotated = [("10.0", "100"), ("15.0", "150"), ("20.0", "200")];
+        let successor = [("25.0", "250"), ("30.0", "300"), ("35.0", "350")];
+        for (old, new) in rotated.into_iter().zip(successor) {
+            for (time, total) in [new, old] {
+                counter
+                    .record(&bytes_input_event(time, total), &labels)
+                    .expect("record total");
+            }
+        }
+
+        assert_eq!(
+            counter_value(
+                &registry,
+                "ss_bytes_input_interleaved",
+                "machine",
+                "10.0.0.1:4500"
+            ),
+            350.0
+        );
+    }
+
+    fn counter_sum(registry: &Registry, name: &str) -> f64 {
+        registry
+            .gather()
+            .iter()
+            .filter(|family| family.get_name() == format!("{name}_total"))
+            .flat_map(|family| family.get_metric())
+            .map(|metric| metric.get_counter().get_value())
+            .sum()
+    }
+
+    #[test]
+    fn monotonic_counter_follows_totals_per_role_instance() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let counter = MonotonicCounter::new(
+            "StorageMetrics",
+            "BytesInput",
+            "ss_bytes_input_instances",
+            "Bytes input",
+            &meter,
+        );
+        let labels = vec![KeyValue::new("machine", "10.0.0.1:4500")];
+        let record = |counter: &dyn FDBMetric, id: &str, time: &str, total: &str| {
+            let mut event = bytes_input_event(time, total);
+            event.insert("ID".into(), Value::String(id.into()));
+            counter.record(&event, &labels).expect("record total");
+        };
+
+        // Two instances reporting too far apart to be keyed share one series without their lower
+        // totals being read as restarts.
+        record(&counter, "ss1", "0.0", "100");
+        record(&counter, "ss2", "6.0", "40");
+        record(&counter, "ss1", "12.0", "110");
+        record(&counter, "ss2", "18.0", "45");
+        assert_eq!(counter_sum(&registry, "ss_bytes_input_instances"), 155.0);
+
+        // Once keyed by `ID`, an instance's series continues from its last total.
+        let instances = RoleInstances::new(
+            Arc::new(counter),
+            "StorageMetrics".into(),
+            collision_counter(&meter),
+        );
+        record(&instances, "ss1", "20.0", "120");
+        record(&instances, "ss2", "20.5", "50");
+        record(&instances, "ss1", "25.0", "130");
+        assert_eq!(
+            counter_value(&registry, "ss_bytes_input_instances", "ID", "ss1"),
+            10.0
+        );
+        assert_eq!(counter_sum(&registry, "ss_bytes_input_instances"), 180.0);
+    }
 }
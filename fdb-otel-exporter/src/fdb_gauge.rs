@@ -1,7 +1,13 @@
+use crate::fdb_counter::CounterTotal;
 use crate::fdb_metric::FDBMetric;
-use anyhow::{Context, Result};
+use anyhow::{bail, Context, Result};
 use opentelemetry::metrics::{Gauge, Meter};
 use opentelemetry::KeyValue;
+use prometheus::{
+    core::{Collector, Desc},
+    proto::{self, MetricFamily, MetricType},
+};
+use serde::Deserialize;
 use serde_json::Value;
 use std::{
     collections::{BTreeMap, HashMap, VecDeque},
@@ -9,15 +15,25 @@ use std::{
     sync::{Arc, Mutex},
 };
 
-#[derive(Debug, Clone, Copy)]
-enum HistogramUnit {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum HistogramUnit {
     Milliseconds,
     Bytes,
     Count,
 }
 
 impl HistogramUnit {
-    fn divisor(&self) -> f64 {
+    fn from_trace(unit: &str) -> Option<Self> {
+        match unit {
+            "milliseconds" => Some(Self::Milliseconds),
+            "bytes" => Some(Self::Bytes),
+            "count" => Some(Self::Count),
+            _ => None,
+        }
+    }
+
+    // Divide bucket bounds by this to get seconds for latency histograms, or bytes and counts.
+    pub(crate) fn divisor(&self) -> f64 {
         match self {
             Self::Milliseconds => 1_000_000.0,
             Self::Bytes => 1.0,
@@ -36,11 +52,229 @@ impl HistogramUnit {
 // Snapshot of a histogram bucket expressed in the trace's base units (microseconds, bytes, or counts),
 // along with per-bucket counts and cumulative totals.
 #[derive(Debug, Clone, Copy)]
-struct HistogramBucket {
-    lower_bound: u64,
-    upper_bound: u64,
-    count: u64,
-    cumulative_count: u64,
+pub(crate) struct HistogramBucket {
+    pub(crate) lower_bound: u64,
+    pub(crate) upper_bound: u64,
+    pub(crate) count: u64,
+    pub(crate) cumulative_count: u64,
+}
+
+// Bucket counts from one `Type=Histogram` trace event, keyed by bucket upper bound in base units.
+pub(crate) struct HistogramEvent {
+    pub(crate) unit: HistogramUnit,
+    pub(crate) total_count: u64,
+    pub(crate) counts: BTreeMap<u64, u64>,
+}
+
+// Read the buckets of a `Type=Histogram` event for `(group, op)`. Returns `None` for other events
+// and for histograms in units the exporter does not know.
+pub(crate) fn parse_histogram_event(
+    trace_event: &HashMap<String, Value>,
+    group: &str,
+    op: &str,
+) -> Result<Option<HistogramEvent>> {
+    if get_trace_field(trace_event, "Type")? != "Histogram" {
+        return Ok(None);
+    }
+    if get_trace_field(trace_event, "Group")? != group {
+        return Ok(None);
+    }
+    if get_trace_field(trace_event, "Op")? != op {
+        return Ok(None);
+    }
+
+    let Some(unit) = HistogramUnit::from_trace(get_trace_field(trace_event, "Unit")?) else {
+        return Ok(None);
+    };
+
+    let total_count = get_trace_field(trace_event, "TotalCount")?.parse::<u64>()?;
+    if total_count == 0 {
+        return Ok(Some(HistogramEvent {
+            unit,
+            total_count,
+            counts: BTreeMap::new(),
+        }));
+    }
+
+    let mut counts: BTreeMap<u64, u64> = BTreeMap::new();
+
+    for (k, v) in trace_event {
+        if k.starts_with("LessThan") {
+            let bucket_value = k.strip_prefix("LessThan").unwrap().parse::<f64>()?;
+            let bucket_upper = unit.convert_bucket_upper(bucket_value);
+            let count = v
+                .as_str()
+                .with_context(|| "Trace event values should be strings")?
+                .parse::<u64>()?;
+            counts.insert(bucket_upper, count);
+        }
+    }
+
+    Ok(Some(HistogramEvent {
+        unit,
+        total_count,
+        counts,
+    }))
+}
+
+// Lay out bucket counts as FDB's doubling buckets, from the smallest upper bound present to the
+// largest, with empty buckets filling any gaps and running cumulative counts.
+pub(crate) fn doubling_buckets(counts: &BTreeMap<u64, u64>) -> Vec<HistogramBucket> {
+    let mut buckets: Vec<HistogramBucket> = Vec::new();
+    let mut cumulative = 0u64;
+
+    let Some((&first_upper, _)) = counts.first_key_value() else {
+        return buckets;
+    };
+    let mut expected_upper = first_upper;
+
+    for (&upper_bound, &count) in counts {
+        while expected_upper < upper_bound {
+            let lower_bound = expected_upper / 2;
+            buckets.push(HistogramBucket {
+                lower_bound,
+                upper_bound: expected_upper,
+                count: 0,
+                cumulative_count: cumulative,
+            });
+            expected_upper = expected_upper.saturating_mul(2);
+            if expected_upper == 0 {
+                break;
+            }
+        }
+
+        cumulative += count;
+
+        buckets.push(HistogramBucket {
+            lower_bound: upper_bound / 2,
+            upper_bound,
+            count,
+            cumulative_count: cumulative,
+        });
+
+        expected_upper = match upper_bound.checked_mul(2) {
+            Some(value) => value,
+            None => upper_bound,
+        };
+    }
+
+    buckets
+}
+
+// FDB histograms do not log a sum, so estimate one from bucket midpoints in the trace's units.
+pub(crate) fn estimate_sum(buckets: &[HistogramBucket], unit_divisor: f64) -> f64 {
+    buckets
+        .iter()
+        .map(|bucket| {
+            let midpoint = (bucket.lower_bound + bucket.upper_bound) as f64 / 2.0;
+            bucket.count as f64 * midpoint / unit_divisor
+        })
+        .sum()
+}
+
+// How a percentile is placed inside the histogram bucket that spans it. FDB only logs bucket
+// counts, so each option is an assumption about how observations are spread within a bucket.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PercentileEstimator {
+    // Observations follow an exponential distribution fitted to the bucket's cumulative count.
+    #[default]
+    Exponential,
+    // Observations are spread evenly between the bucket's bounds.
+    Linear,
+    // Observations are spread evenly between the logarithms of the bucket's bounds.
+    LogLinear,
+    // Report the bucket's upper bound, which never under-estimates the percentile.
+    UpperBound,
+}
+
+impl PercentileEstimator {
+    pub(crate) fn estimate(
+        self,
+        buckets: &[HistogramBucket],
+        total_count: u64,
+        percentile: f64,
+        unit_divisor: f64,
+    ) -> Option<f64> {
+        match self {
+            Self::Exponential => {
+                interpolate_exponential_percentile(buckets, total_count, percentile, unit_divisor)
+            }
+            Self::Linear => {
+                interpolate_within_bucket(buckets, total_count, percentile, unit_divisor, |b| {
+                    b.lower + b.fraction * (b.upper - b.lower)
+                })
+            }
+            Self::LogLinear => {
+                interpolate_within_bucket(buckets, total_count, percentile, unit_divisor, |b| {
+                    if b.lower > 0.0 {
+                        b.lower * (b.upper / b.lower).powf(b.fraction)
+                    } else {
+                        b.lower + b.fraction * (b.upper - b.lower)
+                    }
+                })
+            }
+            Self::UpperBound => {
+                interpolate_within_bucket(buckets, total_count, percentile, unit_divisor, |b| {
+                    b.upper
+                })
+            }
+        }
+    }
+}
+
+// The bucket spanning a percentile, with bounds in output units and the fraction of the bucket's
+// own count that falls below the percentile.
+struct SpanningBucket {
+    lower: f64,
+    upper: f64,
+    fraction: f64,
+}
+
+// Locate the first non-empty bucket whose cumulative count reaches the percentile's rank and place
+// the percentile inside it with `interpolate`. The 100th percentile is always the last bucket's
+// upper bound.
+fn interpolate_within_bucket(
+    buckets: &[HistogramBucket],
+    total_count: u64,
+    percentile: f64,
+    unit_divisor: f64,
+    interpolate: impl Fn(SpanningBucket) -> f64,
+) -> Option<f64> {
+    if buckets.is_empty() || total_count == 0 || unit_divisor <= 0.0 || !unit_divisor.is_finite() {
+        return None;
+    }
+
+    let percentile = percentile.clamp(0.0, 1.0);
+    if percentile >= 1.0 {
+        return buckets
+            .last()
+            .map(|bucket| bucket.upper_bound as f64 / unit_divisor);
+    }
+
+    let target_rank = percentile * total_count as f64;
+    let bucket = buckets
+        .iter()
+        .find(|bucket| bucket.count > 0 && bucket.cumulative_count as f64 >= target_rank)
+        .or_else(|| buckets.last())
+        .copied()?;
+
+    let upper = bucket.upper_bound as f64 / unit_divisor;
+    if bucket.count == 0 {
+        return Some(upper);
+    }
+    let lower_cumulative_count = bucket.cumulative_count.saturating_sub(bucket.count) as f64;
+    let fraction = ((target_rank - lower_cumulative_count) / bucket.count as f64).clamp(0.0, 1.0);
+    let lower = bucket.lower_bound as f64 / unit_divisor;
+
+    Some(
+        interpolate(SpanningBucket {
+            lower,
+            upper,
+            fraction,
+        })
+        .clamp(lower, upper),
+    )
 }
 
 // Interpolate a percentile value from histogram buckets assuming an exponential distribution.
@@ -149,7 +383,7 @@ struct FDBGaugeImpl {
     gauge: Gauge<f64>,
 }
 
-fn get_trace_field<'a>(
+pub(crate) fn get_trace_field<'a>(
     trace_event: &'a HashMap<String, Value>,
     field_name: &str,
 ) -> Result<&'a str> {
@@ -260,10 +494,10 @@ impl FDBMetric for TotalCounterFDBGauge {
 }
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
-struct LabelKey(Vec<(String, String)>);
+pub(crate) struct LabelKey(Vec<(String, String)>);
 
 impl LabelKey {
-    fn from_labels(labels: &[KeyValue]) -> Self {
+    pub(crate) fn from_labels(labels: &[KeyValue]) -> Self {
         let mut entries: Vec<(String, String)> = labels
             .iter()
             .map(|kv| (kv.key.as_str().to_string(), kv.value.to_string()))
@@ -319,6 +553,82 @@ impl RollingWindow {
     }
 }
 
+#[derive(Clone)]
+struct TimedHistogram {
+    time: f64,
+    total_count: u64,
+    counts: BTreeMap<u64, u64>,
+}
+
+// Histogram counterpart of `RollingWindow`: adds up the buckets of the histograms reported for a
+// label set within the last `window_seconds` of trace time.
+#[derive(Clone)]
+struct HistogramWindow {
+    window_seconds: f64,
+    histograms: Arc<Mutex<HashMap<LabelKey, VecDeque<TimedHistogram>>>>,
+}
+
+impl HistogramWindow {
+    fn new(window_seconds: f64) -> Self {
+        Self {
+            window_seconds,
+            histograms: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn observe(
+        &self,
+        labels: &[KeyValue],
+        time: f64,
+        histogram: HistogramEvent,
+    ) -> (u64, BTreeMap<u64, u64>) {
+        let key = LabelKey::from_labels(labels);
+        let mut histograms = self
+            .histograms
+            .lock()
+            .expect("histogram window cache poisoned");
+        let window = histograms.entry(key).or_default();
+        window.push_back(TimedHistogram {
+            time,
+            total_count: histogram.total_count,
+            counts: histogram.counts,
+        });
+        while let Some(front) = window.front() {
+            if time - front.time > self.window_seconds {
+                window.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        let mut total_count = 0;
+        let mut counts = BTreeMap::new();
+        for histogram in window.iter() {
+            total_count += histogram.total_count;
+            for (upper_bound, count) in &histogram.counts {
+                *counts.entry(*upper_bound).or_default() += count;
+            }
+        }
+        (total_count, counts)
+    }
+}
+
+// The buckets to compute percentiles from: the event's own, or those accumulated over the window.
+fn windowed_counts(
+    window: Option<&HistogramWindow>,
+    trace_event: &HashMap<String, Value>,
+    labels: &[KeyValue],
+    histogram: HistogramEvent,
+) -> Result<(u64, BTreeMap<u64, u64>)> {
+    match window {
+        Some(window) => {
+            let time = get_trace_field(trace_event, "Time")?.parse::<f64>()?;
+            Ok(window.observe(labels, time, histogram))
+        }
+        None => Ok((histogram.total_count, histogram.counts)),
+    }
+}
+
 #[derive(Clone)]
 // Maintains a 15 second rolling mean of raw samples keyed by label set so Prometheus scrapes see a
 // stable value even when scrape periods exceed log emission frequency.
@@ -403,130 +713,378 @@ impl FDBMetric for ElapsedRateFDBGauge {
     }
 }
 
+// One of the space-delimited values of an FDB counter field, `"<rate> <roughness> <total>"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CounterComponent {
+    Rate,
+    Roughness,
+    Total,
+}
+
+// Parse a counter field once and export the requested components: `<gauge_name>_rate` as a 15
+// second rolling mean like `RateCounterFDBGauge`, `<gauge_name>_roughness` as reported, and the
+// total as a monotonic counter that Prometheus exports as `<gauge_name>_total`.
+pub struct CounterFDBGauge {
+    trace_type: String,
+    field_name: String,
+    rate: Option<(Gauge<f64>, RollingWindow)>,
+    roughness: Option<Gauge<f64>>,
+    total: Option<CounterTotal>,
+}
+
+impl CounterFDBGauge {
+    pub fn new(
+        trace_type: impl Into<String>,
+        field_name: impl Into<String>,
+        gauge_name: impl Into<String>,
+        description: impl Into<String>,
+        components: &[CounterComponent],
+        meter: &Meter,
+    ) -> Self {
+        let gauge_name = gauge_name.into();
+        let description = description.into();
+        let gauge = |suffix: &str| {
+            meter
+                .f64_gauge(format!("{gauge_name}_{suffix}"))
+                .with_description(format!("{description} ({suffix})"))
+                .init()
+        };
+
+        Self {
+            trace_type: trace_type.into(),
+            field_name: field_name.into(),
+            rate: components
+                .contains(&CounterComponent::Rate)
+                .then(|| (gauge("rate"), RollingWindow::new(ROLLING_WINDOW_SECONDS))),
+            roughness: components
+                .contains(&CounterComponent::Roughness)
+                .then(|| gauge("roughness")),
+            total: components.contains(&CounterComponent::Total).then(|| {
+                CounterTotal::new(
+                    meter
+                        .f64_counter(gauge_name.clone())
+                        .with_description(format!("{description} (total)"))
+                        .init(),
+                )
+            }),
+        }
+    }
+}
+
+impl FDBMetric for CounterFDBGauge {
+    fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
+        let trace_type = get_trace_field(trace_event, "Type")?;
+
+        if trace_type == "ProgramStart" {
+            if let Some(total) = &self.total {
+                total.program_start(trace_event, labels)?;
+            }
+            return Ok(());
+        }
+        if trace_type != self.trace_type {
+            return Ok(());
+        }
+
+        let value = get_trace_field(trace_event, self.field_name.as_str())?;
+        let mut parts = value.split(' ').map(str::parse::<f64>);
+        let (Some(rate), Some(roughness), Some(total)) = (parts.next(), parts.next(), parts.next())
+        else {
+            bail!("Malformed {} counter", self.field_name);
+        };
+
+        if let Some((gauge, rolling_window)) = &self.rate {
+            let time = get_trace_field(trace_event, "Time")?.parse::<f64>()?;
+            gauge.record(rolling_window.observe(labels, time, rate?), labels);
+        }
+        if let Some(gauge) = &self.roughness {
+            gauge.record(roughness?, labels);
+        }
+        if let Some(counter_total) = &self.total {
+            counter_total.record(trace_event, labels, total?)?;
+        }
+        Ok(())
+    }
+}
+
 // Because histograms are precomputed, interpolate percentiles and emit as gauge
 pub struct HistogramPercentileFDBGauge {
     percentile: f64,
+    estimator: PercentileEstimator,
     group: String,
     op: String,
     gauge: Gauge<f64>,
+    window: Option<HistogramWindow>,
 }
 
 impl HistogramPercentileFDBGauge {
     // Record pre-aggregated histogram percentiles as gauges. FoundationDB log files contain
     // histogram buckets (with upper-bound thresholds) for each `(Group, Op)` combination. This
     // gauge collects buckets from the matching log event and interpolates the requested percentile
-    // under an exponential assumption.
+    // with the configured estimator.
     pub fn new(
         group: impl Into<String>,
         op: impl Into<String>,
         percentile: f64,
+        estimator: PercentileEstimator,
         gauge_name: impl Into<String>,
         description: impl Into<String>,
         meter: &Meter,
     ) -> Self {
         Self {
             percentile,
+            estimator,
             group: group.into(),
             op: op.into(),
             gauge: meter
                 .f64_gauge(gauge_name.into())
                 .with_description(description.into())
                 .init(),
+            window: None,
         }
     }
+
+    // Compute the percentile over the histograms reported in the last `window_seconds` of trace
+    // time instead of only the latest one.
+    pub fn with_window(mut self, window_seconds: f64) -> Self {
+        self.window = Some(HistogramWindow::new(window_seconds));
+        self
+    }
 }
 
 impl FDBMetric for HistogramPercentileFDBGauge {
     fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
-        if get_trace_field(trace_event, "Type")? != "Histogram" {
+        let Some(histogram) = parse_histogram_event(trace_event, &self.group, &self.op)? else {
             return Ok(());
-        }
-        if get_trace_field(trace_event, "Group")? != self.group {
+        };
+        let divisor = histogram.unit.divisor();
+        let (total_count, counts) =
+            windowed_counts(self.window.as_ref(), trace_event, labels, histogram)?;
+        if total_count == 0 {
             return Ok(());
         }
-        if get_trace_field(trace_event, "Op")? != self.op {
+
+        let buckets = doubling_buckets(&counts);
+        if buckets.is_empty() {
             return Ok(());
         }
 
-        let unit_str = get_trace_field(trace_event, "Unit")?;
-        let unit = match unit_str {
-            "milliseconds" => HistogramUnit::Milliseconds,
-            "bytes" => HistogramUnit::Bytes,
-            "count" => HistogramUnit::Count,
-            _ => return Ok(()),
-        };
-        let unit_divisor = unit.divisor();
-
-        let total_count = get_trace_field(trace_event, "TotalCount")?.parse::<u64>()?;
-        if total_count == 0 {
-            return Ok(());
+        if let Some(interpolated_value) =
+            self.estimator
+                .estimate(&buckets, total_count, self.percentile, divisor)
+        {
+            self.gauge.record(interpolated_value, labels);
         }
 
-        let mut hist: BTreeMap<u64, u64> = BTreeMap::new();
-
-        for (k, v) in trace_event {
-            if k.starts_with("LessThan") {
-                let bucket_value = k.strip_prefix("LessThan").unwrap().parse::<f64>()?;
-                let bucket_upper = unit.convert_bucket_upper(bucket_value);
-                let count = v
-                    .as_str()
-                    .with_context(|| "Trace event values should be strings")?
-                    .parse::<u64>()?;
-                hist.insert(bucket_upper, count);
-            }
+        Ok(())
+    }
+}
+
+// Quantiles, observation counts and sums of every summary gauge, exported as Prometheus summary
+// families. OTel has no summary instrument and the Prometheus exporter adds `_total` to counters,
+// so the store is registered with the Prometheus registry as a collector of its own instead. It
+// must be registered with the registry that the meter provider's exporter writes to.
+#[derive(Debug, Clone)]
+pub struct Summaries {
+    // The registry requires every collector to describe at least one metric, and rejects a second
+    // collector without any as a duplicate of the exporter's. Families are only known as gauges
+    // record, so this names the store rather than a family.
+    desc: Desc,
+    families: Arc<Mutex<BTreeMap<String, SummaryFamily>>>,
+}
+
+#[derive(Debug)]
+struct SummaryFamily {
+    description: String,
+    series: HashMap<LabelKey, SummarySeries>,
+}
+
+#[derive(Debug)]
+struct SummarySeries {
+    labels: Vec<KeyValue>,
+    // Latest estimate for each percentile, keyed by its `quantile` label.
+    quantiles: BTreeMap<String, (f64, f64)>,
+    count: u64,
+    sum: f64,
+}
+
+impl Default for Summaries {
+    fn default() -> Self {
+        Self {
+            desc: Desc::new(
+                "fdb_histogram_summaries".to_string(),
+                "Summaries of FoundationDB histogram events".to_string(),
+                Vec::new(),
+                HashMap::new(),
+            )
+            .expect("summary store descriptor is valid"),
+            families: Arc::new(Mutex::new(BTreeMap::new())),
         }
+    }
+}
 
-        if hist.is_empty() {
-            return Ok(());
+impl Summaries {
+    // Replace the series' quantile estimates and add one histogram event's observations.
+    fn record(
+        &self,
+        name: &str,
+        description: &str,
+        labels: &[KeyValue],
+        quantiles: &[(f64, f64)],
+        count: u64,
+        sum: f64,
+    ) {
+        let mut families = self.families.lock().expect("summaries poisoned");
+        let family = families
+            .entry(name.to_string())
+            .or_insert_with(|| SummaryFamily {
+                description: description.to_string(),
+                series: HashMap::new(),
+            });
+        let series = family
+            .series
+            .entry(LabelKey::from_labels(labels))
+            .or_insert_with(|| SummarySeries {
+                labels: labels.to_vec(),
+                quantiles: BTreeMap::new(),
+                count: 0,
+                sum: 0.0,
+            });
+        for &(quantile, value) in quantiles {
+            series
+                .quantiles
+                .insert(quantile.to_string(), (quantile, value));
         }
+        series.count += count;
+        series.sum += sum;
+    }
+}
+
+impl Collector for Summaries {
+    fn desc(&self) -> Vec<&Desc> {
+        vec![&self.desc]
+    }
 
-        let mut buckets: Vec<HistogramBucket> = Vec::new();
-        let mut cumulative = 0u64;
-        let hist_entries: Vec<(u64, u64)> = hist
+    fn collect(&self) -> Vec<MetricFamily> {
+        let families = self.families.lock().expect("summaries poisoned");
+        families
             .iter()
-            .map(|(upper_bound, count)| (*upper_bound, *count))
-            .collect();
+            .map(|(name, family)| {
+                let mut metric_family = MetricFamily::default();
+                metric_family.set_name(name.clone());
+                metric_family.set_help(family.description.clone());
+                metric_family.set_field_type(MetricType::SUMMARY);
+                for series in family.series.values() {
+                    let mut summary = proto::Summary::default();
+                    summary.set_sample_count(series.count);
+                    summary.set_sample_sum(series.sum);
+                    for &(quantile, value) in series.quantiles.values() {
+                        let mut estimate = proto::Quantile::default();
+                        estimate.set_quantile(quantile);
+                        estimate.set_value(value);
+                        summary.mut_quantile().push(estimate);
+                    }
+
+                    let mut labels = series.labels.clone();
+                    labels.sort_by(|a, b| a.key.as_str().cmp(b.key.as_str()));
+                    let mut metric = proto::Metric::default();
+                    for label in labels {
+                        let mut pair = proto::LabelPair::default();
+                        pair.set_name(label.key.to_string());
+                        pair.set_value(label.value.to_string());
+                        metric.mut_label().push(pair);
+                    }
+                    metric.set_summary(summary);
+                    metric_family.mut_metric().push(metric);
+                }
+                metric_family
+            })
+            .collect()
+    }
+}
 
-        let Some((mut expected_upper, _)) = hist_entries.first().copied() else {
-            return Ok(());
-        };
+// Emit histogram percentiles as a Prometheus summary: `gauge_name` with one `quantile` series per
+// percentile, plus `gauge_name_count` and `gauge_name_sum` holding the observations seen so far.
+// The sum is estimated from bucket midpoints.
+pub struct HistogramSummaryFDBGauge {
+    percentiles: Vec<f64>,
+    estimator: PercentileEstimator,
+    group: String,
+    op: String,
+    gauge_name: String,
+    description: String,
+    summaries: Summaries,
+    window: Option<HistogramWindow>,
+}
 
-        for (upper_bound, count) in hist_entries {
-            while expected_upper < upper_bound {
-                let lower_bound = expected_upper / 2;
-                buckets.push(HistogramBucket {
-                    lower_bound,
-                    upper_bound: expected_upper,
-                    count: 0,
-                    cumulative_count: cumulative,
-                });
-                expected_upper = expected_upper.saturating_mul(2);
-                if expected_upper == 0 {
-                    break;
-                }
-            }
+impl HistogramSummaryFDBGauge {
+    pub fn new(
+        group: impl Into<String>,
+        op: impl Into<String>,
+        percentiles: Vec<f64>,
+        estimator: PercentileEstimator,
+        gauge_name: impl Into<String>,
+        description: impl Into<String>,
+        summaries: Summaries,
+    ) -> Self {
+        Self {
+            percentiles,
+            estimator,
+            group: group.into(),
+            op: op.into(),
+            gauge_name: gauge_name.into(),
+            description: description.into(),
+            summaries,
+            window: None,
+        }
+    }
 
-            cumulative += count;
+    // Compute the quantiles over the histograms reported in the last `window_seconds` of trace
+    // time. `_count` and `_sum` are unaffected.
+    pub fn with_window(mut self, window_seconds: f64) -> Self {
+        self.window = Some(HistogramWindow::new(window_seconds));
+        self
+    }
+}
 
-            buckets.push(HistogramBucket {
-                lower_bound: upper_bound / 2,
-                upper_bound,
-                count,
-                cumulative_count: cumulative,
-            });
+impl FDBMetric for HistogramSummaryFDBGauge {
+    fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
+        let Some(histogram) = parse_histogram_event(trace_event, &self.group, &self.op)? else {
+            return Ok(());
+        };
+        let divisor = histogram.unit.divisor();
+        let event_count = histogram.total_count;
+        let event_sum = estimate_sum(&doubling_buckets(&histogram.counts), divisor);
 
-            expected_upper = match upper_bound.checked_mul(2) {
-                Some(value) => value,
-                None => upper_bound,
-            };
+        let (total_count, counts) =
+            windowed_counts(self.window.as_ref(), trace_event, labels, histogram)?;
+        if total_count == 0 {
+            return Ok(());
         }
 
-        if let Some(interpolated_value) =
-            interpolate_exponential_percentile(&buckets, total_count, self.percentile, unit_divisor)
-        {
-            self.gauge.record(interpolated_value, labels);
+        let buckets = doubling_buckets(&counts);
+        if buckets.is_empty() {
+            return Ok(());
         }
 
+        let quantiles: Vec<(f64, f64)> = self
+            .percentiles
+            .iter()
+            .filter_map(|&percentile| {
+                self.estimator
+                    .estimate(&buckets, total_count, percentile, divisor)
+                    .map(|value| (percentile, value))
+            })
+            .collect();
+        self.summaries.record(
+            &self.gauge_name,
+            &self.description,
+            labels,
+            &quantiles,
+            event_count,
+            event_sum,
+        );
+
         Ok(())
     }
 }
@@ -534,11 +1092,11 @@ impl FDBMetric for HistogramPercentileFDBGauge {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::test_helpers::metrics::{find_metric, prometheus_meter};
+    use crate::test_helpers::metrics::{find_metric, find_metric_in, prometheus_meter};
     use opentelemetry::metrics::{Meter, MeterProvider};
     use opentelemetry::KeyValue;
     use opentelemetry_sdk::metrics::{ManualReader, SdkMeterProvider};
-    use prometheus::Registry;
+    use prometheus::{Encoder, Registry, TextEncoder};
 
     fn bucket(upper_bound: u64, count: u64, cumulative: u64) -> HistogramBucket {
         HistogramBucket {
@@ -573,6 +1131,7 @@ mod tests {
             "StorageServer",
             "Read",
             0.5,
+            PercentileEstimator::Exponential,
             "ss_read_latency_p50_test",
             "Read latency",
             meter,
@@ -852,6 +1411,198 @@ mod tests {
         gauge.record(&event, &[]).expect("record should succeed");
     }
 
+    #[test]
+    fn histogram_percentile_accumulates_buckets_over_window() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let gauge = HistogramPercentileFDBGauge::new(
+            "StorageServer",
+            "Read",
+            0.75,
+            PercentileEstimator::UpperBound,
+            "ss_read_latency_windowed_test",
+            "Read latency",
+            &meter,
+        )
+        .with_window(30.0);
+        let labels = [KeyValue::new("machine", "10.0.0.1:4500")];
+        let at = |time: &str, bucket: &str| {
+            let mut event = base_histogram_event();
+            event.insert("Unit".into(), Value::String("milliseconds".into()));
+            event.insert("Time".into(), Value::String(time.into()));
+            event.insert("TotalCount".into(), Value::String("10".into()));
+            event.insert(format!("LessThan{bucket}"), Value::String("10".into()));
+            event
+        };
+        let p75 = || {
+            find_metric(
+                &registry,
+                "ss_read_latency_windowed_test",
+                "machine",
+                "10.0.0.1:4500",
+            )
+            .expect("windowed percentile")
+            .get_gauge()
+            .get_value()
+        };
+
+        gauge
+            .record(&at("100.0", "1.024"), &labels)
+            .expect("fast interval");
+        assert_eq!(p75(), 0.001024);
+
+        // The slow interval only holds half of the window's observations, but sets its p75.
+        gauge
+            .record(&at("105.0", "65.536"), &labels)
+            .expect("slow interval");
+        assert_eq!(p75(), 0.065536);
+
+        // Both earlier intervals have left the window.
+        gauge
+            .record(&at("140.0", "1.024"), &labels)
+            .expect("later interval");
+        assert_eq!(p75(), 0.001024);
+    }
+
+    #[test]
+    fn counter_gauge_exports_rate_roughness_and_total() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let gauge = CounterFDBGauge::new(
+            "StorageMetrics",
+            "Mutations",
+            "ss_mutations_test",
+            "Mutations",
+            &[
+                CounterComponent::Rate,
+                CounterComponent::Roughness,
+                CounterComponent::Total,
+            ],
+            &meter,
+        );
+        let labels = [KeyValue::new("machine", "test")];
+
+        let mut event = base_event_with_type("StorageMetrics");
+        event.insert("Time".into(), Value::String("100.0".into()));
+        event.insert("Mutations".into(), Value::String("250 1.8 4000".into()));
+        gauge.record(&event, &labels).expect("record counter");
+
+        let families = registry.gather();
+        let value = |name: &str| find_metric_in(&families, name, "machine", "test");
+        assert_eq!(
+            value("ss_mutations_test_rate")
+                .unwrap()
+                .get_gauge()
+                .get_value(),
+            250.0
+        );
+        assert_eq!(
+            value("ss_mutations_test_roughness")
+                .unwrap()
+                .get_gauge()
+                .get_value(),
+            1.8
+        );
+        assert_eq!(
+            value("ss_mutations_test_total")
+                .unwrap()
+                .get_counter()
+                .get_value(),
+            4000.0
+        );
+    }
+
+    #[test]
+    fn counter_gauge_emits_only_requested_series() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let gauge = CounterFDBGauge::new(
+            "StorageMetrics",
+            "Mutations",
+            "ss_mutations_roughness_only",
+            "Mutations",
+            &[CounterComponent::Roughness],
+            &meter,
+        );
+
+        // Without a rate series the event's `Time` is not needed.
+        let mut event = base_event_with_type("StorageMetrics");
+        event.insert("Mutations".into(), Value::String("250 1.8 4000".into()));
+        gauge
+            .record(&event, &[KeyValue::new("machine", "test")])
+            .expect("record counter");
+
+        let names: Vec<String> = registry
+            .gather()
+            .iter()
+            .map(|family| family.get_name().to_string())
+            .filter(|name| name.starts_with("ss_mutations_roughness_only"))
+            .collect();
+        assert_eq!(names, ["ss_mutations_roughness_only_roughness"]);
+
+        event.insert("Mutations".into(), Value::String("250 1.8".into()));
+        let error = gauge
+            .record(&event, &[KeyValue::new("machine", "test")])
+            .expect_err("two-part counter is malformed");
+        assert!(error.to_string().contains("Malformed Mutations counter"));
+    }
+
+    #[test]
+    fn histogram_summary_records_quantiles_count_and_sum() {
+        let registry = Registry::new();
+        let summaries = Summaries::default();
+        registry
+            .register(Box::new(summaries.clone()))
+            .expect("register summaries");
+        let gauge = HistogramSummaryFDBGauge::new(
+            "StorageServer",
+            "Read",
+            vec![0.5, 0.99],
+            PercentileEstimator::Exponential,
+            "ss_read_latency_summary_test",
+            "Read latency",
+            summaries,
+        );
+        let labels = [KeyValue::new("machine", "10.0.0.1:4500")];
+
+        let mut event = base_histogram_event();
+        event.insert("Unit".into(), Value::String("milliseconds".into()));
+        event.insert("TotalCount".into(), Value::String("10".into()));
+        event.insert("LessThan1.0".into(), Value::String("4".into()));
+        event.insert("LessThan2.0".into(), Value::String("6".into()));
+        gauge.record(&event, &labels).expect("first record");
+        gauge.record(&event, &labels).expect("second record");
+
+        let families = registry.gather();
+        let family = families
+            .iter()
+            .find(|family| family.get_name() == "ss_read_latency_summary_test")
+            .expect("summary family");
+        assert_eq!(family.get_field_type(), MetricType::SUMMARY);
+        let summary = family.get_metric()[0].get_summary();
+        for (quantile, expected) in summary.get_quantile().iter().zip([0.5, 0.99]) {
+            assert_eq!(quantile.get_quantile(), expected);
+            let value = quantile.get_value();
+            assert!(
+                value > 0.001 && value <= 0.002,
+                "quantile {expected} = {value}"
+            );
+        }
+
+        let mut text = Vec::new();
+        TextEncoder::new().encode(&families, &mut text).unwrap();
+        let text = String::from_utf8(text).unwrap();
+        assert!(
+            text.contains("# TYPE ss_read_latency_summary_test summary\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("ss_read_latency_summary_test_count{machine=\"10.0.0.1:4500\"} 20\n"),
+            "{text}"
+        );
+        assert!(
+            text.contains("ss_read_latency_summary_test_sum{machine=\"10.0.0.1:4500\"} 0.024"),
+            "{text}"
+        );
+    }
+
     #[test]
     fn histogram_percentile_skips_non_histogram_events() {
         let meter = test_meter();
@@ -1086,4 +1837,111 @@ mod tests {
             );
         }
     }
+
+    // Doubling buckets whose cumulative counts follow `cdf`, with any remaining mass in the last.
+    fn buckets_from_cdf(
+        uppers: &[u64],
+        total: u64,
+        cdf: impl Fn(f64) -> f64,
+    ) -> Vec<HistogramBucket> {
+        let mut previous = 0;
+        uppers
+            .iter()
+            .enumerate()
+            .map(|(index, &upper)| {
+                let cumulative = if index + 1 == uppers.len() {
+                    total
+                } else {
+                    (cdf(upper as f64) * total as f64).round() as u64
+                };
+                let count = cumulative - previous;
+                previous = cumulative;
+                bucket(upper, count, cumulative)
+            })
+            .collect()
+    }
+
+    fn estimate(
+        estimator: PercentileEstimator,
+        buckets: &[HistogramBucket],
+        percentile: f64,
+    ) -> f64 {
+        let total = buckets.last().unwrap().cumulative_count;
+        estimator
+            .estimate(buckets, total, percentile, 1.0)
+            .expect("estimate")
+    }
+
+    #[test]
+    fn exponential_estimator_recovers_exponential_distribution() {
+        let uppers: Vec<u64> = (0..18).map(|shift| 1 << shift).collect();
+        let buckets = buckets_from_cdf(&uppers, 1_000_000, |x| 1.0 - (-x / 1000.0).exp());
+
+        for percentile in [0.5f64, 0.9, 0.99] {
+            let expected = -1000.0 * (1.0 - percentile).ln();
+            let value = estimate(PercentileEstimator::Exponential, &buckets, percentile);
+            assert!(
+                (value - expected).abs() / expected < 0.01,
+                "p{percentile}: {value} != {expected}"
+            );
+        }
+    }
+
+    #[test]
+    fn linear_estimator_recovers_uniform_distribution() {
+        // Uniform between 1024 and 4096.
+        let buckets = [bucket(2048, 1024, 1024), bucket(4096, 2048, 3072)];
+
+        for percentile in [0.1, 0.5, 0.9] {
+            let expected = 1024.0 + percentile * 3072.0;
+            let value = estimate(PercentileEstimator::Linear, &buckets, percentile);
+            assert!((value - expected).abs() < 1e-9, "p{percentile}: {value}");
+        }
+    }
+
+    #[test]
+    fn log_linear_estimator_recovers_log_uniform_distribution() {
+        // Log-uniform between 1024 and 8192, so each doubling bucket holds the same count.
+        let buckets = [
+            bucket(2048, 100, 100),
+            bucket(4096, 100, 200),
+            bucket(8192, 100, 300),
+        ];
+
+        for percentile in [0.25, 0.5, 0.75] {
+            let expected = 1024.0 * 8f64.powf(percentile);
+            let value = estimate(PercentileEstimator::LogLinear, &buckets, percentile);
+            assert!((value - expected).abs() < 1e-9, "p{percentile}: {value}");
+        }
+    }
+
+    #[test]
+    fn upper_bound_estimator_reports_spanning_bucket_bound() {
+        // Bimodal: most observations near 1ms, a tail near 64ms, with empty buckets between.
+        let buckets = [
+            bucket(1024, 90, 90),
+            bucket(2048, 0, 90),
+            bucket(4096, 0, 90),
+            bucket(8192, 0, 90),
+            bucket(16384, 0, 90),
+            bucket(32768, 0, 90),
+            bucket(65536, 10, 100),
+        ];
+
+        assert_eq!(
+            estimate(PercentileEstimator::UpperBound, &buckets, 0.5),
+            1024.0
+        );
+        assert_eq!(
+            estimate(PercentileEstimator::UpperBound, &buckets, 0.9),
+            1024.0
+        );
+        assert_eq!(
+            estimate(PercentileEstimator::UpperBound, &buckets, 0.95),
+            65536.0
+        );
+        // Within the tail bucket, linear interpolation stays above the empty gap.
+        let linear = estimate(PercentileEstimator::Linear, &buckets, 0.95);
+        assert!((linear - 49152.0).abs() < 1e-9, "linear p95: {linear}");
+    }
 }
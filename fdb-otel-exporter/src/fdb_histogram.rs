@@ -0,0 +1,599 @@
+use crate::fdb_gauge::{
+    doubling_buckets, estimate_sum, get_trace_field, parse_histogram_event, HistogramBucket,
+    HistogramUnit, LabelKey, PercentileEstimator,
+};
+use crate::fdb_metric::FDBMetric;
+use anyhow::Result;
+use opentelemetry::{
+    metrics::{Gauge, Meter, MetricsError},
+    InstrumentationLibrary, KeyValue,
+};
+use opentelemetry_sdk::metrics::{
+    data::{self, ScopeMetrics, Temporality},
+    reader::MetricProducer,
+};
+use serde_json::Value;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+// Cumulative bucket counts of every native histogram, exported as OTel histogram data points when
+// the meter provider's reader collects. The SDK instruments only take individual measurements, so
+// the pre-aggregated buckets FDB logs are handed to the reader through this producer instead. It
+// must be registered with the reader of the provider whose meter the gauges are built from.
+#[derive(Debug, Clone, Default)]
+pub struct NativeHistograms {
+    families: Arc<Mutex<BTreeMap<String, HistogramFamily>>>,
+}
+
+#[derive(Debug)]
+struct HistogramFamily {
+    description: String,
+    series: HashMap<LabelKey, HistogramSeries>,
+}
+
+#[derive(Debug)]
+struct HistogramSeries {
+    labels: Vec<KeyValue>,
+    start_time: SystemTime,
+    unit: HistogramUnit,
+    // Bucket upper bound in the trace's base units to the count observed since `start_time`.
+    counts: BTreeMap<u64, u64>,
+}
+
+impl NativeHistograms {
+    fn add(
+        &self,
+        name: &str,
+        description: &str,
+        labels: &[KeyValue],
+        unit: HistogramUnit,
+        counts: &BTreeMap<u64, u64>,
+    ) {
+        let mut families = self.families.lock().expect("native histograms poisoned");
+        let family = families
+            .entry(name.to_string())
+            .or_insert_with(|| HistogramFamily {
+                description: description.to_string(),
+                series: HashMap::new(),
+            });
+        let series = family
+            .series
+            .entry(LabelKey::from_labels(labels))
+            .or_insert_with(|| HistogramSeries {
+                labels: labels.to_vec(),
+                start_time: SystemTime::now(),
+                unit,
+                counts: BTreeMap::new(),
+            });
+        for (upper_bound, count) in counts {
+            *series.counts.entry(*upper_bound).or_default() += count;
+        }
+    }
+}
+
+// FDB histograms count into doubling buckets whose upper bounds run from 1 to 2^32 in the trace's
+// base units (microseconds, bytes or counts).
+const FDB_BUCKET_BOUNDS: u32 = 33;
+
+impl HistogramSeries {
+    // Bounds are all of FDB's doubling buckets converted to base units, so every series of a family
+    // shares one layout that Prometheus can sum across by `le`. Counts above the largest bound land
+    // in the `+Inf` bucket.
+    fn data_point(&self, now: SystemTime) -> data::HistogramDataPoint<f64> {
+        let divisor = self.unit.divisor();
+        let mut bucket_counts = vec![0u64; FDB_BUCKET_BOUNDS as usize + 1];
+        for (upper_bound, count) in &self.counts {
+            let index = upper_bound.next_power_of_two().trailing_zeros();
+            bucket_counts[index.min(FDB_BUCKET_BOUNDS) as usize] += count;
+        }
+
+        let mut cumulative_count = 0;
+        let buckets: Vec<HistogramBucket> = (0..FDB_BUCKET_BOUNDS)
+            .map(|index| {
+                let upper_bound = 1u64 << index;
+                let count = bucket_counts[index as usize];
+                cumulative_count += count;
+                HistogramBucket {
+                    lower_bound: upper_bound / 2,
+                    upper_bound,
+                    count,
+                    cumulative_count,
+                }
+            })
+            .collect();
+        let bounds = buckets
+            .iter()
+            .map(|bucket| bucket.upper_bound as f64 / divisor)
+            .collect();
+        let sum = estimate_sum(&buckets, divisor);
+
+        data::HistogramDataPoint {
+            attributes: self.labels.as_slice().into(),
+            start_time: self.start_time,
+            time: now,
+            count: bucket_counts.iter().sum(),
+            bounds,
+            bucket_counts,
+            min: None,
+            max: None,
+            sum,
+            exemplars: Vec::new(),
+        }
+    }
+}
+
+impl MetricProducer for NativeHistograms {
+    fn produce(&self) -> Result<ScopeMetrics, MetricsError> {
+        let now = SystemTime::now();
+        let families = self.families.lock().expect("native histograms poisoned");
+        let metrics = families
+            .iter()
+            .map(|(name, family)| data::Metric {
+                name: Cow::Owned(name.clone()),
+                description: Cow::Owned(family.description.clone()),
+                unit: Default::default(),
+                data: Box::new(data::Histogram {
+                    data_points: family
+                        .series
+                        .values()
+                        .map(|series| series.data_point(now))
+                        .collect(),
+                    temporality: Temporality::Cumulative,
+                }),
+            })
+            .collect();
+
+        Ok(ScopeMetrics {
+            scope: InstrumentationLibrary::builder("fdb-otel-exporter").build(),
+            metrics,
+        })
+    }
+}
+
+// Accumulate the buckets of `(Group, Op)` histogram events into a cumulative histogram per label
+// set, so Prometheus can aggregate it across machines with `histogram_quantile`.
+pub struct NativeHistogramFDBGauge {
+    group: String,
+    op: String,
+    name: String,
+    description: String,
+    histograms: NativeHistograms,
+}
+
+impl NativeHistogramFDBGauge {
+    pub fn new(
+        group: impl Into<String>,
+        op: impl Into<String>,
+        name: impl Into<String>,
+        description: impl Into<String>,
+        histograms: NativeHistograms,
+    ) -> Self {
+        Self {
+            group: group.into(),
+            op: op.into(),
+            name: name.into(),
+            description: description.into(),
+            histograms,
+        }
+    }
+}
+
+impl FDBMetric for NativeHistogramFDBGauge {
+    fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
+        let Some(histogram) = parse_histogram_event(trace_event, &self.group, &self.op)? else {
+            return Ok(());
+        };
+        if histogram.counts.is_empty() {
+            return Ok(());
+        }
+
+        self.histograms.add(
+            &self.name,
+            &self.description,
+            labels,
+            histogram.unit,
+            &histogram.counts,
+        );
+        Ok(())
+    }
+}
+
+// Which processes' histograms are merged: those sharing the values of `labels` (all processes when
+// empty), counting each process's latest histogram if it is at most `interval_seconds` older, by
+// trace `Time`, than the newest histogram in the group.
+#[derive(Debug, Clone)]
+pub struct MergeScope {
+    pub labels: Vec<String>,
+    pub interval_seconds: f64,
+}
+
+#[derive(Default)]
+struct MergeGroup {
+    processes: HashMap<LabelKey, ProcessHistogram>,
+}
+
+struct ProcessHistogram {
+    time: f64,
+    total_count: u64,
+    counts: BTreeMap<u64, u64>,
+}
+
+// Percentiles of the distribution obtained by adding up the buckets every process reported for a
+// `(Group, Op)` in the same interval. Unlike per-machine percentiles, these describe the whole
+// role, zone or cluster. Each percentile is exported under a `quantile` label alongside the
+// `MergeScope` labels.
+pub struct MergedHistogramPercentileFDBGauge {
+    group: String,
+    op: String,
+    percentiles: Vec<f64>,
+    estimator: PercentileEstimator,
+    scope: MergeScope,
+    gauge: Gauge<f64>,
+    groups: Mutex<HashMap<LabelKey, MergeGroup>>,
+}
+
+impl MergedHistogramPercentileFDBGauge {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        group: impl Into<String>,
+        op: impl Into<String>,
+        percentiles: Vec<f64>,
+        estimator: PercentileEstimator,
+        scope: MergeScope,
+        gauge_name: impl Into<String>,
+        description: impl Into<String>,
+        meter: &Meter,
+    ) -> Self {
+        Self {
+            group: group.into(),
+            op: op.into(),
+            percentiles,
+            estimator,
+            scope,
+            gauge: meter
+                .f64_gauge(gauge_name.into())
+                .with_description(description.into())
+                .init(),
+            groups: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // The scope labels of a process, or `None` if it lacks one of them.
+    fn group_labels(&self, labels: &[KeyValue]) -> Option<Vec<KeyValue>> {
+        self.scope
+            .labels
+            .iter()
+            .map(|name| {
+                labels
+                    .iter()
+                    .find(|label| label.key.as_str() == name)
+                    .cloned()
+            })
+            .collect()
+    }
+}
+
+impl MergeGroup {
+    // Replace the process's histogram, forget processes that stopped reporting, and return the
+    // merged counts of the rest.
+    fn merge(
+        &mut self,
+        process: LabelKey,
+        histogram: ProcessHistogram,
+        interval_seconds: f64,
+    ) -> (u64, BTreeMap<u64, u64>) {
+        self.processes.insert(process, histogram);
+        let newest = self
+            .processes
+            .values()
+            .map(|histogram| histogram.time)
+            .fold(f64::NEG_INFINITY, f64::max);
+        self.processes
+            .retain(|_, histogram| newest - histogram.time <= interval_seconds);
+
+        let mut total_count = 0;
+        let mut counts = BTreeMap::new();
+        for histogram in self.processes.values() {
+            total_count += histogram.total_count;
+            for (upper_bound, count) in &histogram.counts {
+                *counts.entry(*upper_bound).or_default() += count;
+            }
+        }
+        (total_count, counts)
+    }
+}
+
+impl FDBMetric for MergedHistogramPercentileFDBGauge {
+    fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
+        let Some(histogram) = parse_histogram_event(trace_event, &self.group, &self.op)? else {
+            return Ok(());
+        };
+        let Some(group_labels) = self.group_labels(labels) else {
+            return Ok(());
+        };
+        let time = get_trace_field(trace_event, "Time")?.parse::<f64>()?;
+
+        let (total_count, counts) = self
+            .groups
+            .lock()
+            .expect("merged histograms poisoned")
+            .entry(LabelKey::from_labels(&group_labels))
+            .or_default()
+            .merge(
+                LabelKey::from_labels(labels),
+                ProcessHistogram {
+                    time,
+                    total_count: histogram.total_count,
+                    counts: histogram.counts,
+                },
+                self.scope.interval_seconds,
+            );
+
+        let buckets = doubling_buckets(&counts);
+        for &percentile in &self.percentiles {
+            if let Some(value) =
+                self.estimator
+                    .estimate(&buckets, total_count, percentile, histogram.unit.divisor())
+            {
+                let mut quantile_labels = group_labels.clone();
+                quantile_labels.push(KeyValue::new("quantile", percentile.to_string()));
+                self.gauge.record(value, &quantile_labels);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::metrics::{find_metric, find_metric_in, prometheus_meter};
+    use opentelemetry_prometheus::exporter as prometheus_exporter;
+    use opentelemetry_sdk::metrics::SdkMeterProvider;
+    use prometheus::Registry;
+
+    fn histogram_event(buckets: &[(&str, &str)]) -> HashMap<String, Value> {
+        let mut event: HashMap<String, Value> = [
+            ("Type", "Histogram"),
+            ("Group", "CommitProxy"),
+            ("Op", "TlogLogging"),
+            ("Unit", "milliseconds"),
+        ]
+        .into_iter()
+        .map(|(key, value)| (key.to_string(), Value::String(value.to_string())))
+        .collect();
+        let total: u64 = buckets
+            .iter()
+            .map(|(_, count)| count.parse::<u64>().unwrap())
+            .sum();
+        event.insert("TotalCount".into(), Value::String(total.to_string()));
+        for (bucket, count) in buckets {
+            event.insert(
+                format!("LessThan{bucket}"),
+                Value::String(count.to_string()),
+            );
+        }
+        event
+    }
+
+    #[test]
+    fn native_histogram_exports_cumulative_buckets_in_seconds() {
+        let histograms = NativeHistograms::default();
+        let registry = Registry::new();
+        let reader = prometheus_exporter()
+            .with_registry(registry.clone())
+            .with_producer(histograms.clone())
+            .build()
+            .expect("prometheus exporter");
+        let _provider = SdkMeterProvider::builder().with_reader(reader).build();
+
+        let gauge = NativeHistogramFDBGauge::new(
+            "CommitProxy",
+            "TlogLogging",
+            "cp_tlog_logging_latency_seconds",
+            "Commit proxy TLog logging latency",
+            histograms.clone(),
+        );
+        let labels = [KeyValue::new("machine", "10.0.0.1:4500")];
+        gauge
+            .record(&histogram_event(&[("1.024", "3"), ("4.096", "1")]), &labels)
+            .expect("record first interval");
+        gauge
+            .record(&histogram_event(&[("1.024", "2")]), &labels)
+            .expect("record second interval");
+
+        let metric = find_metric(
+            &registry,
+            "cp_tlog_logging_latency_seconds",
+            "machine",
+            "10.0.0.1:4500",
+        )
+        .expect("native histogram");
+        let histogram = metric.get_histogram();
+        assert_eq!(histogram.get_sample_count(), 6);
+        let buckets: Vec<(f64, u64)> = histogram
+            .get_bucket()
+            .iter()
+            .map(|bucket| (bucket.get_upper_bound(), bucket.get_cumulative_count()))
+            .collect();
+        assert_eq!(buckets.len(), FDB_BUCKET_BOUNDS as usize);
+        assert_eq!(buckets[0], (0.000001, 0));
+        assert_eq!(buckets[9], (0.000512, 0));
+        assert_eq!(
+            buckets[10..13],
+            [(0.001024, 5), (0.002048, 5), (0.004096, 6)]
+        );
+        assert_eq!(buckets[32], (4294.967296, 6));
+        let expected_sum = 5.0 * 0.000768 + 0.003072;
+        assert!((histogram.get_sample_sum() - expected_sum).abs() < 1e-12);
+
+        // Counts are cumulative, so a later scrape still sees them.
+        let again = find_metric(
+            &registry,
+            "cp_tlog_logging_latency_seconds",
+            "machine",
+            "10.0.0.1:4500",
+        )
+        .expect("native histogram on second scrape");
+        assert_eq!(again.get_histogram().get_sample_count(), 6);
+    }
+
+    #[test]
+    fn native_histogram_series_share_one_bucket_layout() {
+        let histograms = NativeHistograms::default();
+        let gauge = NativeHistogramFDBGauge::new(
+            "CommitProxy",
+            "TlogLogging",
+            "cp_tlog_logging_latency_seconds",
+            "Commit proxy TLog logging latency",
+            histograms.clone(),
+        );
+        gauge
+            .record(
+                &histogram_event(&[("0.004", "3")]),
+                &[KeyValue::new("machine", "10.0.0.1:4500")],
+            )
+            .expect("record first machine");
+        gauge
+            .record(
+                &histogram_event(&[("1.024", "1"), ("8192.0", "2")]),
+                &[KeyValue::new("machine", "10.0.0.2:4500")],
+            )
+            .expect("record second machine");
+
+        let produced = histograms.produce().expect("produce");
+        let histogram = produced.metrics[0]
+            .data
+            .as_any()
+            .downcast_ref::<data::Histogram<f64>>()
+            .expect("histogram data");
+        let [first, second] = histogram.data_points.as_slice() else {
+            panic!("expected two series");
+        };
+        assert_eq!(first.bounds, second.bounds);
+        assert_eq!(first.bounds.len(), FDB_BUCKET_BOUNDS as usize);
+        assert_eq!(first.bucket_counts.len(), second.bucket_counts.len());
+    }
+
+    #[test]
+    fn native_histogram_ignores_other_ops() {
+        let histograms = NativeHistograms::default();
+        let gauge = NativeHistogramFDBGauge::new(
+            "CommitProxy",
+            "Other",
+            "cp_other_latency_seconds",
+            "Other latency",
+            histograms.clone(),
+        );
+        gauge
+            .record(&histogram_event(&[("1.024", "3")]), &[])
+            .expect("other ops are skipped");
+        let produced = histograms.produce().expect("produce");
+        assert!(produced.metrics.is_empty());
+    }
+
+    #[test]
+    fn merged_percentiles_combine_processes_per_label() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let gauge = MergedHistogramPercentileFDBGauge::new(
+            "CommitProxy",
+            "TlogLogging",
+            vec![0.5, 0.95],
+            PercentileEstimator::UpperBound,
+            MergeScope {
+                labels: vec!["zone".into()],
+                interval_seconds: 10.0,
+            },
+            "cp_tlog_logging_latency_merged",
+            "Commit proxy TLog logging latency",
+            &meter,
+        );
+        let process = |machine: &str, zone: &str| {
+            [
+                KeyValue::new("machine", machine.to_string()),
+                KeyValue::new("zone", zone.to_string()),
+            ]
+        };
+        let at = |time: &str, buckets: &[(&str, &str)]| {
+            let mut event = histogram_event(buckets);
+            event.insert("Time".into(), Value::String(time.into()));
+            event
+        };
+
+        gauge
+            .record(
+                &at("100.0", &[("1.024", "90")]),
+                &process("10.0.0.1:4500", "a"),
+            )
+            .expect("first process");
+        gauge
+            .record(
+                &at("101.0", &[("65.536", "10")]),
+                &process("10.0.0.2:4500", "a"),
+            )
+            .expect("second process");
+        gauge
+            .record(
+                &at("101.5", &[("65.536", "5")]),
+                &process("10.0.0.3:4500", "b"),
+            )
+            .expect("other zone");
+
+        // Zone `a` merges both processes: 90 fast and 10 slow observations.
+        let families = registry.gather();
+        let zone_a: Vec<(String, f64)> = families
+            .iter()
+            .find(|family| family.get_name() == "cp_tlog_logging_latency_merged")
+            .expect("merged gauge")
+            .get_metric()
+            .iter()
+            .filter(|metric| {
+                metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_name() == "zone" && label.get_value() == "a")
+            })
+            .map(|metric| {
+                let quantile = metric
+                    .get_label()
+                    .iter()
+                    .find(|label| label.get_name() == "quantile")
+                    .expect("quantile label")
+                    .get_value()
+                    .to_string();
+                (quantile, metric.get_gauge().get_value())
+            })
+            .collect();
+        assert!(
+            zone_a.contains(&("0.5".to_string(), 0.001024)),
+            "{zone_a:?}"
+        );
+        assert!(
+            zone_a.contains(&("0.95".to_string(), 0.065536)),
+            "{zone_a:?}"
+        );
+        let zone_b = find_metric_in(&families, "cp_tlog_logging_latency_merged", "zone", "b")
+            .expect("zone b");
+        assert_eq!(zone_b.get_gauge().get_value(), 0.065536);
+
+        // Once the slow process stops reporting, its last interval no longer counts.
+        gauge
+            .record(
+                &at("115.0", &[("1.024", "90")]),
+                &process("10.0.0.1:4500", "a"),
+            )
+            .expect("later interval");
+        let metric = find_metric(
+            &registry,
+            "cp_tlog_logging_latency_merged",
+            "quantile",
+            "0.95",
+        )
+        .expect("p95 after the slow process went quiet");
+        assert_eq!(metric.get_gauge().get_value(), 0.001024);
+    }
+}
@@ -2,8 +2,134 @@ use anyhow::Result;
 use opentelemetry::KeyValue;
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Common interface for FoundationDB metrics that can process trace events.
 pub trait FDBMetric: Send + Sync {
     fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()>;
 }
+
+// Label value used for field values outside a label's allowlist.
+const OTHER_LABEL_VALUE: &str = "other";
+
+// A trace event field promoted to a label named `name`. When `allow` is non-empty, values outside
+// it are reported as `other` so an unexpected field value cannot create unbounded series.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldLabel {
+    pub field: String,
+    pub name: String,
+    pub allow: Vec<String>,
+}
+
+impl FieldLabel {
+    fn label(&self, trace_event: &HashMap<String, Value>) -> Option<KeyValue> {
+        let value = trace_event.get(&self.field)?.as_str()?;
+        let value = if self.allow.is_empty() || self.allow.iter().any(|allowed| allowed == value) {
+            value.to_string()
+        } else {
+            OTHER_LABEL_VALUE.to_string()
+        };
+        Some(KeyValue::new(self.name.clone(), value))
+    }
+}
+
+// Record into `metric` with the configured field labels appended to the event's labels, replacing
+// any of the same name. Fields an event does not carry are left out.
+pub struct FieldLabeledMetric {
+    metric: Arc<dyn FDBMetric>,
+    field_labels: Vec<FieldLabel>,
+}
+
+impl FieldLabeledMetric {
+    pub fn new(metric: Arc<dyn FDBMetric>, field_labels: Vec<FieldLabel>) -> Self {
+        Self {
+            metric,
+            field_labels,
+        }
+    }
+}
+
+impl FDBMetric for FieldLabeledMetric {
+    fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
+        let mut labels = labels.to_vec();
+        for label in self
+            .field_labels
+            .iter()
+            .filter_map(|field_label| field_label.label(trace_event))
+        {
+            match labels.iter_mut().find(|existing| existing.key == label.key) {
+                Some(existing) => *existing = label,
+                None => labels.push(label),
+            }
+        }
+        self.metric.record(trace_event, &labels)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct RecordedLabels(Mutex<Vec<Vec<KeyValue>>>);
+
+    impl FDBMetric for RecordedLabels {
+        fn record(&self, _trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
+            self.0.lock().unwrap().push(labels.to_vec());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn field_labels_are_renamed_and_limited_to_allowlist() {
+        let recorded = Arc::new(RecordedLabels::default());
+        let metric = FieldLabeledMetric::new(
+            recorded.clone(),
+            vec![
+                FieldLabel {
+                    field: "ID".into(),
+                    name: "ss_id".into(),
+                    allow: Vec::new(),
+                },
+                FieldLabel {
+                    field: "Tag".into(),
+                    name: "Tag".into(),
+                    allow: vec!["0:1".into()],
+                },
+            ],
+        );
+        let machine = [KeyValue::new("machine", "10.0.0.1:4500")];
+
+        for (id, tag) in [("a1b2", "0:1"), ("c3d4", "0:7")] {
+            let event: HashMap<String, Value> = [
+                ("ID".to_string(), Value::String(id.into())),
+                ("Tag".to_string(), Value::String(tag.into())),
+            ]
+            .into_iter()
+            .collect();
+            metric.record(&event, &machine).expect("record");
+        }
+        metric
+            .record(&HashMap::new(), &machine)
+            .expect("record event without the fields");
+
+        let recorded = recorded.0.lock().unwrap();
+        assert_eq!(
+            *recorded,
+            [
+                vec![
+                    KeyValue::new("machine", "10.0.0.1:4500"),
+                    KeyValue::new("ss_id", "a1b2"),
+                    KeyValue::new("Tag", "0:1"),
+                ],
+                vec![
+                    KeyValue::new("machine", "10.0.0.1:4500"),
+                    KeyValue::new("ss_id", "c3d4"),
+                    KeyValue::new("Tag", "other"),
+                ],
+                vec![KeyValue::new("machine", "10.0.0.1:4500")],
+            ]
+        );
+    }
+}
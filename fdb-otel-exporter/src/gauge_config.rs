@@ -1,3 +1,7 @@
+use crate::fdb_counter::FieldPredicate;
+use crate::fdb_gauge::{CounterComponent, PercentileEstimator};
+use crate::fdb_metric::FieldLabel;
+use crate::log_sources::RESERVED_LABELS;
 use anyhow::{bail, Context, Result};
 use serde::de::{self, Deserializer};
 use serde::Deserialize;
@@ -11,6 +15,7 @@ enum GaugeType {
     Simple,
     CounterTotal,
     CounterRate,
+    CounterMonotonic,
     ElapsedRate,
 }
 
@@ -20,6 +25,7 @@ impl GaugeType {
             "simple_gauge" => Some(Self::Simple),
             "counter_total_gauge" => Some(Self::CounterTotal),
             "counter_rate_gauge" => Some(Self::CounterRate),
+            "counter_monotonic_gauge" => Some(Self::CounterMonotonic),
             "elapsed_rate_gauge" => Some(Self::ElapsedRate),
             _ => None,
         }
@@ -85,6 +91,26 @@ pub struct StandardGaugeDefinition {
     pub gauge_name: String,
     pub field_name: String,
     pub description: String,
+    pub labels: Vec<FieldLabel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CounterGaugeDefinition {
+    pub trace_type: String,
+    pub field_name: String,
+    pub gauge_name: String,
+    pub description: String,
+    pub components: Vec<CounterComponent>,
+    pub labels: Vec<FieldLabel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EventCounterDefinition {
+    pub trace_type: Option<String>,
+    pub predicates: Vec<FieldPredicate>,
+    pub counter_name: String,
+    pub description: String,
+    pub labels: Vec<FieldLabel>,
 }
 
 #[derive(Debug, Clone)]
@@ -92,8 +118,45 @@ pub struct HistogramPercentileGaugeDefinition {
     pub group: String,
     pub op: String,
     pub percentile: f64,
+    pub estimator: PercentileEstimator,
+    pub window_seconds: Option<f64>,
+    pub gauge_name: String,
+    pub description: String,
+    pub labels: Vec<FieldLabel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct HistogramSummaryGaugeDefinition {
+    pub group: String,
+    pub op: String,
+    pub percentiles: Vec<f64>,
+    pub estimator: PercentileEstimator,
+    pub window_seconds: Option<f64>,
+    pub gauge_name: String,
+    pub description: String,
+    pub labels: Vec<FieldLabel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MergedHistogramPercentileGaugeDefinition {
+    pub group: String,
+    pub op: String,
+    pub percentiles: Vec<f64>,
+    pub estimator: PercentileEstimator,
+    pub merge_by: Vec<String>,
+    pub interval_seconds: f64,
+    pub gauge_name: String,
+    pub description: String,
+    pub labels: Vec<FieldLabel>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NativeHistogramGaugeDefinition {
+    pub group: String,
+    pub op: String,
     pub gauge_name: String,
     pub description: String,
+    pub labels: Vec<FieldLabel>,
 }
 
 #[derive(Debug, Clone)]
@@ -101,8 +164,94 @@ pub enum GaugeDefinition {
     Simple(StandardGaugeDefinition),
     CounterTotal(StandardGaugeDefinition),
     CounterRate(StandardGaugeDefinition),
+    CounterMonotonic(StandardGaugeDefinition),
+    Counter(CounterGaugeDefinition),
     ElapsedRate(StandardGaugeDefinition),
     HistogramPercentile(HistogramPercentileGaugeDefinition),
+    HistogramSummary(HistogramSummaryGaugeDefinition),
+    MergedHistogramPercentile(MergedHistogramPercentileGaugeDefinition),
+    NativeHistogram(NativeHistogramGaugeDefinition),
+    EventCounter(EventCounterDefinition),
+}
+
+impl GaugeDefinition {
+    // Trace event fields promoted to labels on this gauge's series.
+    pub fn labels(&self) -> &[FieldLabel] {
+        match self {
+            Self::Simple(definition)
+            | Self::CounterTotal(definition)
+            | Self::CounterRate(definition)
+            | Self::CounterMonotonic(definition)
+            | Self::ElapsedRate(definition) => &definition.labels,
+            Self::Counter(definition) => &definition.labels,
+            Self::HistogramPercentile(definition) => &definition.labels,
+            Self::HistogramSummary(definition) => &definition.labels,
+            Self::MergedHistogramPercentile(definition) => &definition.labels,
+            Self::NativeHistogram(definition) => &definition.labels,
+            Self::EventCounter(definition) => &definition.labels,
+        }
+    }
+
+    // The event type this gauge reads a value from per role instance. Event counters only count
+    // events, so they have none.
+    pub fn instance_type(&self) -> Option<&str> {
+        match self {
+            Self::Simple(definition)
+            | Self::CounterTotal(definition)
+            | Self::CounterRate(definition)
+            | Self::CounterMonotonic(definition)
+            | Self::ElapsedRate(definition) => Some(&definition.trace_type),
+            Self::Counter(definition) => Some(&definition.trace_type),
+            Self::HistogramPercentile(_)
+            | Self::HistogramSummary(_)
+            | Self::MergedHistogramPercentile(_)
+            | Self::NativeHistogram(_) => Some("Histogram"),
+            Self::EventCounter(_) => None,
+        }
+    }
+}
+
+// A trace event field to promote to a label: its name alone, or a table that renames the label and
+// limits it to an allowlist of values.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+enum FieldLabelConfig {
+    Field(String),
+    Detailed {
+        field: String,
+        name: Option<String>,
+        #[serde(default)]
+        allow: Vec<String>,
+    },
+}
+
+// Resolve label names and reject ones the exporter already attaches or that repeat. `ID` is only
+// allowed as the label for the `ID` field itself, which is the label role instances are split by.
+fn field_labels(configs: Vec<FieldLabelConfig>) -> Result<Vec<FieldLabel>> {
+    let mut labels: Vec<FieldLabel> = Vec::with_capacity(configs.len());
+    for config in configs {
+        let label = match config {
+            FieldLabelConfig::Field(field) => FieldLabel {
+                name: field.clone(),
+                field,
+                allow: Vec::new(),
+            },
+            FieldLabelConfig::Detailed { field, name, allow } => FieldLabel {
+                name: name.unwrap_or_else(|| field.clone()),
+                field,
+                allow,
+            },
+        };
+        let role_instance_label = label.name == "ID" && label.field == "ID";
+        if RESERVED_LABELS.contains(&label.name.as_str()) && !role_instance_label {
+            bail!("label {} is reserved", label.name);
+        }
+        if labels.iter().any(|existing| existing.name == label.name) {
+            bail!("label {} is listed more than once", label.name);
+        }
+        labels.push(label);
+    }
+    Ok(labels)
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -111,6 +260,49 @@ struct GaugeConfigEntry {
     gauge_name: String,
     field_name: String,
     description: String,
+    #[serde(default)]
+    labels: Vec<FieldLabelConfig>,
+}
+
+fn default_counter_components() -> Vec<CounterComponent> {
+    vec![
+        CounterComponent::Rate,
+        CounterComponent::Roughness,
+        CounterComponent::Total,
+    ]
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CounterGaugeConfigEntry {
+    trace_type: String,
+    gauge_name: String,
+    field_name: String,
+    description: String,
+    #[serde(default = "default_counter_components")]
+    series: Vec<CounterComponent>,
+    #[serde(default)]
+    labels: Vec<FieldLabelConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EventCounterConfigEntry {
+    trace_type: Option<String>,
+    #[serde(default, rename = "where")]
+    predicates: Vec<String>,
+    counter_name: String,
+    description: String,
+    #[serde(default)]
+    labels: Vec<FieldLabelConfig>,
+}
+
+// How a histogram gauge entry publishes its percentiles: one gauge name per percentile, or a single
+// summary-style gauge with a `quantile` label and `_count`/`_sum` series.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum PercentileOutput {
+    #[default]
+    Gauges,
+    Summary,
 }
 
 #[derive(Debug, Clone, Deserialize)]
@@ -121,6 +313,46 @@ struct HistogramGaugeConfigEntry {
     percentiles: Vec<f64>,
     gauge_name: String,
     description: String,
+    #[serde(default)]
+    output: PercentileOutput,
+    #[serde(default)]
+    estimator: PercentileEstimator,
+    window_seconds: Option<f64>,
+    #[serde(default)]
+    labels: Vec<FieldLabelConfig>,
+}
+
+// FDB's default `knob_histogram_report_interval`.
+fn default_merge_interval_seconds() -> f64 {
+    30.0
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct MergedHistogramGaugeConfigEntry {
+    group: String,
+    op: String,
+    #[serde(deserialize_with = "deserialize_percentiles")]
+    percentiles: Vec<f64>,
+    gauge_name: String,
+    description: String,
+    #[serde(default)]
+    estimator: PercentileEstimator,
+    #[serde(default)]
+    merge_by: Vec<String>,
+    #[serde(default = "default_merge_interval_seconds")]
+    interval_seconds: f64,
+    #[serde(default)]
+    labels: Vec<FieldLabelConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct NativeHistogramGaugeConfigEntry {
+    group: String,
+    op: String,
+    gauge_name: String,
+    description: String,
+    #[serde(default)]
+    labels: Vec<FieldLabelConfig>,
 }
 
 // Read `gauge_config.toml` from disk and return the normalized gauge definitions.
@@ -184,7 +416,47 @@ fn parse_typed_gauge_configs(value: &Value, toml_config: &Path) -> Result<Vec<Ga
                         percentiles,
                         gauge_name,
                         description,
+                        output,
+                        estimator,
+                        window_seconds,
+                        labels,
                     } = entry;
+                    let labels = field_labels(labels).with_context(|| {
+                        format!(
+                            "invalid labels in {} entry {} in {}",
+                            section,
+                            index,
+                            toml_config.display()
+                        )
+                    })?;
+
+                    if let Some(window_seconds) = window_seconds {
+                        if !(window_seconds.is_finite() && window_seconds > 0.0) {
+                            bail!(
+                                "{} entry {} in {} has non-positive window_seconds {}",
+                                section,
+                                index,
+                                toml_config.display(),
+                                window_seconds
+                            );
+                        }
+                    }
+
+                    if output == PercentileOutput::Summary {
+                        gauges.push(GaugeDefinition::HistogramSummary(
+                            HistogramSummaryGaugeDefinition {
+                                group,
+                                op,
+                                percentiles,
+                                estimator,
+                                window_seconds,
+                                gauge_name,
+                                description,
+                                labels,
+                            },
+                        ));
+                        continue;
+                    }
 
                     let total = percentiles.len();
                     let base_gauge_name = gauge_name.clone();
@@ -208,13 +480,218 @@ fn parse_typed_gauge_configs(value: &Value, toml_config: &Path) -> Result<Vec<Ga
                                 group: group.clone(),
                                 op: op.clone(),
                                 percentile,
+                                estimator,
+                                window_seconds,
                                 gauge_name,
                                 description,
+                                labels: labels.clone(),
                             },
                         ));
                     }
                 }
             }
+            "counter_gauge" => {
+                recognized_any = true;
+
+                let array = entries.as_array().with_context(|| {
+                    format!(
+                        "expected {} section to be an array in {}",
+                        section,
+                        toml_config.display()
+                    )
+                })?;
+
+                for (index, entry_value) in array.iter().enumerate() {
+                    let entry: CounterGaugeConfigEntry =
+                        entry_value.clone().try_into().with_context(|| {
+                            format!(
+                                "failed to parse {} entry {} in {}",
+                                section,
+                                index,
+                                toml_config.display()
+                            )
+                        })?;
+
+                    if entry.series.is_empty() {
+                        bail!(
+                            "{} entry {} in {} has an empty series list",
+                            section,
+                            index,
+                            toml_config.display()
+                        );
+                    }
+
+                    let labels = field_labels(entry.labels).with_context(|| {
+                        format!(
+                            "invalid labels in {} entry {} in {}",
+                            section,
+                            index,
+                            toml_config.display()
+                        )
+                    })?;
+
+                    gauges.push(GaugeDefinition::Counter(CounterGaugeDefinition {
+                        trace_type: entry.trace_type,
+                        field_name: entry.field_name,
+                        gauge_name: entry.gauge_name,
+                        description: entry.description,
+                        components: entry.series,
+                        labels,
+                    }));
+                }
+            }
+            "event_counter" => {
+                recognized_any = true;
+
+                let array = entries.as_array().with_context(|| {
+                    format!(
+                        "expected {} section to be an array in {}",
+                        section,
+                        toml_config.display()
+                    )
+                })?;
+
+                for (index, entry_value) in array.iter().enumerate() {
+                    let entry: EventCounterConfigEntry =
+                        entry_value.clone().try_into().with_context(|| {
+                            format!(
+                                "failed to parse {} entry {} in {}",
+                                section,
+                                index,
+                                toml_config.display()
+                            )
+                        })?;
+
+                    let predicates = entry
+                        .predicates
+                        .iter()
+                        .map(|predicate| FieldPredicate::parse(predicate))
+                        .collect::<Result<Vec<_>>>()
+                        .with_context(|| {
+                            format!(
+                                "invalid where clause in {} entry {} in {}",
+                                section,
+                                index,
+                                toml_config.display()
+                            )
+                        })?;
+
+                    let labels = field_labels(entry.labels).with_context(|| {
+                        format!(
+                            "invalid labels in {} entry {} in {}",
+                            section,
+                            index,
+                            toml_config.display()
+                        )
+                    })?;
+
+                    gauges.push(GaugeDefinition::EventCounter(EventCounterDefinition {
+                        trace_type: entry.trace_type,
+                        predicates,
+                        counter_name: entry.counter_name,
+                        description: entry.description,
+                        labels,
+                    }));
+                }
+            }
+            "merged_histogram_percentile_gauge" => {
+                recognized_any = true;
+
+                let array = entries.as_array().with_context(|| {
+                    format!(
+                        "expected {} section to be an array in {}",
+                        section,
+                        toml_config.display()
+                    )
+                })?;
+
+                for (index, entry_value) in array.iter().enumerate() {
+                    let entry: MergedHistogramGaugeConfigEntry =
+                        entry_value.clone().try_into().with_context(|| {
+                            format!(
+                                "failed to parse {} entry {} in {}",
+                                section,
+                                index,
+                                toml_config.display()
+                            )
+                        })?;
+
+                    if !(entry.interval_seconds.is_finite() && entry.interval_seconds > 0.0) {
+                        bail!(
+                            "{} entry {} in {} has non-positive interval_seconds {}",
+                            section,
+                            index,
+                            toml_config.display(),
+                            entry.interval_seconds
+                        );
+                    }
+
+                    let labels = field_labels(entry.labels).with_context(|| {
+                        format!(
+                            "invalid labels in {} entry {} in {}",
+                            section,
+                            index,
+                            toml_config.display()
+                        )
+                    })?;
+
+                    gauges.push(GaugeDefinition::MergedHistogramPercentile(
+                        MergedHistogramPercentileGaugeDefinition {
+                            group: entry.group,
+                            op: entry.op,
+                            percentiles: entry.percentiles,
+                            estimator: entry.estimator,
+                            merge_by: entry.merge_by,
+                            interval_seconds: entry.interval_seconds,
+                            gauge_name: entry.gauge_name,
+                            description: entry.description,
+                            labels,
+                        },
+                    ));
+                }
+            }
+            "native_histogram_gauge" => {
+                recognized_any = true;
+
+                let array = entries.as_array().with_context(|| {
+                    format!(
+                        "expected {} section to be an array in {}",
+                        section,
+                        toml_config.display()
+                    )
+                })?;
+
+                for (index, entry_value) in array.iter().enumerate() {
+                    let entry: NativeHistogramGaugeConfigEntry =
+                        entry_value.clone().try_into().with_context(|| {
+                            format!(
+                                "failed to parse {} entry {} in {}",
+                                section,
+                                index,
+                                toml_config.display()
+                            )
+                        })?;
+
+                    let labels = field_labels(entry.labels).with_context(|| {
+                        format!(
+                            "invalid labels in {} entry {} in {}",
+                            section,
+                            index,
+                            toml_config.display()
+                        )
+                    })?;
+
+                    gauges.push(GaugeDefinition::NativeHistogram(
+                        NativeHistogramGaugeDefinition {
+                            group: entry.group,
+                            op: entry.op,
+                            gauge_name: entry.gauge_name,
+                            description: entry.description,
+                            labels,
+                        },
+                    ));
+                }
+            }
             _ => {
                 let Some(gauge_type) = GaugeType::from_section_name(section) else {
                     continue;
@@ -240,17 +717,28 @@ fn parse_typed_gauge_configs(value: &Value, toml_config: &Path) -> Result<Vec<Ga
                         )
                     })?;
 
+                    let labels = field_labels(entry.labels).with_context(|| {
+                        format!(
+                            "invalid labels in {} entry {} in {}",
+                            section,
+                            index,
+                            toml_config.display()
+                        )
+                    })?;
+
                     let standard = StandardGaugeDefinition {
                         trace_type: entry.trace_type,
                         gauge_name: entry.gauge_name,
                         field_name: entry.field_name,
                         description: entry.description,
+                        labels,
                     };
 
                     gauges.push(match gauge_type {
                         GaugeType::Simple => GaugeDefinition::Simple(standard),
                         GaugeType::CounterTotal => GaugeDefinition::CounterTotal(standard),
                         GaugeType::CounterRate => GaugeDefinition::CounterRate(standard),
+                        GaugeType::CounterMonotonic => GaugeDefinition::CounterMonotonic(standard),
                         GaugeType::ElapsedRate => GaugeDefinition::ElapsedRate(standard),
                     });
                 }
@@ -437,6 +925,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parses_native_histogram_gauges() {
+        let file = write_config(
+            r#"
+            [[native_histogram_gauge]]
+            group = "CommitProxy"
+            op = "TlogLogging"
+            gauge_name = "cp_tlog_logging_latency_seconds"
+            description = "Commit proxy TLog logging latency"
+            "#,
+        );
+
+        let gauges = read_gauge_config_file(file.path()).expect("native histogram should parse");
+        match gauges.as_slice() {
+            [GaugeDefinition::NativeHistogram(def)] => {
+                assert_eq!(def.group, "CommitProxy");
+                assert_eq!(def.op, "TlogLogging");
+                assert_eq!(def.gauge_name, "cp_tlog_logging_latency_seconds");
+            }
+            other => panic!("expected one native histogram gauge, got {other:?}"),
+        }
+    }
+
     #[test]
     fn parses_standard_gauges() {
         let file = write_config(
@@ -453,6 +964,12 @@ mod tests {
             field_name = "BytesDurable"
             description = "Durable bytes"
 
+            [[counter_monotonic_gauge]]
+            trace_type = "StorageMetrics"
+            gauge_name = "ss_bytes_input"
+            field_name = "BytesInput"
+            description = "Input bytes"
+
             [[elapsed_rate_gauge]]
             trace_type = "ProxyMetrics"
             gauge_name = "cp_cpu_util"
@@ -463,7 +980,7 @@ mod tests {
 
         let gauges =
             read_gauge_config_file(file.path()).expect("standard gauges should parse successfully");
-        assert_eq!(gauges.len(), 3, "unexpected number of gauges");
+        assert_eq!(gauges.len(), 4, "unexpected number of gauges");
 
         let simple = gauges
             .iter()
@@ -488,6 +1005,16 @@ mod tests {
         assert_eq!(counter_total.gauge_name, "ss_bytes_durable");
         assert_eq!(counter_total.field_name, "BytesDurable");
 
+        let counter_monotonic = gauges
+            .iter()
+            .find_map(|g| match g {
+                GaugeDefinition::CounterMonotonic(def) => Some(def),
+                _ => None,
+            })
+            .expect("expected monotonic counter definition");
+        assert_eq!(counter_monotonic.gauge_name, "ss_bytes_input");
+        assert_eq!(counter_monotonic.field_name, "BytesInput");
+
         let elapsed_rate = gauges
             .iter()
             .find_map(|g| match g {
@@ -537,6 +1064,287 @@ mod tests {
         }
     }
 
+    #[test]
+    fn summary_output_keeps_percentiles_under_one_name() {
+        let file = write_config(
+            r#"
+            [[histogram_percentile_gauge]]
+            group = "StorageServer"
+            op = "Read"
+            percentiles = [0.5, 0.99]
+            gauge_name = "ss_read_latency_seconds"
+            description = "Read latency"
+            output = "summary"
+            "#,
+        );
+
+        let gauges = read_gauge_config_file(file.path()).expect("summary gauge should parse");
+
+        match gauges.as_slice() {
+            [GaugeDefinition::HistogramSummary(def)] => {
+                assert_eq!(def.percentiles, [0.5, 0.99]);
+                assert_eq!(def.gauge_name, "ss_read_latency_seconds");
+                assert_eq!(def.description, "Read latency");
+            }
+            other => panic!("expected one summary gauge, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_percentile_estimator() {
+        let file = write_config(
+            r#"
+            [[histogram_percentile_gauge]]
+            group = "StorageServer"
+            op = "Read"
+            percentiles = [0.99]
+            gauge_name = "ss_read_latency_seconds"
+            description = "Read latency"
+            estimator = "log_linear"
+            "#,
+        );
+
+        let gauges = read_gauge_config_file(file.path()).expect("estimator should parse");
+
+        match gauges.as_slice() {
+            [GaugeDefinition::HistogramPercentile(def)] => {
+                assert_eq!(def.estimator, PercentileEstimator::LogLinear);
+            }
+            other => panic!("expected one histogram gauge, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_merged_histogram_gauges() {
+        let file = write_config(
+            r#"
+            [[merged_histogram_percentile_gauge]]
+            group = "CommitProxy"
+            op = "TlogLogging"
+            percentiles = [0.99]
+            gauge_name = "cp_tlog_logging_latency_merged"
+            description = "Commit proxy TLog logging latency"
+            merge_by = ["zone"]
+
+            [[merged_histogram_percentile_gauge]]
+            group = "CommitProxy"
+            op = "TlogLogging"
+            percentiles = [0.99]
+            gauge_name = "cp_tlog_logging_latency_cluster"
+            description = "Commit proxy TLog logging latency"
+            interval_seconds = 10.0
+            "#,
+        );
+
+        let gauges = read_gauge_config_file(file.path()).expect("merged gauges should parse");
+
+        match gauges.as_slice() {
+            [GaugeDefinition::MergedHistogramPercentile(zone), GaugeDefinition::MergedHistogramPercentile(cluster)] =>
+            {
+                assert_eq!(zone.merge_by, ["zone"]);
+                assert_eq!(zone.interval_seconds, 30.0);
+                assert!(cluster.merge_by.is_empty());
+                assert_eq!(cluster.interval_seconds, 10.0);
+            }
+            other => panic!("expected two merged histogram gauges, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_and_validates_histogram_window() {
+        let entry = |window: &str| {
+            write_config(&format!(
+                r#"
+                [[histogram_percentile_gauge]]
+                group = "StorageServer"
+                op = "Read"
+                percentiles = [0.99]
+                gauge_name = "ss_read_latency_seconds"
+                description = "Read latency"
+                window_seconds = {window}
+                "#
+            ))
+        };
+
+        let gauges = read_gauge_config_file(entry("60.0").path()).expect("window should parse");
+        match gauges.as_slice() {
+            [GaugeDefinition::HistogramPercentile(def)] => {
+                assert_eq!(def.window_seconds, Some(60.0));
+            }
+            other => panic!("expected one histogram gauge, got {other:?}"),
+        }
+
+        let error = read_gauge_config_file(entry("0.0").path())
+            .expect_err("zero window should be rejected");
+        assert!(
+            error_chain_contains(&error, "non-positive window_seconds"),
+            "unexpected error: {error}"
+        );
+    }
+
+    #[test]
+    fn parses_counter_gauge_series() {
+        let file = write_config(
+            r#"
+            [[counter_gauge]]
+            trace_type = "StorageMetrics"
+            gauge_name = "ss_mutations"
+            field_name = "Mutations"
+            description = "Storage server mutations"
+
+            [[counter_gauge]]
+            trace_type = "StorageMetrics"
+            gauge_name = "ss_bytes_input"
+            field_name = "BytesInput"
+            description = "Storage server input bytes"
+            series = ["roughness"]
+            "#,
+        );
+
+        let gauges = read_gauge_config_file(file.path()).expect("counter gauges should parse");
+
+        match gauges.as_slice() {
+            [GaugeDefinition::Counter(all), GaugeDefinition::Counter(roughness)] => {
+                assert_eq!(
+                    all.components,
+                    [
+                        CounterComponent::Rate,
+                        CounterComponent::Roughness,
+                        CounterComponent::Total
+                    ]
+                );
+                assert_eq!(roughness.components, [CounterComponent::Roughness]);
+            }
+            other => panic!("expected two counter gauges, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_event_counters() {
+        let file = write_config(
+            r#"
+            [[event_counter]]
+            trace_type = "SlowTask"
+            where = ["Duration > 0.5"]
+            labels = ["TaskID"]
+            counter_name = "process_slow_task_500_ms"
+            description = "Slow tasks"
+
+            [[event_counter]]
+            where = ["Severity >= 30"]
+            counter_name = "process_warnings"
+            description = "Warnings"
+            "#,
+        );
+
+        let gauges = read_gauge_config_file(file.path()).expect("event counters should parse");
+
+        match gauges.as_slice() {
+            [GaugeDefinition::EventCounter(slow), GaugeDefinition::EventCounter(warnings)] => {
+                assert_eq!(slow.trace_type.as_deref(), Some("SlowTask"));
+                assert_eq!(
+                    slow.predicates,
+                    [FieldPredicate::parse("Duration > 0.5").unwrap()]
+                );
+                assert_eq!(
+                    slow.labels,
+                    [FieldLabel {
+                        field: "TaskID".into(),
+                        name: "TaskID".into(),
+                        allow: Vec::new(),
+                    }]
+                );
+                assert!(warnings.trace_type.is_none());
+                assert!(warnings.labels.is_empty());
+            }
+            other => panic!("expected two event counters, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_field_labels_with_renames_and_allowlists() {
+        let file = write_config(
+            r#"
+            [[simple_gauge]]
+            trace_type = "StorageMetrics"
+            field_name = "Version"
+            gauge_name = "storage_version"
+            description = "Storage version"
+            labels = ["ID", { field = "Tag", name = "tag", allow = ["0:1", "0:2"] }]
+            "#,
+        );
+
+        let gauges = read_gauge_config_file(file.path()).expect("labels should parse");
+
+        assert_eq!(
+            gauges[0].labels(),
+            [
+                FieldLabel {
+                    field: "ID".into(),
+                    name: "ID".into(),
+                    allow: Vec::new(),
+                },
+                FieldLabel {
+                    field: "Tag".into(),
+                    name: "tag".into(),
+                    allow: vec!["0:1".into(), "0:2".into()],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_reserved_and_duplicate_field_labels() {
+        for (labels, needle) in [
+            (r#"[{ field = "Machine", name = "machine" }]"#, "reserved"),
+            (
+                r#"[{ field = "Percentile", name = "quantile" }]"#,
+                "reserved",
+            ),
+            (r#"[{ field = "Bucket", name = "le" }]"#, "reserved"),
+            (r#"[{ field = "UID", name = "ID" }]"#, "reserved"),
+            (
+                r#"["Tag", { field = "OtherTag", name = "Tag" }]"#,
+                "more than once",
+            ),
+        ] {
+            let file = write_config(&format!(
+                r#"
+                [[simple_gauge]]
+                trace_type = "StorageMetrics"
+                field_name = "Version"
+                gauge_name = "storage_version"
+                description = "Storage version"
+                labels = {labels}
+                "#
+            ));
+
+            let err = read_gauge_config_file(file.path()).expect_err("labels should be rejected");
+            assert!(
+                error_chain_contains(&err, needle),
+                "expected `{needle}` error, got {err:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_event_counter_predicates() {
+        let file = write_config(
+            r#"
+            [[event_counter]]
+            where = ["Severity"]
+            counter_name = "process_events"
+            description = "Events"
+            "#,
+        );
+
+        let error = read_gauge_config_file(file.path()).expect_err("predicate without operator");
+        assert!(
+            error_chain_contains(&error, "has no comparison operator"),
+            "unexpected error: {error}"
+        );
+    }
+
     #[test]
     fn errors_when_no_recognized_sections() {
         let file = write_config(
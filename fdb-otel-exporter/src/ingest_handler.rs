@@ -0,0 +1,272 @@
+use crate::{
+    exporter_metrics::ExporterMetrics,
+    log_metrics::{LogMetrics, TraceEvent},
+    otlp_logs::otlp_logs_handler,
+};
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Arc;
+
+// At most this many per-line errors are echoed back in a response; the rest are only counted.
+const MAX_REPORTED_ERRORS: usize = 20;
+
+// Shared state for the push routes, which record trace events sent by processes or log shippers
+// whose log directory the exporter cannot read.
+#[derive(Clone)]
+pub struct IngestState {
+    token: Arc<str>,
+    max_body_bytes: usize,
+    metrics: Arc<LogMetrics>,
+    exporter_metrics: ExporterMetrics,
+}
+
+impl IngestState {
+    pub fn new(
+        token: &str,
+        max_body_bytes: usize,
+        metrics: LogMetrics,
+        exporter_metrics: ExporterMetrics,
+    ) -> Self {
+        Self {
+            token: token.into(),
+            max_body_bytes,
+            metrics: Arc::new(metrics),
+            exporter_metrics,
+        }
+    }
+
+    pub(crate) fn max_body_bytes(&self) -> usize {
+        self.max_body_bytes
+    }
+
+    pub(crate) fn is_authorized(&self, headers: &HeaderMap) -> bool {
+        headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|presented| constant_time_eq(presented.as_bytes(), self.token.as_bytes()))
+    }
+
+    // Record one pushed event, counting it as processed or as a record error.
+    pub(crate) fn record(&self, trace_event: &TraceEvent) -> Result<()> {
+        match self.metrics.record(trace_event) {
+            Ok(()) => {
+                self.exporter_metrics.record_processed();
+                Ok(())
+            }
+            Err(error) => {
+                self.exporter_metrics.record_record_error();
+                Err(error)
+            }
+        }
+    }
+
+    pub(crate) fn record_parse_error(&self) {
+        self.exporter_metrics.record_parse_error();
+    }
+}
+
+// Outcome of one pushed batch, returned as the response body.
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct IngestSummary {
+    pub accepted: u64,
+    pub rejected: u64,
+    pub errors: Vec<IngestError>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct IngestError {
+    // 1-based line of the request body the rejected event was on.
+    pub line: usize,
+    pub error: String,
+}
+
+// Routes for pushed trace events: `/ingest` for newline-delimited JSON and `/v1/logs` for OTLP/HTTP
+// log exports. Bodies larger than the configured limit are refused with `413 Payload Too Large`
+// before any event is recorded.
+pub fn ingest_router(state: IngestState) -> Router {
+    let max_body_bytes = state.max_body_bytes;
+    Router::new()
+        .route("/ingest", post(ingest_handler))
+        .route("/v1/logs", post(otlp_logs_handler))
+        .layer(DefaultBodyLimit::max(max_body_bytes))
+        .with_state(state)
+}
+
+// Record a newline-delimited batch of JSON trace events. Each line holds either one event object
+// or an array of them. Events that fail to parse or record are rejected individually while the
+// rest of the batch is still recorded.
+pub async fn ingest_handler(
+    State(state): State<IngestState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !state.is_authorized(&headers) {
+        return (StatusCode::UNAUTHORIZED, "missing or invalid bearer token").into_response();
+    }
+    let body = match String::from_utf8(body.to_vec()) {
+        Ok(body) => body,
+        Err(_) => return (StatusCode::BAD_REQUEST, "body is not valid UTF-8").into_response(),
+    };
+
+    let recorded = tokio::task::spawn_blocking(move || ingest_batch(&body, &state)).await;
+    match recorded {
+        Ok(summary) => (StatusCode::OK, Json(summary)).into_response(),
+        Err(error) => {
+            tracing::error!(?error, "ingest batch failed");
+            (StatusCode::INTERNAL_SERVER_ERROR, "ingest failed").into_response()
+        }
+    }
+}
+
+// Compare without short-circuiting, so response timing does not reveal how much of a guessed
+// token was right.
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len()
+        && left
+            .iter()
+            .zip(right)
+            .fold(0, |diff, (left, right)| diff | (left ^ right))
+            == 0
+}
+
+fn ingest_batch(body: &str, state: &IngestState) -> IngestSummary {
+    let mut summary = IngestSummary::default();
+    for (index, line) in body.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        let events = match serde_json::from_str::<Value>(trimmed) {
+            Ok(Value::Array(events)) => events,
+            Ok(event) => vec![event],
+            Err(error) => {
+                state.record_parse_error();
+                summary.reject(index + 1, error.to_string());
+                continue;
+            }
+        };
+
+        for event in events {
+            let trace_event = match serde_json::from_value::<TraceEvent>(event) {
+                Ok(trace_event) => trace_event,
+                Err(error) => {
+                    state.record_parse_error();
+                    summary.reject(index + 1, error.to_string());
+                    continue;
+                }
+            };
+            match state.record(&trace_event) {
+                Ok(()) => summary.accepted += 1,
+                Err(error) => summary.reject(index + 1, format!("{error:#}")),
+            }
+        }
+    }
+    summary
+}
+
+impl IngestSummary {
+    fn reject(&mut self, line: usize, error: String) {
+        self.rejected += 1;
+        if self.errors.len() < MAX_REPORTED_ERRORS {
+            self.errors.push(IngestError { line, error });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::metrics::prometheus_meter;
+    use crate::test_helpers::recording::RecordingMetric;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    fn app(recorder: &RecordingMetric, max_body_bytes: usize) -> Router {
+        let (_provider, meter, _registry) = prometheus_meter();
+        let metrics = LogMetrics::from_metrics(vec![Arc::new(recorder.clone())]);
+        let state = IngestState::new(
+            "secret",
+            max_body_bytes,
+            metrics,
+            ExporterMetrics::new(&meter),
+        );
+        ingest_router(state)
+    }
+
+    fn request(token: Option<&str>, body: &str) -> Request<Body> {
+        let mut builder = Request::post("/ingest");
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        builder.body(Body::from(body.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn ingest_records_events_and_counts_rejections() {
+        let recorder = RecordingMetric::default();
+        let body = concat!(
+            r#"{"Type":"One","Machine":"10.0.0.1:4500"}"#,
+            "\n\n",
+            r#"[{"Type":"Two","Machine":"10.0.0.1:4500"},{"Type":"NoMachine"}]"#,
+            "\n",
+            "not json\n",
+        );
+
+        let response = app(&recorder, 1024)
+            .oneshot(request(Some("secret"), body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+        let summary: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(summary["accepted"], 2);
+        assert_eq!(summary["rejected"], 2);
+        let lines: Vec<_> = summary["errors"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|error| error["line"].clone())
+            .collect();
+        assert_eq!(lines, [3, 4]);
+        assert_eq!(recorder.types(), ["One", "Two"]);
+    }
+
+    #[tokio::test]
+    async fn ingest_requires_bearer_token() {
+        let recorder = RecordingMetric::default();
+        let body = r#"{"Type":"One","Machine":"10.0.0.1:4500"}"#;
+
+        for token in [None, Some("wrong")] {
+            let response = app(&recorder, 1024)
+                .oneshot(request(token, body))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+        assert!(recorder.types().is_empty());
+    }
+
+    #[tokio::test]
+    async fn ingest_rejects_oversized_bodies() {
+        let recorder = RecordingMetric::default();
+        let body = format!(r#"{{"Type":"{}","Machine":"m"}}"#, "x".repeat(100));
+
+        let response = app(&recorder, 64)
+            .oneshot(request(Some("secret"), &body))
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        assert!(recorder.types().is_empty());
+    }
+}
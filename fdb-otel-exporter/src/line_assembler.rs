@@ -0,0 +1,120 @@
+use std::mem;
+
+// What feeding a chunk into a `LineAssembler` produced.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Assembled {
+    // A complete line, including its trailing newline.
+    Line(Vec<u8>),
+    // The line being assembled grew past the maximum length and is dropped, up to its newline.
+    Oversized,
+    // Nothing to hand out yet.
+    Waiting,
+}
+
+// Joins the chunks a tailer reads into complete lines. FDB can flush part of a line and append the
+// rest later, so data is held back until its trailing newline arrives. Lines longer than
+// `max_line_bytes` (excluding the newline) are dropped instead of being buffered indefinitely.
+#[derive(Debug)]
+pub struct LineAssembler {
+    pending: Vec<u8>,
+    max_line_bytes: usize,
+    discarding: bool,
+}
+
+impl LineAssembler {
+    pub fn new(max_line_bytes: usize) -> Self {
+        Self {
+            pending: Vec::new(),
+            max_line_bytes,
+            discarding: false,
+        }
+    }
+
+    // Most bytes the next read should return, so the pending buffer never exceeds the limit by
+    // more than the newline.
+    pub fn read_limit(&self) -> usize {
+        (self.max_line_bytes + 1)
+            .saturating_sub(self.pending.len())
+            .max(1)
+    }
+
+    // Add the next chunk read from the file. A chunk holds at most one newline, at its end.
+    pub fn push(&mut self, chunk: &[u8]) -> Assembled {
+        let complete = chunk.ends_with(b"\n");
+        if self.discarding {
+            self.discarding = !complete;
+            return Assembled::Waiting;
+        }
+
+        self.pending.extend_from_slice(chunk);
+        let content_len = self.pending.len() - usize::from(complete);
+        if content_len > self.max_line_bytes {
+            self.pending.clear();
+            self.discarding = !complete;
+            Assembled::Oversized
+        } else if complete {
+            Assembled::Line(mem::take(&mut self.pending))
+        } else {
+            Assembled::Waiting
+        }
+    }
+
+    // Whether everything pushed so far ended on a line boundary, so the read offset can be saved.
+    pub fn at_line_boundary(&self) -> bool {
+        self.pending.is_empty() && !self.discarding
+    }
+
+    // Drop any partial line, for when its file was rotated before the line was finished. Returns
+    // whether buffered data was lost.
+    pub fn abandon(&mut self) -> bool {
+        self.discarding = false;
+        let abandoned = !self.pending.is_empty();
+        self.pending.clear();
+        abandoned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn holds_back_partial_lines_until_newline() {
+        let mut assembler = LineAssembler::new(64);
+        assert_eq!(assembler.push(b"{\"Type\":"), Assembled::Waiting);
+        assert!(!assembler.at_line_boundary());
+        assert_eq!(
+            assembler.push(b"\"Test\"}\n"),
+            Assembled::Line(b"{\"Type\":\"Test\"}\n".to_vec())
+        );
+        assert!(assembler.at_line_boundary());
+    }
+
+    #[test]
+    fn drops_oversized_lines_through_their_newline() {
+        let mut assembler = LineAssembler::new(4);
+        assert_eq!(assembler.push(b"abc"), Assembled::Waiting);
+        assert_eq!(assembler.read_limit(), 2);
+        assert_eq!(assembler.push(b"de"), Assembled::Oversized);
+        assert_eq!(assembler.push(b"fgh"), Assembled::Waiting);
+        assert!(!assembler.at_line_boundary());
+        assert_eq!(assembler.push(b"i\n"), Assembled::Waiting);
+        assert!(assembler.at_line_boundary());
+
+        assert_eq!(
+            assembler.push(b"abcd\n"),
+            Assembled::Line(b"abcd\n".to_vec())
+        );
+        assert_eq!(assembler.push(b"abcde\n"), Assembled::Oversized);
+        assert!(assembler.at_line_boundary());
+    }
+
+    #[test]
+    fn abandon_reports_lost_partial_lines() {
+        let mut assembler = LineAssembler::new(64);
+        assert!(!assembler.abandon());
+        assembler.push(b"{\"Type\"");
+        assert!(assembler.abandon());
+        assert!(assembler.at_line_boundary());
+    }
+}
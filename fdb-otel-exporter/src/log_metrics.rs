@@ -1,17 +1,27 @@
 use crate::{
-    fdb_counter::{SevCounter, SlowTaskCounter},
+    fdb_counter::{EventCounter, MonotonicCounter},
     fdb_gauge::{
-        ElapsedRateFDBGauge, HistogramPercentileFDBGauge, RateCounterFDBGauge, SimpleFDBGauge,
+        CounterFDBGauge, ElapsedRateFDBGauge, HistogramPercentileFDBGauge,
+        HistogramSummaryFDBGauge, RateCounterFDBGauge, SimpleFDBGauge, Summaries,
         TotalCounterFDBGauge,
     },
-    fdb_metric::FDBMetric,
+    fdb_histogram::{
+        MergeScope, MergedHistogramPercentileFDBGauge, NativeHistogramFDBGauge, NativeHistograms,
+    },
+    fdb_metric::{FDBMetric, FieldLabel, FieldLabeledMetric},
     gauge_config::{
-        read_gauge_config_file, GaugeDefinition, HistogramPercentileGaugeDefinition,
+        read_gauge_config_file, CounterGaugeDefinition, EventCounterDefinition, GaugeDefinition,
+        HistogramPercentileGaugeDefinition, HistogramSummaryGaugeDefinition,
+        MergedHistogramPercentileGaugeDefinition, NativeHistogramGaugeDefinition,
         StandardGaugeDefinition,
     },
+    role_instances::{collision_counter, RoleInstances},
 };
 use anyhow::{Context, Result};
-use opentelemetry::{metrics::Meter, KeyValue};
+use opentelemetry::{
+    metrics::{Counter, Meter},
+    KeyValue,
+};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -21,23 +31,34 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub struct LogMetrics {
     metrics: Vec<Arc<dyn FDBMetric>>,
+    static_labels: Arc<[KeyValue]>,
 }
 
 impl LogMetrics {
     // Load gauge definitions from `gauge_config.toml` and instantiate their implementations.
-    pub fn new(meter: &Meter) -> Result<Self> {
+    // Native histograms and summaries are accumulated in `histograms` and `summaries`, which are
+    // exported alongside `meter`.
+    pub fn new(
+        meter: &Meter,
+        histograms: &NativeHistograms,
+        summaries: &Summaries,
+    ) -> Result<Self> {
         let config_path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("gauge_config.toml");
         let configs = read_gauge_config_file(&config_path)?;
+        let collisions = collision_counter(meter);
 
-        let mut metrics: Vec<Arc<dyn FDBMetric>> = configs
+        let metrics: Vec<Arc<dyn FDBMetric>> = configs
             .into_iter()
             .map(|config| -> Arc<dyn FDBMetric> {
-                match config {
+                let labels = config.labels().to_vec();
+                let instance_type = config.instance_type().map(str::to_owned);
+                let metric: Arc<dyn FDBMetric> = match config {
                     GaugeDefinition::Simple(StandardGaugeDefinition {
                         trace_type,
                         field_name,
                         gauge_name,
                         description,
+                        ..
                     }) => Arc::new(SimpleFDBGauge::new(
                         trace_type,
                         field_name,
@@ -50,6 +71,7 @@ impl LogMetrics {
                         field_name,
                         gauge_name,
                         description,
+                        ..
                     }) => Arc::new(TotalCounterFDBGauge::new(
                         trace_type,
                         field_name,
@@ -62,6 +84,7 @@ impl LogMetrics {
                         field_name,
                         gauge_name,
                         description,
+                        ..
                     }) => Arc::new(RateCounterFDBGauge::new(
                         trace_type,
                         field_name,
@@ -69,11 +92,40 @@ impl LogMetrics {
                         description,
                         meter,
                     )),
+                    GaugeDefinition::CounterMonotonic(StandardGaugeDefinition {
+                        trace_type,
+                        field_name,
+                        gauge_name,
+                        description,
+                        ..
+                    }) => Arc::new(MonotonicCounter::new(
+                        trace_type,
+                        field_name,
+                        gauge_name,
+                        description,
+                        meter,
+                    )),
+                    GaugeDefinition::Counter(CounterGaugeDefinition {
+                        trace_type,
+                        field_name,
+                        gauge_name,
+                        description,
+                        components,
+                        ..
+                    }) => Arc::new(CounterFDBGauge::new(
+                        trace_type,
+                        field_name,
+                        gauge_name,
+                        description,
+                        &components,
+                        meter,
+                    )),
                     GaugeDefinition::ElapsedRate(StandardGaugeDefinition {
                         trace_type,
                         field_name,
                         gauge_name,
                         description,
+                        ..
                     }) => Arc::new(ElapsedRateFDBGauge::new(
                         trace_type,
                         field_name,
@@ -85,31 +137,119 @@ impl LogMetrics {
                         group,
                         op,
                         percentile,
+                        estimator,
+                        window_seconds,
                         gauge_name,
                         description,
-                    }) => Arc::new(HistogramPercentileFDBGauge::new(
+                        ..
+                    }) => {
+                        let gauge = HistogramPercentileFDBGauge::new(
+                            group,
+                            op,
+                            percentile,
+                            estimator,
+                            gauge_name,
+                            description,
+                            meter,
+                        );
+                        match window_seconds {
+                            Some(window_seconds) => Arc::new(gauge.with_window(window_seconds)),
+                            None => Arc::new(gauge),
+                        }
+                    }
+                    GaugeDefinition::HistogramSummary(HistogramSummaryGaugeDefinition {
                         group,
                         op,
-                        percentile,
+                        percentiles,
+                        estimator,
+                        window_seconds,
+                        gauge_name,
+                        description,
+                        ..
+                    }) => {
+                        let gauge = HistogramSummaryFDBGauge::new(
+                            group,
+                            op,
+                            percentiles,
+                            estimator,
+                            gauge_name,
+                            description,
+                            summaries.clone(),
+                        );
+                        match window_seconds {
+                            Some(window_seconds) => Arc::new(gauge.with_window(window_seconds)),
+                            None => Arc::new(gauge),
+                        }
+                    }
+                    GaugeDefinition::MergedHistogramPercentile(
+                        MergedHistogramPercentileGaugeDefinition {
+                            group,
+                            op,
+                            percentiles,
+                            estimator,
+                            merge_by,
+                            interval_seconds,
+                            gauge_name,
+                            description,
+                            ..
+                        },
+                    ) => Arc::new(MergedHistogramPercentileFDBGauge::new(
+                        group,
+                        op,
+                        percentiles,
+                        estimator,
+                        MergeScope {
+                            labels: merge_by,
+                            interval_seconds,
+                        },
                         gauge_name,
                         description,
                         meter,
                     )),
-                }
+                    GaugeDefinition::NativeHistogram(NativeHistogramGaugeDefinition {
+                        group,
+                        op,
+                        gauge_name,
+                        description,
+                        ..
+                    }) => Arc::new(NativeHistogramFDBGauge::new(
+                        group,
+                        op,
+                        gauge_name,
+                        description,
+                        histograms.clone(),
+                    )),
+                    GaugeDefinition::EventCounter(EventCounterDefinition {
+                        trace_type,
+                        predicates,
+                        counter_name,
+                        description,
+                        ..
+                    }) => Arc::new(EventCounter::new(
+                        trace_type,
+                        predicates,
+                        counter_name,
+                        description,
+                        meter,
+                    )),
+                };
+
+                label_metric(metric, labels, instance_type, &collisions)
             })
             .collect();
 
-        metrics.extend(
-            [10, 20, 30, 40]
-                .into_iter()
-                .map(|severity| Arc::new(SevCounter::new(severity, meter)) as Arc<dyn FDBMetric>),
-        );
-
-        metrics.extend([10, 100, 1000].into_iter().map(|threshold_ms| {
-            Arc::new(SlowTaskCounter::new(threshold_ms, meter)) as Arc<dyn FDBMetric>
-        }));
+        Ok(Self {
+            metrics,
+            static_labels: Arc::new([]),
+        })
+    }
 
-        Ok(Self { metrics })
+    // Share the same metrics, attaching `labels` to every event recorded through the result.
+    pub fn with_static_labels(&self, labels: &[KeyValue]) -> Self {
+        Self {
+            metrics: self.metrics.clone(),
+            static_labels: labels.into(),
+        }
     }
 
     // Record a single FoundationDB trace event across every configured metric.
@@ -129,6 +269,7 @@ impl LogMetrics {
         if let Some(roles) = roles {
             storage_labels.push(KeyValue::new("Roles", roles));
         }
+        storage_labels.extend(self.static_labels.iter().cloned());
 
         for metric in self.metrics.iter() {
             metric.record(trace_event, &storage_labels)?;
@@ -139,10 +280,34 @@ impl LogMetrics {
 
 pub type TraceEvent = HashMap<String, Value>;
 
+// Wrap `metric` so its series are split by role instance on the labels it finally records with,
+// unless it labels its series by the `ID` field itself, then add its field labels.
+fn label_metric(
+    metric: Arc<dyn FDBMetric>,
+    labels: Vec<FieldLabel>,
+    instance_type: Option<String>,
+    collisions: &Counter<u64>,
+) -> Arc<dyn FDBMetric> {
+    let metric: Arc<dyn FDBMetric> = match instance_type {
+        Some(trace_type) if !labels.iter().any(|label| label.field == "ID") => {
+            Arc::new(RoleInstances::new(metric, trace_type, collisions.clone()))
+        }
+        _ => metric,
+    };
+    if labels.is_empty() {
+        metric
+    } else {
+        Arc::new(FieldLabeledMetric::new(metric, labels))
+    }
+}
+
 #[cfg(test)]
 impl LogMetrics {
     pub(crate) fn from_metrics(metrics: Vec<Arc<dyn FDBMetric>>) -> Self {
-        Self { metrics }
+        Self {
+            metrics,
+            static_labels: Arc::new([]),
+        }
     }
 }
 
@@ -181,7 +346,8 @@ mod tests {
     #[test]
     fn new_loads_gauge_config() {
         let meter = test_meter();
-        LogMetrics::new(&meter).expect("should load gauges from config");
+        LogMetrics::new(&meter, &NativeHistograms::default(), &Summaries::default())
+            .expect("should load gauges from config");
     }
 
     #[test]
@@ -227,4 +393,112 @@ mod tests {
             "expected machine label, got {labels:?}"
         );
     }
+
+    #[test]
+    fn record_attaches_static_labels() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(TestGauge::new(Arc::clone(&calls)))];
+        let log_metrics = LogMetrics::from_metrics(metrics)
+            .with_static_labels(&[KeyValue::new("component", "backup_agent")]);
+
+        let mut event = HashMap::new();
+        event.insert("Machine".to_string(), Value::String("10.0.0.1".into()));
+        event.insert("Roles".to_string(), Value::String("BA".into()));
+
+        log_metrics.record(&event).expect("record should succeed");
+
+        let recorded = calls.lock().unwrap();
+        assert_eq!(
+            recorded[0],
+            vec![
+                KeyValue::new("machine", "10.0.0.1"),
+                KeyValue::new("Roles", "BA"),
+                KeyValue::new("component", "backup_agent"),
+            ]
+        );
+    }
+
+    fn storage_metrics(id: &str, tag: &str, time: &str) -> TraceEvent {
+        HashMap::from([
+            ("Machine".to_string(), Value::String("10.0.0.1".into())),
+            ("Type".to_string(), Value::String("StorageMetrics".into())),
+            ("ID".to_string(), Value::String(id.into())),
+            ("Tag".to_string(), Value::String(tag.into())),
+            ("Time".to_string(), Value::String(time.into())),
+        ])
+    }
+
+    fn field_label(field: &str, name: &str) -> FieldLabel {
+        FieldLabel {
+            field: field.into(),
+            name: name.into(),
+            allow: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn record_labels_colliding_role_instances_by_id() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let metric = label_metric(
+            Arc::new(TestGauge::new(Arc::clone(&calls))),
+            vec![field_label("Tag", "Tag")],
+            Some("StorageMetrics".into()),
+            &collision_counter(&test_meter()),
+        );
+        let log_metrics = LogMetrics::from_metrics(vec![metric]);
+
+        for (id, tag, time) in [
+            ("ss1", "0:1", "0.0"),
+            ("ss2", "0:2", "0.5"),
+            ("ss3", "0:1", "1.0"),
+        ] {
+            log_metrics
+                .record(&storage_metrics(id, tag, time))
+                .expect("record should succeed");
+        }
+
+        let recorded = calls.lock().unwrap();
+        assert_eq!(
+            recorded[1],
+            vec![
+                KeyValue::new("machine", "10.0.0.1"),
+                KeyValue::new("Tag", "0:2"),
+            ]
+        );
+        assert_eq!(
+            recorded[2],
+            vec![
+                KeyValue::new("machine", "10.0.0.1"),
+                KeyValue::new("Tag", "0:1"),
+                KeyValue::new("ID", "ss3"),
+            ]
+        );
+    }
+
+    #[test]
+    fn record_leaves_metrics_labeled_by_id_unkeyed() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let metric = label_metric(
+            Arc::new(TestGauge::new(Arc::clone(&calls))),
+            vec![field_label("ID", "ss_id")],
+            Some("StorageMetrics".into()),
+            &collision_counter(&test_meter()),
+        );
+        let log_metrics = LogMetrics::from_metrics(vec![metric]);
+
+        for (id, time) in [("ss1", "0.0"), ("ss2", "0.5")] {
+            log_metrics
+                .record(&storage_metrics(id, "0:1", time))
+                .expect("record should succeed");
+        }
+
+        let recorded = calls.lock().unwrap();
+        assert_eq!(
+            recorded[1],
+            vec![
+                KeyValue::new("machine", "10.0.0.1"),
+                KeyValue::new("ss_id", "ss2"),
+            ]
+        );
+    }
 }
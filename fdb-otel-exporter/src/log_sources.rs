@@ -0,0 +1,285 @@
+use crate::trace_format::TraceFormat;
+use anyhow::{bail, Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
+use opentelemetry::KeyValue;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const DEFAULT_INCLUDE: &[&str] = &["trace.*.json", "trace.*.xml"];
+// Labels the exporter derives from every event, or adds to summary quantiles (`quantile`), native
+// histogram buckets (`le`) and colliding role instances (`ID`); a root may not override them.
+pub(crate) const RESERVED_LABELS: &[&str] = &["machine", "Roles", "quantile", "le", "ID"];
+
+// A directory the exporter discovers trace files under, with the rules for which files to tail
+// and the static labels attached to every event read from them.
+#[derive(Debug, Clone)]
+pub struct LogRoot {
+    pub path: PathBuf,
+    pub recursive: bool,
+    pub filter: FileFilter,
+    pub labels: Vec<KeyValue>,
+    // Format of every file under the root, or `None` to detect it per file.
+    pub format: Option<TraceFormat>,
+}
+
+impl LogRoot {
+    // A non-recursive root tailing `trace.*.json` and `trace.*.xml` files, detecting each file's
+    // format, with no extra labels.
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            recursive: false,
+            filter: FileFilter::new(DEFAULT_INCLUDE, &[] as &[&str])
+                .expect("default patterns are valid"),
+            labels: Vec::new(),
+            format: None,
+        }
+    }
+}
+
+// Include and exclude glob rules. Patterns without a `/` are matched against the file name;
+// patterns with one are matched against the path relative to the root, where `*` stays within a
+// directory and `**` spans directories. A file is tailed if any include rule matches and no
+// exclude rule does.
+#[derive(Debug, Clone)]
+pub struct FileFilter {
+    include: Vec<FilePattern>,
+    exclude: Vec<FilePattern>,
+}
+
+#[derive(Debug, Clone)]
+struct FilePattern {
+    matcher: GlobMatcher,
+    match_path: bool,
+}
+
+impl FilePattern {
+    fn new(pattern: &str) -> Result<Self> {
+        let glob = GlobBuilder::new(pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("invalid file pattern {pattern:?}"))?;
+        Ok(Self {
+            matcher: glob.compile_matcher(),
+            match_path: pattern.contains('/'),
+        })
+    }
+
+    fn matches(&self, relative_path: &Path) -> bool {
+        if self.match_path {
+            self.matcher.is_match(relative_path)
+        } else {
+            relative_path
+                .file_name()
+                .is_some_and(|name| self.matcher.is_match(name))
+        }
+    }
+}
+
+impl FileFilter {
+    pub fn new(include: &[impl AsRef<str>], exclude: &[impl AsRef<str>]) -> Result<Self> {
+        Ok(Self {
+            include: compile_patterns(include)?,
+            exclude: compile_patterns(exclude)?,
+        })
+    }
+
+    pub fn matches(&self, relative_path: &Path) -> bool {
+        self.include
+            .iter()
+            .any(|pattern| pattern.matches(relative_path))
+            && !self
+                .exclude
+                .iter()
+                .any(|pattern| pattern.matches(relative_path))
+    }
+}
+
+fn compile_patterns(patterns: &[impl AsRef<str>]) -> Result<Vec<FilePattern>> {
+    patterns
+        .iter()
+        .map(|pattern| FilePattern::new(pattern.as_ref()))
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogSourcesFile {
+    #[serde(default)]
+    log_root: Vec<LogRootEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogRootEntry {
+    path: PathBuf,
+    #[serde(default)]
+    recursive: bool,
+    include: Option<Vec<String>>,
+    #[serde(default)]
+    exclude: Vec<String>,
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    format: FormatSetting,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum FormatSetting {
+    #[default]
+    Auto,
+    Json,
+    Xml,
+}
+
+// Read the `[[log_root]]` entries of a log sources file.
+pub fn read_log_sources_file(path: &Path) -> Result<Vec<LogRoot>> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("failed to read log sources file {}", path.display()))?;
+    parse_log_sources(&contents)
+        .with_context(|| format!("failed to parse log sources file {}", path.display()))
+}
+
+fn parse_log_sources(contents: &str) -> Result<Vec<LogRoot>> {
+    let file: LogSourcesFile = toml::from_str(contents)?;
+    if file.log_root.is_empty() {
+        bail!("at least one [[log_root]] entry is required");
+    }
+
+    file.log_root
+        .into_iter()
+        .map(|entry| {
+            if let Some(reserved) = entry
+                .labels
+                .keys()
+                .find(|key| RESERVED_LABELS.contains(&key.as_str()))
+            {
+                bail!(
+                    "log root {} sets reserved label {reserved}",
+                    entry.path.display()
+                );
+            }
+
+            let include = entry.include.unwrap_or_else(|| {
+                DEFAULT_INCLUDE
+                    .iter()
+                    .map(|pattern| pattern.to_string())
+                    .collect()
+            });
+            let filter = FileFilter::new(&include, &entry.exclude).with_context(|| {
+                format!("invalid patterns for log root {}", entry.path.display())
+            })?;
+
+            Ok(LogRoot {
+                path: entry.path,
+                recursive: entry.recursive,
+                filter,
+                labels: entry
+                    .labels
+                    .into_iter()
+                    .map(|(key, value)| KeyValue::new(key, value))
+                    .collect(),
+                format: match entry.format {
+                    FormatSetting::Auto => None,
+                    FormatSetting::Json => Some(TraceFormat::Json),
+                    FormatSetting::Xml => Some(TraceFormat::Xml),
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_root_tails_json_and_xml_trace_files() {
+        let root = LogRoot::new(PathBuf::from("/logs"));
+        assert!(root.filter.matches(Path::new("trace.1.json")));
+        assert!(root.filter.matches(Path::new("sub/trace.1.json")));
+        assert!(root.filter.matches(Path::new("trace.1.xml")));
+        assert!(!root.filter.matches(Path::new("trace.1.log")));
+        assert!(!root.filter.matches(Path::new("random.log")));
+    }
+
+    #[test]
+    fn filter_matches_names_and_relative_paths() {
+        let filter = FileFilter::new(&["trace.*.json", "backup/**/*.log"], &["*.tmp.json"])
+            .expect("valid patterns");
+
+        assert!(filter.matches(Path::new("4500/trace.1.json")));
+        assert!(!filter.matches(Path::new("4500/trace.1.tmp.json")));
+        assert!(filter.matches(Path::new("backup/agent-1/out.log")));
+        assert!(!filter.matches(Path::new("other/out.log")));
+    }
+
+    #[test]
+    fn parse_log_sources_reads_roots() {
+        let roots = parse_log_sources(
+            r#"
+            [[log_root]]
+            path = "/var/log/fdb/4500"
+
+            [[log_root]]
+            path = "/var/log/fdb/backup"
+            recursive = true
+            include = ["trace.*.json"]
+            exclude = ["old/**"]
+            labels = { component = "backup_agent" }
+            format = "xml"
+            "#,
+        )
+        .expect("valid log sources");
+
+        assert_eq!(roots.len(), 2);
+        assert_eq!(roots[0].path, PathBuf::from("/var/log/fdb/4500"));
+        assert!(!roots[0].recursive);
+        assert!(roots[0].labels.is_empty());
+        assert_eq!(roots[0].format, None);
+        assert_eq!(roots[1].format, Some(TraceFormat::Xml));
+        assert!(roots[1].recursive);
+        assert!(roots[1].filter.matches(Path::new("new/trace.1.json")));
+        assert!(!roots[1].filter.matches(Path::new("old/trace.1.json")));
+        assert_eq!(
+            roots[1].labels,
+            vec![KeyValue::new("component", "backup_agent")]
+        );
+    }
+
+    #[test]
+    fn parse_log_sources_rejects_reserved_labels_and_bad_patterns() {
+        let error = parse_log_sources(
+            r#"
+            [[log_root]]
+            path = "/logs"
+            labels = { machine = "override" }
+            "#,
+        )
+        .expect_err("reserved label should fail");
+        assert!(
+            error.to_string().contains("reserved label machine"),
+            "unexpected error: {error}"
+        );
+
+        let error = parse_log_sources(
+            r#"
+            [[log_root]]
+            path = "/logs"
+            include = ["trace.[.json"]
+            "#,
+        )
+        .expect_err("invalid glob should fail");
+        assert!(
+            format!("{error:#}").contains("invalid file pattern"),
+            "unexpected error: {error:#}"
+        );
+
+        assert!(parse_log_sources("").is_err());
+    }
+}
@@ -1,13 +1,25 @@
+mod bundle;
+mod change_notifier;
+mod checkpoint;
 mod config;
 mod exporter_metrics;
 mod fdb_counter;
 mod fdb_gauge;
+mod fdb_histogram;
 mod fdb_metric;
 mod gauge_config;
+mod ingest_handler;
+mod line_assembler;
 mod log_metrics;
+mod log_sources;
 mod metrics_handler;
+mod otlp_logs;
+mod pipeline;
+mod replay;
+mod role_instances;
 #[cfg(test)]
 mod test_helpers;
+mod trace_format;
 mod watch_logs;
 
 use std::{
@@ -18,44 +30,122 @@ use std::{
 
 use anyhow::{anyhow, Context, Result};
 use axum::{http::StatusCode, routing::get, Router};
-use config::AppConfig;
-use opentelemetry::KeyValue;
+use checkpoint::CheckpointStore;
+use config::{AppConfig, Command, EventSource};
+use exporter_metrics::ExporterMetrics;
+use fdb_gauge::Summaries;
+use fdb_histogram::NativeHistograms;
+use log_metrics::LogMetrics;
+use opentelemetry::{metrics::MeterProvider, KeyValue};
 use opentelemetry_sdk::{metrics::SdkMeterProvider, Resource};
 use prometheus::Registry;
 use tokio::{net::TcpListener, signal};
 use tracing_subscriber::{fmt, EnvFilter};
 
+use bundle::ingest_bundles;
+use ingest_handler::{ingest_router, IngestState};
 use metrics_handler::{metrics_handler, AppState};
-use watch_logs::watch_logs;
+use watch_logs::{watch_logs, WatchOptions};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize logging, metrics infrastructure, and start watching FDB trace logs.
+    // Initialize logging, metrics infrastructure, and start watching FDB trace logs or ingesting
+    // the bundles or historical logs named on the command line. Replays write their output and
+    // exit without serving.
+    let command = Command::from_args(std::env::args().skip(1))?;
     let config = AppConfig::from_env().context("failed to load exporter configuration")?;
     init_tracing(&config)?;
 
-    let (registry, meter_provider) = init_metrics()?;
-    let meter_provider = Arc::new(meter_provider);
-
-    tracing::info!(log_dir = %config.log_dir.display(), "watching JSON logs directory");
-    if let Err(err) = watch_logs(
-        &config.log_dir,
-        Arc::clone(&meter_provider),
-        config.log_poll_interval,
-    )
-    .await
-    {
-        tracing::error!(?err, "watch_logs failed");
-        return Err(err);
-    }
+    let source = match command {
+        Command::Replay(options) => return replay::run_replay(options).await,
+        Command::Serve(source) => source,
+    };
+
+    let (registry, meter_provider, histograms, summaries) = init_metrics()?;
+    // Tailed, pushed, ingested and played back events all record through one set of metrics, so
+    // their rolling windows, counter totals and role instance state stay consistent.
+    let meter = meter_provider.meter("fdb-otel-exporter");
+    let log_metrics = LogMetrics::new(&meter, &histograms, &summaries)
+        .with_context(|| "failed to load gauge configuration")?;
+    let exporter_metrics = ExporterMetrics::new(&meter);
+
+    let checkpoints = match source {
+        EventSource::Watch => {
+            let checkpoints = match &config.state_dir {
+                Some(state_dir) => {
+                    CheckpointStore::load(state_dir).context("failed to load log checkpoints")?
+                }
+                None => CheckpointStore::disabled(),
+            };
+
+            for root in config.log_roots.iter() {
+                tracing::info!(log_dir = %root.path.display(), recursive = root.recursive, "watching trace logs directory");
+            }
+            let watch_options = WatchOptions {
+                poll_interval: config.log_poll_interval,
+                start_mode: config.start_mode,
+                idle_timeout: config.tailer_idle_timeout,
+                watch_mode: config.watch_mode,
+                max_line_bytes: config.max_line_bytes,
+                pipeline: config.pipeline,
+            };
+            if let Err(err) = watch_logs(
+                &config.log_roots,
+                log_metrics.clone(),
+                exporter_metrics.clone(),
+                &meter,
+                watch_options,
+                checkpoints.clone(),
+            )
+            .await
+            {
+                tracing::error!(?err, "watch_logs failed");
+                return Err(err);
+            }
+            checkpoints
+        }
+        EventSource::Ingest(paths) => {
+            let (metrics, exporter_metrics) = (log_metrics.clone(), exporter_metrics.clone());
+            tokio::spawn(async move {
+                match ingest_bundles(&paths, metrics, exporter_metrics).await {
+                    Ok(()) => tracing::info!(bundles = paths.len(), "bundle ingestion complete"),
+                    Err(error) => tracing::error!(?error, "bundle ingestion failed"),
+                }
+            });
+            CheckpointStore::disabled()
+        }
+        EventSource::Playback(options) => {
+            let (metrics, exporter_metrics) = (log_metrics.clone(), exporter_metrics.clone());
+            tokio::spawn(async move {
+                if let Err(error) = replay::play_back(options, metrics, exporter_metrics).await {
+                    tracing::error!(?error, "playback failed");
+                }
+            });
+            CheckpointStore::disabled()
+        }
+    };
 
     let app_state = AppState::new(registry.clone());
 
-    let app = Router::new()
+    let mut app = Router::new()
         .route("/metrics", get(metrics_handler))
         .route("/health", get(|| async { StatusCode::OK }))
         .with_state(app_state);
 
+    if let Some(ingest) = &config.ingest {
+        let state = IngestState::new(
+            &ingest.token,
+            ingest.max_body_bytes,
+            log_metrics,
+            exporter_metrics,
+        );
+        app = app.merge(ingest_router(state));
+        tracing::info!(
+            max_body_bytes = ingest.max_body_bytes,
+            "accepting pushed trace events on /ingest and /v1/logs"
+        );
+    }
+
     let listener = TcpListener::bind(config.listen_addr).await?;
     tracing::info!("listening on {}", listener.local_addr()?);
 
@@ -63,6 +153,10 @@ async fn main() -> Result<()> {
         .with_graceful_shutdown(shutdown_signal())
         .await?;
 
+    if let Err(error) = checkpoints.flush_in_background().await {
+        tracing::warn!(?error, "failed to persist log checkpoints on shutdown");
+    }
+
     Ok(())
 }
 
@@ -126,12 +220,17 @@ fn init_tracing(config: &AppConfig) -> Result<()> {
     Ok(())
 }
 
-fn init_metrics() -> Result<(Arc<Registry>, SdkMeterProvider)> {
+fn init_metrics() -> Result<(Arc<Registry>, SdkMeterProvider, NativeHistograms, Summaries)> {
     // Build a Prometheus-backed meter provider so OpenTelemetry metrics feed the `/metrics` endpoint.
+    // Summaries have no OTel equivalent and are collected by the registry directly.
     let registry = Registry::new();
+    let histograms = NativeHistograms::default();
+    let summaries = Summaries::default();
+    registry.register(Box::new(summaries.clone()))?;
 
     let exporter = opentelemetry_prometheus::exporter()
         .with_registry(registry.clone())
+        .with_producer(histograms.clone())
         .build()?;
 
     let resource = Resource::new(vec![KeyValue::new("service.name", "fdb-otel-exporter")]);
@@ -139,7 +238,8 @@ fn init_metrics() -> Result<(Arc<Registry>, SdkMeterProvider)> {
     let provider = SdkMeterProvider::builder()
         .with_resource(resource)
         .with_reader(exporter)
+        .with_view(exporter_metrics::event_latency_view()?)
         .build();
 
-    Ok((Arc::new(registry), provider))
+    Ok((Arc::new(registry), provider, histograms, summaries))
 }
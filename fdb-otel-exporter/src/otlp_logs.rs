@@ -0,0 +1,552 @@
+use crate::{ingest_handler::IngestState, log_metrics::TraceEvent, trace_format::TraceFormat};
+use anyhow::{bail, Context, Result};
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use flate2::read::GzDecoder;
+use prost::Message;
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+use std::io::Read;
+
+const PROTOBUF_CONTENT_TYPE: &str = "application/x-protobuf";
+const JSON_CONTENT_TYPE: &str = "application/json";
+
+// The subset of the OTLP logs protocol (`opentelemetry/proto/collector/logs/v1`) the receiver
+// reads. Fields it has no use for are skipped when decoding, in either encoding. The JSON mapping
+// uses lowerCamelCase names and 64-bit integers as decimal strings.
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportLogsServiceRequest {
+    #[prost(message, repeated, tag = "1")]
+    pub resource_logs: Vec<ResourceLogs>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResourceLogs {
+    #[prost(message, repeated, tag = "2")]
+    pub scope_logs: Vec<ScopeLogs>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ScopeLogs {
+    #[prost(message, repeated, tag = "2")]
+    pub log_records: Vec<LogRecord>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LogRecord {
+    #[prost(fixed64, tag = "1")]
+    #[serde(deserialize_with = "json_u64", serialize_with = "serialize_display")]
+    pub time_unix_nano: u64,
+    #[prost(message, optional, tag = "5")]
+    pub body: Option<AnyValue>,
+    #[prost(message, repeated, tag = "6")]
+    pub attributes: Vec<KeyValue>,
+    #[prost(fixed64, tag = "11")]
+    #[serde(deserialize_with = "json_u64", serialize_with = "serialize_display")]
+    pub observed_time_unix_nano: u64,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyValue {
+    #[prost(string, tag = "1")]
+    pub key: String,
+    #[prost(message, optional, tag = "2")]
+    pub value: Option<AnyValue>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(from = "JsonAnyValue", into = "JsonAnyValue")]
+pub struct AnyValue {
+    #[prost(oneof = "any_value::Value", tags = "1, 2, 3, 4, 5, 6")]
+    pub value: Option<any_value::Value>,
+}
+
+pub mod any_value {
+    use super::{ArrayValue, KeyValueList};
+
+    #[derive(Clone, PartialEq, prost::Oneof)]
+    pub enum Value {
+        #[prost(string, tag = "1")]
+        String(String),
+        #[prost(bool, tag = "2")]
+        Bool(bool),
+        #[prost(int64, tag = "3")]
+        Int(i64),
+        #[prost(double, tag = "4")]
+        Double(f64),
+        #[prost(message, tag = "5")]
+        Array(ArrayValue),
+        #[prost(message, tag = "6")]
+        Kvlist(KeyValueList),
+    }
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArrayValue {
+    #[prost(message, repeated, tag = "1")]
+    pub values: Vec<AnyValue>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyValueList {
+    #[prost(message, repeated, tag = "1")]
+    pub values: Vec<KeyValue>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportLogsServiceResponse {
+    #[prost(message, optional, tag = "1")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub partial_success: Option<ExportLogsPartialSuccess>,
+}
+
+#[derive(Clone, PartialEq, Message, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ExportLogsPartialSuccess {
+    #[prost(int64, tag = "1")]
+    #[serde(deserialize_with = "json_i64", serialize_with = "serialize_display")]
+    pub rejected_log_records: i64,
+    #[prost(string, tag = "2")]
+    pub error_message: String,
+}
+
+// JSON form of `AnyValue`, an object with one key naming the variant that is set.
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+struct JsonAnyValue {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    string_value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bool_value: Option<bool>,
+    #[serde(
+        deserialize_with = "json_optional_i64",
+        skip_serializing_if = "Option::is_none"
+    )]
+    int_value: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    double_value: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    array_value: Option<ArrayValue>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    kvlist_value: Option<KeyValueList>,
+}
+
+impl From<JsonAnyValue> for AnyValue {
+    fn from(json: JsonAnyValue) -> Self {
+        use any_value::Value;
+        let value = json
+            .string_value
+            .map(Value::String)
+            .or(json.bool_value.map(Value::Bool))
+            .or(json.int_value.map(Value::Int))
+            .or(json.double_value.map(Value::Double))
+            .or(json.array_value.map(Value::Array))
+            .or(json.kvlist_value.map(Value::Kvlist));
+        Self { value }
+    }
+}
+
+impl From<AnyValue> for JsonAnyValue {
+    fn from(any: AnyValue) -> Self {
+        use any_value::Value;
+        let mut json = Self::default();
+        match any.value {
+            Some(Value::String(value)) => json.string_value = Some(value),
+            Some(Value::Bool(value)) => json.bool_value = Some(value),
+            Some(Value::Int(value)) => json.int_value = Some(value),
+            Some(Value::Double(value)) => json.double_value = Some(value),
+            Some(Value::Array(value)) => json.array_value = Some(value),
+            Some(Value::Kvlist(value)) => json.kvlist_value = Some(value),
+            None => {}
+        }
+        json
+    }
+}
+
+// OTLP/JSON writes 64-bit integers as strings, though some clients send plain numbers.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonInteger {
+    Number(i128),
+    Text(String),
+}
+
+impl JsonInteger {
+    fn parse<T: TryFrom<i128>, E: serde::de::Error>(self) -> Result<T, E> {
+        let value = match self {
+            Self::Number(value) => value,
+            Self::Text(text) => text.parse::<i128>().map_err(E::custom)?,
+        };
+        T::try_from(value).map_err(|_| E::custom(format!("integer {value} out of range")))
+    }
+}
+
+fn json_u64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    JsonInteger::deserialize(deserializer)?.parse()
+}
+
+fn json_i64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    JsonInteger::deserialize(deserializer)?.parse()
+}
+
+fn json_optional_i64<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<i64>, D::Error> {
+    JsonInteger::deserialize(deserializer)?.parse().map(Some)
+}
+
+fn serialize_display<T: ToString, S: serde::Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&value.to_string())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Encoding {
+    Protobuf,
+    Json,
+}
+
+// Receive an OTLP/HTTP log export and record each log record as a trace event. Records that do not
+// yield a recordable event are reported back as a partial success, as the protocol expects.
+pub async fn otlp_logs_handler(
+    State(state): State<IngestState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    if !state.is_authorized(&headers) {
+        return (StatusCode::UNAUTHORIZED, "missing or invalid bearer token").into_response();
+    }
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let encoding = match content_type.split(';').next().map(str::trim) {
+        Some(PROTOBUF_CONTENT_TYPE) => Encoding::Protobuf,
+        Some(JSON_CONTENT_TYPE) => Encoding::Json,
+        _ => {
+            return (
+                StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                "expected application/x-protobuf or application/json",
+            )
+                .into_response()
+        }
+    };
+    let gzipped = match headers
+        .get(header::CONTENT_ENCODING)
+        .and_then(|value| value.to_str().ok())
+    {
+        None | Some("identity") => false,
+        Some("gzip") => true,
+        Some(_) => {
+            return (StatusCode::UNSUPPORTED_MEDIA_TYPE, "expected gzip encoding").into_response()
+        }
+    };
+
+    let exported = tokio::task::spawn_blocking(move || {
+        let body = decompress(body, gzipped, state.max_body_bytes())?;
+        let request = decode_request(&body, encoding)?;
+        Ok::<_, anyhow::Error>(record_logs(&request, &state))
+    })
+    .await;
+
+    let response = match exported {
+        Ok(Ok(response)) => response,
+        Ok(Err(error)) => {
+            return (StatusCode::BAD_REQUEST, format!("{error:#}")).into_response();
+        }
+        Err(error) => {
+            tracing::error!(?error, "OTLP log export failed");
+            return (StatusCode::INTERNAL_SERVER_ERROR, "export failed").into_response();
+        }
+    };
+    match encoding {
+        Encoding::Protobuf => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)],
+            response.encode_to_vec(),
+        )
+            .into_response(),
+        Encoding::Json => (StatusCode::OK, axum::Json(response)).into_response(),
+    }
+}
+
+// Inflate a gzipped body, holding it to the same size limit as uncompressed requests.
+fn decompress(body: Bytes, gzipped: bool, max_body_bytes: usize) -> Result<Bytes> {
+    if !gzipped {
+        return Ok(body);
+    }
+    let mut inflated = Vec::new();
+    GzDecoder::new(body.as_ref())
+        .take(max_body_bytes as u64 + 1)
+        .read_to_end(&mut inflated)
+        .context("failed to decompress request body")?;
+    if inflated.len() > max_body_bytes {
+        bail!("decompressed request body exceeds {max_body_bytes} bytes");
+    }
+    Ok(inflated.into())
+}
+
+fn decode_request(body: &[u8], encoding: Encoding) -> Result<ExportLogsServiceRequest> {
+    match encoding {
+        Encoding::Protobuf => {
+            ExportLogsServiceRequest::decode(body).context("invalid OTLP protobuf request")
+        }
+        Encoding::Json => serde_json::from_slice(body).context("invalid OTLP JSON request"),
+    }
+}
+
+fn record_logs(
+    request: &ExportLogsServiceRequest,
+    state: &IngestState,
+) -> ExportLogsServiceResponse {
+    let mut rejected = 0;
+    let mut first_error = None;
+    let records = request
+        .resource_logs
+        .iter()
+        .flat_map(|resource| resource.scope_logs.iter())
+        .flat_map(|scope| scope.log_records.iter());
+    for record in records {
+        let recorded = trace_event(record)
+            .inspect_err(|_| state.record_parse_error())
+            .and_then(|trace_event| state.record(&trace_event));
+        if let Err(error) = recorded {
+            rejected += 1;
+            first_error.get_or_insert_with(|| format!("{error:#}"));
+        }
+    }
+
+    ExportLogsServiceResponse {
+        partial_success: first_error.map(|error_message| ExportLogsPartialSuccess {
+            rejected_log_records: rejected,
+            error_message,
+        }),
+    }
+}
+
+// Build a trace event from a log record's attributes and body. A string body holding a JSON or XML
+// trace line, or a key/value list body, supplies fields that take precedence over attributes of
+// the same name. Scalar values are stored as strings, as they are in FDB's own trace files, and
+// the record's timestamp fills in `Time` when no field provides it.
+fn trace_event(record: &LogRecord) -> Result<TraceEvent> {
+    let mut event: TraceEvent = fields(&record.attributes).collect();
+    match record.body.as_ref().and_then(|body| body.value.as_ref()) {
+        Some(any_value::Value::String(line)) => {
+            let line = line.trim();
+            if let Some(format) = TraceFormat::sniff(line) {
+                if let Some(body_event) = format.parse_line(line)? {
+                    event.extend(body_event);
+                }
+            }
+        }
+        Some(any_value::Value::Kvlist(list)) => event.extend(fields(&list.values)),
+        _ => {}
+    }
+
+    let timestamp = match record.time_unix_nano {
+        0 => record.observed_time_unix_nano,
+        time => time,
+    };
+    if !event.contains_key("Time") && timestamp > 0 {
+        let seconds = timestamp as f64 / 1e9;
+        event.insert("Time".to_string(), Value::String(format!("{seconds:.6}")));
+    }
+    Ok(event)
+}
+
+fn fields(attributes: &[KeyValue]) -> impl Iterator<Item = (String, Value)> + '_ {
+    attributes.iter().filter_map(|attribute| {
+        let value = field_value(attribute.value.as_ref()?)?;
+        Some((attribute.key.clone(), value))
+    })
+}
+
+fn field_value(any: &AnyValue) -> Option<Value> {
+    use any_value::Value as Any;
+    Some(match any.value.as_ref()? {
+        Any::String(value) => Value::String(value.clone()),
+        Any::Bool(value) => Value::String(value.to_string()),
+        Any::Int(value) => Value::String(value.to_string()),
+        Any::Double(value) => Value::String(value.to_string()),
+        Any::Array(array) => Value::Array(array.values.iter().filter_map(field_value).collect()),
+        Any::Kvlist(list) => Value::Object(fields(&list.values).collect()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exporter_metrics::ExporterMetrics;
+    use crate::ingest_handler::ingest_router;
+    use crate::log_metrics::LogMetrics;
+    use crate::test_helpers::metrics::prometheus_meter;
+    use crate::test_helpers::recording::RecordingMetric;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use axum::Router;
+    use flate2::{write::GzEncoder, Compression};
+    use std::io::Write;
+    use std::sync::Arc;
+    use tower::ServiceExt;
+
+    fn app(recorder: &RecordingMetric) -> Router {
+        let (_provider, meter, _registry) = prometheus_meter();
+        let metrics = LogMetrics::from_metrics(vec![Arc::new(recorder.clone())]);
+        ingest_router(IngestState::new(
+            "secret",
+            64 * 1024,
+            metrics,
+            ExporterMetrics::new(&meter),
+        ))
+    }
+
+    fn string_value(value: &str) -> AnyValue {
+        AnyValue {
+            value: Some(any_value::Value::String(value.to_string())),
+        }
+    }
+
+    fn attribute(key: &str, value: AnyValue) -> KeyValue {
+        KeyValue {
+            key: key.to_string(),
+            value: Some(value),
+        }
+    }
+
+    fn export(log_records: Vec<LogRecord>) -> ExportLogsServiceRequest {
+        ExportLogsServiceRequest {
+            resource_logs: vec![ResourceLogs {
+                scope_logs: vec![ScopeLogs { log_records }],
+            }],
+        }
+    }
+
+    #[tokio::test]
+    async fn protobuf_export_records_bodies_and_attributes() {
+        let recorder = RecordingMetric::default();
+        let request = export(vec![
+            LogRecord {
+                body: Some(string_value(
+                    r#"{"Type":"FromBody","Machine":"10.0.0.1:4500","Time":"100.5"}"#,
+                )),
+                attributes: vec![attribute("Type", string_value("Overridden"))],
+                ..Default::default()
+            },
+            LogRecord {
+                time_unix_nano: 1_700_000_000_250_000_000,
+                attributes: vec![
+                    attribute("Type", string_value("FromAttributes")),
+                    attribute("Machine", string_value("10.0.0.2:4500")),
+                    attribute(
+                        "Elapsed",
+                        AnyValue {
+                            value: Some(any_value::Value::Double(5.0)),
+                        },
+                    ),
+                ],
+                ..Default::default()
+            },
+            LogRecord {
+                body: Some(string_value(r#"{"Type":"NoMachine"}"#)),
+                ..Default::default()
+            },
+        ]);
+
+        let response = app(&recorder)
+            .oneshot(
+                Request::post("/v1/logs")
+                    .header(header::AUTHORIZATION, "Bearer secret")
+                    .header(header::CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)
+                    .body(Body::from(request.encode_to_vec()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+        let response = ExportLogsServiceResponse::decode(body).unwrap();
+        let partial = response.partial_success.expect("partial success");
+        assert_eq!(partial.rejected_log_records, 1);
+        assert!(partial.error_message.contains("Machine"));
+
+        let events = recorder.events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["Type"], "FromBody");
+        assert_eq!(events[0]["Time"], "100.5");
+        assert_eq!(events[1]["Type"], "FromAttributes");
+        assert_eq!(events[1]["Elapsed"], "5");
+        assert_eq!(events[1]["Time"], "1700000000.250000");
+    }
+
+    #[tokio::test]
+    async fn gzipped_json_export_is_recorded() {
+        let recorder = RecordingMetric::default();
+        let json = r#"{"resourceLogs":[{"resource":{"attributes":[]},"scopeLogs":[{"scope":{},
+            "logRecords":[{"timeUnixNano":"1700000000000000000","severityNumber":9,
+            "body":{"kvlistValue":{"values":[
+                {"key":"Type","value":{"stringValue":"Pushed"}},
+                {"key":"Machine","value":{"stringValue":"10.0.0.1:4500"}},
+                {"key":"Count","value":{"intValue":"42"}}]}}}]}]}]}"#;
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(json.as_bytes()).unwrap();
+
+        let response = app(&recorder)
+            .oneshot(
+                Request::post("/v1/logs")
+                    .header(header::AUTHORIZATION, "Bearer secret")
+                    .header(header::CONTENT_TYPE, JSON_CONTENT_TYPE)
+                    .header(header::CONTENT_ENCODING, "gzip")
+                    .body(Body::from(encoder.finish().unwrap()))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = to_bytes(response.into_body(), 64 * 1024).await.unwrap();
+        assert_eq!(body.as_ref(), b"{}");
+        let events = recorder.events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["Type"], "Pushed");
+        assert_eq!(events[0]["Count"], "42");
+        assert_eq!(events[0]["Time"], "1700000000.000000");
+    }
+
+    #[tokio::test]
+    async fn export_requires_token_and_known_content_type() {
+        let recorder = RecordingMetric::default();
+        let unauthorized = app(&recorder)
+            .oneshot(
+                Request::post("/v1/logs")
+                    .header(header::CONTENT_TYPE, PROTOBUF_CONTENT_TYPE)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(unauthorized.status(), StatusCode::UNAUTHORIZED);
+
+        let unsupported = app(&recorder)
+            .oneshot(
+                Request::post("/v1/logs")
+                    .header(header::AUTHORIZATION, "Bearer secret")
+                    .header(header::CONTENT_TYPE, "text/plain")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(unsupported.status(), StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+}
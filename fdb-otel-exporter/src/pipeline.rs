@@ -0,0 +1,280 @@
+use crate::{
+    config::{OverflowPolicy, PipelineOptions},
+    exporter_metrics::{ExporterMetrics, FileProgress},
+    log_metrics::LogMetrics,
+    trace_format::TraceFormat,
+    watch_logs::{handle_log_line, unix_now},
+};
+use anyhow::{bail, Context, Result};
+use opentelemetry::{metrics::Meter, KeyValue};
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    path::Path,
+    sync::Arc,
+    thread,
+};
+use tokio::sync::mpsc::{self, error::TrySendError};
+
+// A trace line read by a tailer, waiting to be parsed and recorded by a worker.
+struct QueuedLine {
+    line: String,
+    format: TraceFormat,
+    metrics: Arc<LogMetrics>,
+    progress: Arc<FileProgress>,
+    since: Option<f64>,
+    record_latency: bool,
+}
+
+// Bounded queues between the tailers and a pool of worker threads that parse and record trace
+// lines, keeping that work off the async runtime the tailers and `/metrics` run on. Every file is
+// assigned to one worker, so its events are recorded in the order they were written, which the
+// rate gauges rely on. When a worker's queue is full the overflow policy applies: `Block` makes the
+// tailer wait, so the exporter falls behind the logs but loses nothing, while `Drop` discards the
+// line so the exporter stays current at the cost of missing events. Either way the overflow is
+// counted. Lines still queued at shutdown are lost, though their offsets may already be saved.
+#[derive(Clone)]
+pub struct EventPipeline {
+    senders: Arc<[mpsc::Sender<QueuedLine>]>,
+    overflow: OverflowPolicy,
+    exporter_metrics: ExporterMetrics,
+}
+
+impl EventPipeline {
+    pub fn start(options: PipelineOptions, exporter_metrics: ExporterMetrics) -> Result<Self> {
+        let (pipeline, receivers) = Self::new(options, exporter_metrics);
+        for (index, receiver) in receivers.into_iter().enumerate() {
+            let worker_metrics = pipeline.exporter_metrics.clone();
+            thread::Builder::new()
+                .name(format!("trace-worker-{index}"))
+                .spawn(move || run_worker(receiver, worker_metrics))
+                .context("failed to start trace worker thread")?;
+        }
+        Ok(pipeline)
+    }
+
+    fn new(
+        options: PipelineOptions,
+        exporter_metrics: ExporterMetrics,
+    ) -> (Self, Vec<mpsc::Receiver<QueuedLine>>) {
+        let (senders, receivers) = (0..options.workers.max(1))
+            .map(|_| mpsc::channel(options.queue_capacity.max(1)))
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        let pipeline = Self {
+            senders: senders.into(),
+            overflow: options.overflow,
+            exporter_metrics,
+        };
+        (pipeline, receivers)
+    }
+
+    // Export the number of lines waiting in each worker's queue.
+    pub fn observe_queue_depth(&self, meter: &Meter) {
+        let senders = Arc::clone(&self.senders);
+        meter
+            .u64_observable_gauge("fdb_exporter_pipeline_queue_depth")
+            .with_description("Number of trace lines waiting to be parsed and recorded, per worker")
+            .with_callback(move |observer| {
+                for (index, sender) in senders.iter().enumerate() {
+                    let depth = sender.max_capacity() - sender.capacity();
+                    observer.observe(depth as u64, &[KeyValue::new("worker", index.to_string())]);
+                }
+            })
+            .init();
+    }
+
+    // The sender a tailer for `path` queues its lines through; lines are recorded with `metrics`,
+    // and the last recorded event's `Time` is reported through `progress`.
+    pub fn sender(
+        &self,
+        path: &Path,
+        metrics: LogMetrics,
+        progress: Arc<FileProgress>,
+    ) -> LineSender {
+        let mut hasher = DefaultHasher::new();
+        path.hash(&mut hasher);
+        let worker = (hasher.finish() % self.senders.len() as u64) as usize;
+        LineSender {
+            sender: self.senders[worker].clone(),
+            metrics: Arc::new(metrics),
+            progress,
+            overflow: self.overflow,
+            exporter_metrics: self.exporter_metrics.clone(),
+        }
+    }
+}
+
+// One file's handle on its worker queue.
+#[derive(Clone)]
+pub struct LineSender {
+    sender: mpsc::Sender<QueuedLine>,
+    metrics: Arc<LogMetrics>,
+    progress: Arc<FileProgress>,
+    overflow: OverflowPolicy,
+    exporter_metrics: ExporterMetrics,
+}
+
+impl LineSender {
+    pub fn progress(&self) -> &FileProgress {
+        &self.progress
+    }
+
+    // Queue a trimmed, non-empty trace line. Events before `since` are skipped by the worker, and
+    // `record_latency` asks it to report the event's latency once recorded.
+    pub async fn send(
+        &self,
+        line: String,
+        format: TraceFormat,
+        since: Option<f64>,
+        record_latency: bool,
+    ) -> Result<()> {
+        let queued = QueuedLine {
+            line,
+            format,
+            metrics: Arc::clone(&self.metrics),
+            progress: Arc::clone(&self.progress),
+            since,
+            record_latency,
+        };
+        let queued = match self.sender.try_send(queued) {
+            Ok(()) => return Ok(()),
+            Err(TrySendError::Full(queued)) => queued,
+            Err(TrySendError::Closed(_)) => bail!("trace worker stopped"),
+        };
+
+        self.exporter_metrics
+            .record_pipeline_overflow(self.overflow.as_str());
+        match self.overflow {
+            OverflowPolicy::Block => {
+                if self.sender.send(queued).await.is_err() {
+                    bail!("trace worker stopped");
+                }
+            }
+            OverflowPolicy::Drop => self.exporter_metrics.record_pipeline_dropped(),
+        }
+        Ok(())
+    }
+}
+
+fn run_worker(mut receiver: mpsc::Receiver<QueuedLine>, exporter_metrics: ExporterMetrics) {
+    while let Some(queued) = receiver.blocking_recv() {
+        let recorded_time = handle_log_line(
+            &queued.line,
+            queued.format,
+            &queued.metrics,
+            &exporter_metrics,
+            queued.since,
+        );
+        if let Some(time) = recorded_time {
+            queued.progress.record_event_time(time);
+            if queued.record_latency {
+                exporter_metrics.record_event_latency(unix_now() - time);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::metrics::{find_metric_in, prometheus_meter};
+    use crate::test_helpers::recording::RecordingMetric;
+    use std::time::Duration;
+
+    fn options(workers: usize, queue_capacity: usize, overflow: OverflowPolicy) -> PipelineOptions {
+        PipelineOptions {
+            workers,
+            queue_capacity,
+            overflow,
+        }
+    }
+
+    fn line(event_type: &str) -> String {
+        format!(r#"{{"Type":"{event_type}","Machine":"10.0.0.1:4500"}}"#)
+    }
+
+    #[tokio::test]
+    async fn workers_record_each_file_in_order() -> Result<()> {
+        let (_provider, meter, _registry) = prometheus_meter();
+        let recorder = RecordingMetric::default();
+        let metrics = LogMetrics::from_metrics(vec![Arc::new(recorder.clone())]);
+        let exporter_metrics = ExporterMetrics::new(&meter);
+        let pipeline = EventPipeline::start(
+            options(4, 8, OverflowPolicy::Block),
+            exporter_metrics.clone(),
+        )?;
+
+        let path = Path::new("/logs/trace.1.json");
+        let sender = pipeline.sender(path, metrics, exporter_metrics.track_file(path, "test"));
+        let expected: Vec<String> = (0..50).map(|index| format!("Event{index}")).collect();
+        for event_type in expected.iter() {
+            sender
+                .send(line(event_type), TraceFormat::Json, None, false)
+                .await?;
+        }
+
+        for _ in 0..100 {
+            if recorder.len() == expected.len() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(recorder.types(), expected);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn drop_policy_discards_lines_when_queue_is_full() -> Result<()> {
+        let (_provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+        let (pipeline, mut receivers) = EventPipeline::new(
+            options(1, 1, OverflowPolicy::Drop),
+            exporter_metrics.clone(),
+        );
+        pipeline.observe_queue_depth(&meter);
+
+        let path = Path::new("/logs/trace.1.json");
+        let sender = pipeline.sender(
+            path,
+            LogMetrics::from_metrics(Vec::new()),
+            exporter_metrics.track_file(path, "test"),
+        );
+        sender
+            .send(line("Kept"), TraceFormat::Json, None, false)
+            .await?;
+        sender
+            .send(line("Dropped"), TraceFormat::Json, None, false)
+            .await?;
+
+        let families = registry.gather();
+        let depth = find_metric_in(
+            &families,
+            "fdb_exporter_pipeline_queue_depth",
+            "worker",
+            "0",
+        )
+        .expect("queue depth gauge");
+        assert_eq!(depth.get_gauge().get_value(), 1.0);
+        let overflows = find_metric_in(
+            &families,
+            "fdb_exporter_pipeline_overflows_total",
+            "policy",
+            "drop",
+        )
+        .expect("overflow counter");
+        assert_eq!(overflows.get_counter().get_value(), 1.0);
+        let dropped = find_metric_in(
+            &families,
+            "fdb_exporter_pipeline_dropped_lines_total",
+            "otel_scope_name",
+            "test",
+        )
+        .expect("dropped line counter");
+        assert_eq!(dropped.get_counter().get_value(), 1.0);
+
+        let queued = receivers[0].try_recv().expect("first line queued");
+        assert_eq!(queued.line, line("Kept"));
+        assert!(receivers[0].try_recv().is_err());
+        Ok(())
+    }
+}
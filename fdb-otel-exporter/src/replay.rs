@@ -0,0 +1,880 @@
+use crate::{
+    bundle::trace_file_name,
+    config::{PlaybackOptions, ReplayOptions},
+    exporter_metrics::ExporterMetrics,
+    fdb_gauge::Summaries,
+    fdb_histogram::NativeHistograms,
+    log_metrics::{LogMetrics, TraceEvent},
+    log_sources::{FileFilter, LogRoot},
+    trace_format::TraceFormat,
+    watch_logs::trace_time,
+};
+use anyhow::{bail, Context, Result};
+use flate2::read::MultiGzDecoder;
+use opentelemetry::metrics::MeterProvider;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use prometheus::{
+    proto::{Metric, MetricFamily, MetricType},
+    Registry,
+};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    fs::{self, File},
+    io::{self, BufRead, BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
+};
+
+// Run every trace file under `options.input` through the configured gauges in `Time` order and
+// write the resulting samples as OpenMetrics text, stamped with the trace time they were recorded
+// at, for `promtool tsdb create-blocks-from openmetrics`.
+pub async fn run_replay(options: ReplayOptions) -> Result<()> {
+    tokio::task::spawn_blocking(move || replay(&options)).await?
+}
+
+fn replay(options: &ReplayOptions) -> Result<()> {
+    let files = find_trace_files(&options.input)?;
+    if files.is_empty() {
+        bail!("no trace files found under {}", options.input.display());
+    }
+    tracing::info!(
+        log_dir = %options.input.display(),
+        files = files.len(),
+        "replaying trace files"
+    );
+
+    let events = MergedTraceEvents::open(&files)?;
+    let output: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(
+            File::create(path)
+                .with_context(|| format!("failed to create replay output {}", path.display()))?,
+        ),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    let summary = replay_events(events, options.step, &options.job, BufWriter::new(output))?;
+    tracing::info!(
+        events = summary.events,
+        record_errors = summary.record_errors,
+        samples = summary.samples,
+        "replay complete"
+    );
+    Ok(())
+}
+
+// Feed every trace file under `options.input` into the live metrics in `Time` order, waiting
+// between events so trace time advances `options.speed` times as fast as the wall clock. The first
+// event is recorded immediately, and `/metrics` keeps serving the last values once playback ends.
+pub async fn play_back(
+    options: PlaybackOptions,
+    metrics: LogMetrics,
+    exporter_metrics: ExporterMetrics,
+) -> Result<()> {
+    tokio::task::spawn_blocking(move || {
+        let files = find_trace_files(&options.input)?;
+        if files.is_empty() {
+            bail!("no trace files found under {}", options.input.display());
+        }
+        tracing::info!(
+            log_dir = %options.input.display(),
+            files = files.len(),
+            speed = options.speed,
+            "playing back trace files"
+        );
+
+        let start = Instant::now();
+        let events = pace_events(
+            MergedTraceEvents::open(&files)?,
+            options.speed,
+            &metrics,
+            &exporter_metrics,
+            |offset| {
+                if let Some(remaining) = (start + offset).checked_duration_since(Instant::now()) {
+                    thread::sleep(remaining);
+                }
+            },
+        );
+        tracing::info!(events, "playback complete");
+        Ok(())
+    })
+    .await?
+}
+
+// Record `events`, first calling `wait_until` with the wall-clock offset from the start of
+// playback at which each one is due. Returns the number of events played.
+fn pace_events(
+    events: impl Iterator<Item = (f64, TraceEvent)>,
+    speed: f64,
+    metrics: &LogMetrics,
+    exporter_metrics: &ExporterMetrics,
+    mut wait_until: impl FnMut(Duration),
+) -> u64 {
+    let mut first_time = None;
+    let mut played = 0;
+    for (time, event) in events {
+        let first = *first_time.get_or_insert(time);
+        wait_until(Duration::from_secs_f64(((time - first) / speed).max(0.0)));
+
+        match metrics.record(&event) {
+            Ok(()) => exporter_metrics.record_processed(),
+            Err(error) => {
+                exporter_metrics.record_record_error();
+                tracing::warn!(?error, ?event, "failed to record played back event");
+            }
+        }
+        played += 1;
+    }
+    played
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct ReplaySummary {
+    events: u64,
+    record_errors: u64,
+    samples: usize,
+}
+
+// Record `events` (which must be in `Time` order) into a private registry, capturing its contents
+// whenever trace time crosses a `step` boundary, and write every captured sample to `output`.
+fn replay_events(
+    events: impl Iterator<Item = (f64, TraceEvent)>,
+    step: f64,
+    job: &str,
+    mut output: impl Write,
+) -> Result<ReplaySummary> {
+    let registry = Registry::new();
+    let histograms = NativeHistograms::default();
+    let summaries = Summaries::default();
+    registry.register(Box::new(summaries.clone()))?;
+    let exporter = opentelemetry_prometheus::exporter()
+        .with_registry(registry.clone())
+        .with_producer(histograms.clone())
+        .without_scope_info()
+        .without_target_info()
+        .build()?;
+    let provider = SdkMeterProvider::builder().with_reader(exporter).build();
+    let metrics = LogMetrics::new(
+        &provider.meter("fdb-otel-exporter"),
+        &histograms,
+        &summaries,
+    )
+    .with_context(|| "failed to load gauge configuration")?;
+
+    let mut writer = OpenMetricsWriter::new(job);
+    let mut summary = ReplaySummary::default();
+    let mut step_end = None;
+    let mut last_time = None;
+
+    for (time, event) in events {
+        if let (Some(end), Some(last)) = (step_end, last_time) {
+            if time >= end {
+                writer.capture(&registry.gather(), last)?;
+                step_end = None;
+            }
+        }
+        step_end.get_or_insert(((time / step).floor() + 1.0) * step);
+        last_time = Some(time);
+
+        summary.events += 1;
+        if let Err(error) = metrics.record(&event) {
+            summary.record_errors += 1;
+            tracing::debug!(?error, ?event, "failed to record replayed event");
+        }
+    }
+    if let Some(last) = last_time {
+        writer.capture(&registry.gather(), last)?;
+    }
+
+    summary.samples = writer.finish(&mut output)?;
+    output.flush()?;
+    Ok(summary)
+}
+
+// Every trace file below `dir`, at any depth, matching the default `trace.*.json`/`trace.*.xml`
+// rules. Gzipped files are included and matched on the name they had before compression.
+fn find_trace_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let filter = LogRoot::new(dir.to_path_buf()).filter;
+    let mut files = Vec::new();
+    collect_trace_files(dir, dir, &filter, &mut files)?;
+    files.sort();
+    Ok(files)
+}
+
+fn collect_trace_files(
+    root: &Path,
+    dir: &Path,
+    filter: &FileFilter,
+    files: &mut Vec<PathBuf>,
+) -> Result<()> {
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("failed to read log directory {}", dir.display()))?;
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_trace_files(root, &path, filter, files)?;
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            if filter.matches(&trace_file_name(relative)) {
+                files.push(path);
+            }
+        }
+    }
+    Ok(())
+}
+
+// Trace events of one file, in file order, with their `Time`. Lines that fail to parse and events
+// without a `Time` are logged and skipped.
+pub struct TraceEventReader {
+    identity: String,
+    lines: Box<dyn BufRead + Send>,
+    format: Option<TraceFormat>,
+    line: Vec<u8>,
+}
+
+impl TraceEventReader {
+    // Open a trace file, gunzipping it if its name ends in `.gz`.
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)
+            .with_context(|| format!("failed to open trace file {}", path.display()))?;
+        let name = trace_file_name(path);
+        let lines: Box<dyn BufRead + Send> = if name != path {
+            Box::new(BufReader::new(MultiGzDecoder::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        };
+        Ok(Self {
+            identity: path.display().to_string(),
+            lines,
+            format: TraceFormat::from_path(&name),
+            line: Vec::new(),
+        })
+    }
+
+    fn next_event(&mut self) -> Result<Option<(f64, TraceEvent)>> {
+        loop {
+            self.line.clear();
+            let bytes = self
+                .lines
+                .read_until(b'\n', &mut self.line)
+                .with_context(|| format!("failed to read {}", self.identity))?;
+            if bytes == 0 {
+                return Ok(None);
+            }
+
+            let text = String::from_utf8_lossy(&self.line);
+            let trimmed = text.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            match TraceFormat::resolve(&mut self.format, trimmed).parse_line(trimmed) {
+                Ok(None) => {}
+                Ok(Some(event)) => match trace_time(&event) {
+                    Some(time) => return Ok(Some((time, event))),
+                    None => tracing::debug!(file = %self.identity, "skipping event without Time"),
+                },
+                Err(error) => {
+                    tracing::warn!(?error, file = %self.identity, raw_line = %trimmed, "failed to parse log line");
+                }
+            }
+        }
+    }
+}
+
+// Merges the events of several trace files into a single stream ordered by `Time`. Each file is
+// assumed to be in time order already, as FDB writes them; ties go to the file opened first. A
+// file that fails to read is logged and dropped from the merge.
+pub struct MergedTraceEvents {
+    readers: Vec<TraceEventReader>,
+    pending: Vec<Option<TraceEvent>>,
+    heap: BinaryHeap<Reverse<(TraceTime, usize)>>,
+}
+
+impl MergedTraceEvents {
+    pub fn open(paths: &[PathBuf]) -> Result<Self> {
+        let readers = paths
+            .iter()
+            .map(|path| TraceEventReader::open(path))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self::new(readers))
+    }
+
+    pub fn new(readers: Vec<TraceEventReader>) -> Self {
+        let mut merged = Self {
+            pending: readers.iter().map(|_| None).collect(),
+            readers,
+            heap: BinaryHeap::new(),
+        };
+        for index in 0..merged.readers.len() {
+            merged.advance(index);
+        }
+        merged
+    }
+
+    fn advance(&mut self, index: usize) {
+        match self.readers[index].next_event() {
+            Ok(Some((time, event))) => {
+                self.pending[index] = Some(event);
+                self.heap.push(Reverse((TraceTime(time), index)));
+            }
+            Ok(None) => {}
+            Err(error) => {
+                tracing::error!(?error, file = %self.readers[index].identity, "stopped replaying trace file");
+            }
+        }
+    }
+}
+
+impl Iterator for MergedTraceEvents {
+    type Item = (f64, TraceEvent);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((TraceTime(time), index)) = self.heap.pop()?;
+        let event = self.pending[index].take()?;
+        self.advance(index);
+        Some((time, event))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TraceTime(f64);
+
+impl PartialEq for TraceTime {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for TraceTime {}
+
+impl PartialOrd for TraceTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for TraceTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+// Accumulates timestamped samples per metric family. OpenMetrics requires each family's samples
+// to be contiguous, so each family spills its samples to its own temporary file and the files are
+// concatenated by `finish`, keeping memory bounded by the number of series rather than the length
+// of the replay. Gauges drain on every gather and so only appear at the steps where they were
+// recorded; counters are cumulative and are only written when their value changes.
+struct OpenMetricsWriter {
+    job: String,
+    families: BTreeMap<String, FamilyBuffer>,
+    counter_values: HashMap<String, f64>,
+}
+
+struct FamilyBuffer {
+    kind: &'static str,
+    help: String,
+    spill: BufWriter<File>,
+    samples: usize,
+}
+
+impl FamilyBuffer {
+    fn push(&mut self, sample: &str) -> io::Result<()> {
+        writeln!(self.spill, "{sample}")?;
+        self.samples += 1;
+        Ok(())
+    }
+}
+
+impl OpenMetricsWriter {
+    fn new(job: &str) -> Self {
+        Self {
+            job: job.to_string(),
+            families: BTreeMap::new(),
+            counter_values: HashMap::new(),
+        }
+    }
+
+    fn family(
+        &mut self,
+        name: &str,
+        kind: &'static str,
+        family: &MetricFamily,
+    ) -> io::Result<&mut FamilyBuffer> {
+        if !self.families.contains_key(name) {
+            let buffer = FamilyBuffer {
+                kind,
+                help: family.get_help().to_string(),
+                spill: BufWriter::new(tempfile::tempfile()?),
+                samples: 0,
+            };
+            self.families.insert(name.to_string(), buffer);
+        }
+        Ok(self
+            .families
+            .get_mut(name)
+            .expect("family was just inserted"))
+    }
+
+    fn capture(&mut self, families: &[MetricFamily], timestamp: f64) -> io::Result<()> {
+        for family in families {
+            let (kind, name, sample_name) = match family.get_field_type() {
+                MetricType::GAUGE => ("gauge", family.get_name(), family.get_name().to_string()),
+                MetricType::COUNTER => {
+                    let name = family.get_name();
+                    let base = name.strip_suffix("_total").unwrap_or(name);
+                    ("counter", base, format!("{base}_total"))
+                }
+                MetricType::HISTOGRAM => {
+                    self.capture_histograms(family, timestamp)?;
+                    continue;
+                }
+                MetricType::SUMMARY => {
+                    self.capture_summaries(family, timestamp)?;
+                    continue;
+                }
+                other => {
+                    tracing::debug!(
+                        family = family.get_name(),
+                        ?other,
+                        "skipping unsupported metric type"
+                    );
+                    continue;
+                }
+            };
+
+            for metric in family.get_metric() {
+                let (value, is_counter) = match kind {
+                    "counter" => (metric.get_counter().get_value(), true),
+                    _ => (metric.get_gauge().get_value(), false),
+                };
+                let series = format!("{sample_name}{}", self.labels(metric));
+                if is_counter && self.counter_values.insert(series.clone(), value) == Some(value) {
+                    continue;
+                }
+
+                self.family(name, kind, family)?
+                    .push(&format!("{series} {} {timestamp}", format_value(value)))?;
+            }
+        }
+        Ok(())
+    }
+
+    // Write each histogram's `_bucket`, `_count` and `_sum` samples, skipping histograms whose
+    // count has not changed since the last capture, like counters.
+    fn capture_histograms(&mut self, family: &MetricFamily, timestamp: f64) -> io::Result<()> {
+        let name = family.get_name();
+        for metric in family.get_metric() {
+            let histogram = metric.get_histogram();
+            let count = histogram.get_sample_count();
+            let labels = self.labels(metric);
+            if self
+                .counter_values
+                .insert(format!("{name}_count{labels}"), count as f64)
+                == Some(count as f64)
+            {
+                continue;
+            }
+
+            let bucket_labels = |le: &str| with_label(&labels, "le", le);
+            let mut samples: Vec<String> = histogram
+                .get_bucket()
+                .iter()
+                .map(|bucket| {
+                    format!(
+                        "{name}_bucket{} {} {timestamp}",
+                        bucket_labels(&format_value(bucket.get_upper_bound())),
+                        bucket.get_cumulative_count()
+                    )
+                })
+                .collect();
+            samples.push(format!(
+                "{name}_bucket{} {count} {timestamp}",
+                bucket_labels("+Inf")
+            ));
+            samples.push(format!("{name}_count{labels} {count} {timestamp}"));
+            samples.push(format!(
+                "{name}_sum{labels} {} {timestamp}",
+                format_value(histogram.get_sample_sum())
+            ));
+
+            let buffer = self.family(name, "histogram", family)?;
+            for sample in samples.iter() {
+                buffer.push(sample)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Write each summary's quantile, `_count` and `_sum` samples, skipping summaries whose count
+    // has not changed since the last capture, like histograms.
+    fn capture_summaries(&mut self, family: &MetricFamily, timestamp: f64) -> io::Result<()> {
+        let name = family.get_name();
+        for metric in family.get_metric() {
+            let summary = metric.get_summary();
+            let count = summary.get_sample_count();
+            let labels = self.labels(metric);
+            if self
+                .counter_values
+                .insert(format!("{name}_count{labels}"), count as f64)
+                == Some(count as f64)
+            {
+                continue;
+            }
+
+            let mut samples: Vec<String> = summary
+                .get_quantile()
+                .iter()
+                .map(|quantile| {
+                    format!(
+                        "{name}{} {} {timestamp}",
+                        with_label(&labels, "quantile", &quantile.get_quantile().to_string()),
+                        format_value(quantile.get_value())
+                    )
+                })
+                .collect();
+            samples.push(format!("{name}_count{labels} {count} {timestamp}"));
+            samples.push(format!(
+                "{name}_sum{labels} {} {timestamp}",
+                format_value(summary.get_sample_sum())
+            ));
+
+            let buffer = self.family(name, "summary", family)?;
+            for sample in samples.iter() {
+                buffer.push(sample)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn labels(&self, metric: &Metric) -> String {
+        let mut labels = Vec::new();
+        if !metric
+            .get_label()
+            .iter()
+            .any(|label| label.get_name() == "job")
+        {
+            labels.push(format!("job=\"{}\"", escape_label_value(&self.job)));
+        }
+        labels.extend(metric.get_label().iter().map(|label| {
+            format!(
+                "{}=\"{}\"",
+                label.get_name(),
+                escape_label_value(label.get_value())
+            )
+        }));
+        format!("{{{}}}", labels.join(","))
+    }
+
+    // Write every spilled family followed by `# EOF`, returning the number of samples written.
+    fn finish(self, output: &mut impl Write) -> io::Result<usize> {
+        let mut samples = 0;
+        for (name, family) in self.families.into_iter() {
+            writeln!(output, "# TYPE {name} {}", family.kind)?;
+            if !family.help.is_empty() {
+                writeln!(output, "# HELP {name} {}", escape_help(&family.help))?;
+            }
+            let mut spill = family
+                .spill
+                .into_inner()
+                .map_err(|error| error.into_error())?;
+            spill.seek(SeekFrom::Start(0))?;
+            io::copy(&mut spill, output)?;
+            samples += family.samples;
+        }
+        writeln!(output, "# EOF")?;
+        Ok(samples)
+    }
+}
+
+// Add one label to a formatted `{...}` label set.
+fn with_label(labels: &str, name: &str, value: &str) -> String {
+    let labels = labels.strip_suffix('}').unwrap_or(labels);
+    format!("{labels},{name}=\"{}\"}}", escape_label_value(value))
+}
+
+fn format_value(value: f64) -> String {
+    if value.is_nan() {
+        "NaN".to_string()
+    } else if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn escape_help(help: &str) -> String {
+    help.replace('\\', "\\\\").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fdb_gauge::{HistogramSummaryFDBGauge, PercentileEstimator};
+    use crate::fdb_histogram::NativeHistogramFDBGauge;
+    use crate::fdb_metric::FDBMetric;
+    use crate::test_helpers::metrics::prometheus_meter;
+    use crate::test_helpers::recording::RecordingMetric;
+    use opentelemetry::KeyValue;
+    use std::sync::Arc;
+    use tempfile::tempdir;
+
+    fn write_file(path: &Path, lines: &[String]) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, lines.join("\n") + "\n").unwrap();
+    }
+
+    fn tracker_event(time: f64, total_size: u64) -> String {
+        format!(
+            r#"{{"Type":"DDTrackerStats","Severity":"10","Time":"{time}","Machine":"10.0.0.1:4500","Roles":"DD","TotalSizeBytes":"{total_size}"}}"#
+        )
+    }
+
+    fn sev20_event(time: f64) -> String {
+        format!(r#"<Event Severity="20" Time="{time}" Type="Warning" Machine="10.0.0.2:4500" />"#)
+    }
+
+    #[test]
+    fn merged_events_follow_trace_time_across_files() {
+        let dir = tempdir().unwrap();
+        write_file(
+            &dir.path().join("4500/trace.1.json"),
+            &[tracker_event(100.2, 100), tracker_event(102.5, 300)],
+        );
+        write_file(
+            &dir.path().join("4501/trace.2.xml"),
+            &[
+                r#"<?xml version="1.0"?>"#.to_string(),
+                "<Trace>".to_string(),
+                sev20_event(100.5),
+                sev20_event(101.7),
+                "</Trace>".to_string(),
+            ],
+        );
+        write_file(&dir.path().join("notes.txt"), &["ignored".to_string()]);
+
+        let files = find_trace_files(dir.path()).unwrap();
+        assert_eq!(files.len(), 2);
+        let times: Vec<f64> = MergedTraceEvents::open(&files)
+            .unwrap()
+            .map(|(time, _)| time)
+            .collect();
+        assert_eq!(times, [100.2, 100.5, 101.7, 102.5]);
+    }
+
+    #[test]
+    fn replay_writes_timestamped_samples_per_step() {
+        let dir = tempdir().unwrap();
+        write_file(
+            &dir.path().join("trace.1.json"),
+            &[tracker_event(100.2, 100), tracker_event(102.5, 300)],
+        );
+        write_file(
+            &dir.path().join("trace.2.xml"),
+            &[sev20_event(100.5), sev20_event(101.7)],
+        );
+
+        let events = MergedTraceEvents::open(&find_trace_files(dir.path()).unwrap()).unwrap();
+        let mut output = Vec::new();
+        let summary = replay_events(events, 1.0, "fdb-otel-exporter", &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(summary.events, 4);
+        // Gauges are written at the steps they were recorded in, as a 15 second rolling mean.
+        assert!(
+            output.contains("# TYPE dd_total_kv_size gauge\n"),
+            "{output}"
+        );
+        assert!(output.contains(
+            "dd_total_kv_size{job=\"fdb-otel-exporter\",Roles=\"DD\",machine=\"10.0.0.1:4500\"} 100 100.5\n"
+        ), "{output}");
+        assert!(output.contains(
+            "dd_total_kv_size{job=\"fdb-otel-exporter\",Roles=\"DD\",machine=\"10.0.0.1:4500\"} 200 102.5\n"
+        ), "{output}");
+        assert!(
+            output.contains("# TYPE process_sev20_counter counter\n"),
+            "{output}"
+        );
+        assert!(output.contains(
+            "process_sev20_counter_total{job=\"fdb-otel-exporter\",machine=\"10.0.0.2:4500\"} 1 100.5\n"
+        ), "{output}");
+        assert!(output.contains(
+            "process_sev20_counter_total{job=\"fdb-otel-exporter\",machine=\"10.0.0.2:4500\"} 2 101.7\n"
+        ), "{output}");
+        assert!(output.ends_with("# EOF\n"));
+    }
+
+    #[test]
+    fn writer_skips_unchanged_counters_and_escapes_labels() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let counter = meter.u64_counter("replayed_events").init();
+        let labels = [KeyValue::new("machine", "a\"b\\c")];
+        counter.add(1, &labels);
+
+        let mut writer = OpenMetricsWriter::new("incident");
+        writer.capture(&registry.gather(), 10.0).unwrap();
+        writer.capture(&registry.gather(), 11.0).unwrap();
+        counter.add(1, &labels);
+        writer.capture(&registry.gather(), 12.0).unwrap();
+
+        let mut output = Vec::new();
+        writer.finish(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let samples: Vec<&str> = output
+            .lines()
+            .filter(|line| line.starts_with("replayed_events_total"))
+            .collect();
+        assert_eq!(
+            samples,
+            [
+                r#"replayed_events_total{job="incident",machine="a\"b\\c",otel_scope_name="test"} 1 10"#,
+                r#"replayed_events_total{job="incident",machine="a\"b\\c",otel_scope_name="test"} 2 12"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn writer_writes_native_histograms() {
+        let registry = Registry::new();
+        let histograms = NativeHistograms::default();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .with_producer(histograms.clone())
+            .without_scope_info()
+            .without_target_info()
+            .build()
+            .unwrap();
+        let _provider = SdkMeterProvider::builder().with_reader(exporter).build();
+        let gauge = NativeHistogramFDBGauge::new(
+            "CommitProxy",
+            "TlogLogging",
+            "cp_tlog_logging_latency_seconds",
+            "Commit proxy TLog logging latency",
+            histograms,
+        );
+        let event: TraceEvent = serde_json::from_str(
+            r#"{"Type":"Histogram","Group":"CommitProxy","Op":"TlogLogging","Unit":"milliseconds","TotalCount":"3","LessThan1.024":"2","LessThan2.048":"1"}"#,
+        )
+        .unwrap();
+        gauge
+            .record(&event, &[KeyValue::new("machine", "10.0.0.1:4500")])
+            .unwrap();
+
+        let mut writer = OpenMetricsWriter::new("incident");
+        writer.capture(&registry.gather(), 10.0).unwrap();
+        writer.capture(&registry.gather(), 11.0).unwrap();
+        let mut output = Vec::new();
+        writer.finish(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(
+            output.contains("# TYPE cp_tlog_logging_latency_seconds histogram\n"),
+            "{output}"
+        );
+        for sample in [
+            r#"cp_tlog_logging_latency_seconds_bucket{job="incident",machine="10.0.0.1:4500",le="0.001024"} 2 10"#,
+            r#"cp_tlog_logging_latency_seconds_bucket{job="incident",machine="10.0.0.1:4500",le="0.002048"} 3 10"#,
+            r#"cp_tlog_logging_latency_seconds_bucket{job="incident",machine="10.0.0.1:4500",le="+Inf"} 3 10"#,
+            r#"cp_tlog_logging_latency_seconds_count{job="incident",machine="10.0.0.1:4500"} 3 10"#,
+        ] {
+            assert!(output.contains(&format!("{sample}\n")), "{output}");
+        }
+        assert!(
+            !output.contains(" 11\n"),
+            "unchanged histogram rewritten: {output}"
+        );
+    }
+
+    #[test]
+    fn writer_writes_summaries() {
+        let registry = Registry::new();
+        let summaries = Summaries::default();
+        registry.register(Box::new(summaries.clone())).unwrap();
+        let gauge = HistogramSummaryFDBGauge::new(
+            "CommitProxy",
+            "TlogLogging",
+            vec![0.5],
+            PercentileEstimator::UpperBound,
+            "cp_tlog_logging_latency",
+            "Commit proxy TLog logging latency",
+            summaries,
+        );
+        let event: TraceEvent = serde_json::from_str(
+            r#"{"Type":"Histogram","Group":"CommitProxy","Op":"TlogLogging","Unit":"milliseconds","TotalCount":"3","LessThan1.024":"2","LessThan2.048":"1"}"#,
+        )
+        .unwrap();
+        gauge
+            .record(&event, &[KeyValue::new("machine", "10.0.0.1:4500")])
+            .unwrap();
+
+        let mut writer = OpenMetricsWriter::new("incident");
+        writer.capture(&registry.gather(), 10.0).unwrap();
+        writer.capture(&registry.gather(), 11.0).unwrap();
+        let mut output = Vec::new();
+        writer.finish(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(
+            output.contains("# TYPE cp_tlog_logging_latency summary\n"),
+            "{output}"
+        );
+        for sample in [
+            r#"cp_tlog_logging_latency{job="incident",machine="10.0.0.1:4500",quantile="0.5"} 0.001024 10"#,
+            r#"cp_tlog_logging_latency_count{job="incident",machine="10.0.0.1:4500"} 3 10"#,
+        ] {
+            assert!(output.contains(&format!("{sample}\n")), "{output}");
+        }
+        assert!(
+            !output.contains(" 11\n"),
+            "unchanged summary rewritten: {output}"
+        );
+    }
+
+    #[test]
+    fn pace_events_waits_for_scaled_trace_time() {
+        let recorder = RecordingMetric::default();
+        let metrics = LogMetrics::from_metrics(vec![Arc::new(recorder.clone())]);
+        let (_provider, meter, _registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let events = [100.0, 101.0, 104.0].map(|time| {
+            let mut event = TraceEvent::new();
+            event.insert("Time".to_string(), time.to_string().into());
+            event.insert("Machine".to_string(), "10.0.0.1:4500".into());
+            (time, event)
+        });
+        let mut offsets = Vec::new();
+        let played = pace_events(
+            events.into_iter(),
+            2.0,
+            &metrics,
+            &exporter_metrics,
+            |offset| offsets.push((offset, recorder.len())),
+        );
+
+        assert_eq!(played, 3);
+        // Each wait happens before its event is recorded.
+        assert_eq!(
+            offsets,
+            [
+                (Duration::ZERO, 0),
+                (Duration::from_millis(500), 1),
+                (Duration::from_secs(2), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn format_value_uses_openmetrics_spellings() {
+        assert_eq!(format_value(f64::NAN), "NaN");
+        assert_eq!(format_value(f64::INFINITY), "+Inf");
+        assert_eq!(format_value(f64::NEG_INFINITY), "-Inf");
+        assert_eq!(format_value(1.5), "1.5");
+    }
+}
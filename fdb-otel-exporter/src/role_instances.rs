@@ -0,0 +1,305 @@
+use crate::fdb_gauge::LabelKey;
+use crate::fdb_metric::FDBMetric;
+use anyhow::Result;
+use opentelemetry::metrics::{Counter, Meter};
+use opentelemetry::KeyValue;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+// Trace time within which two `ID`s on one series count as separate role instances. FDB logs its
+// role metrics every 5 seconds by default, so instances sharing a process interleave within it.
+const INSTANCE_INTERVAL_SECONDS: f64 = 5.0;
+// Trace time a keyed series is remembered without events, so a pause in logging does not merge
+// its instances back into one series. Series of retired files and machines are dropped after it.
+const KEYED_RETENTION_SECONDS: f64 = 300.0;
+// More `ID`s than a process hosts roles means the field names something else, such as a debug ID,
+// and labeling by each of them would create unbounded series.
+const MAX_INSTANCES_PER_SERIES: usize = 8;
+// Label value for the `ID`s a keyed series sees past `MAX_INSTANCES_PER_SERIES`.
+const OTHER_INSTANCES: &str = "other";
+
+struct SeriesInstances {
+    // The `ID` and trace time of the latest event on the series.
+    last_id: String,
+    last_time: f64,
+    // Set once two instances report within the interval; the series is then keyed by `ID`.
+    keyed: bool,
+    // `ID`s labeled as themselves since the series was keyed. Later ones are labeled `other`.
+    labeled: HashSet<String>,
+}
+
+impl SeriesInstances {
+    fn new(id: &str, time: f64) -> Self {
+        Self {
+            last_id: id.to_string(),
+            last_time: time,
+            keyed: false,
+            labeled: HashSet::new(),
+        }
+    }
+
+    // Note an event of instance `id`, returning whether it made the series keyed.
+    fn observe(&mut self, id: &str, time: f64) -> bool {
+        let collided = !self.keyed
+            && self.last_id != id
+            && (time - self.last_time).abs() <= INSTANCE_INTERVAL_SECONDS;
+        if collided {
+            self.keyed = true;
+            self.labeled.insert(self.last_id.clone());
+        }
+        if time >= self.last_time {
+            self.last_id = id.to_string();
+            self.last_time = time;
+        }
+        if self.keyed && self.labeled.len() < MAX_INSTANCES_PER_SERIES {
+            self.labeled.insert(id.to_string());
+        }
+        collided
+    }
+
+    // The `ID` label value for instance `id`, once the series is keyed.
+    fn label(&self, id: &str) -> Option<KeyValue> {
+        if !self.keyed {
+            return None;
+        }
+        let id = if self.labeled.contains(id) {
+            id
+        } else {
+            OTHER_INSTANCES
+        };
+        Some(KeyValue::new("ID", id.to_string()))
+    }
+
+    fn expired(&self, time: f64) -> bool {
+        let retention = if self.keyed {
+            KEYED_RETENTION_SECONDS
+        } else {
+            INSTANCE_INTERVAL_SECONDS
+        };
+        time - self.last_time > retention
+    }
+}
+
+#[derive(Default)]
+struct InstanceSeries {
+    series: HashMap<LabelKey, SeriesInstances>,
+    // Latest trace time expired series were dropped at.
+    swept_at: f64,
+}
+
+impl InstanceSeries {
+    fn sweep(&mut self, time: f64) {
+        if time - self.swept_at < INSTANCE_INTERVAL_SECONDS {
+            return;
+        }
+        self.swept_at = time;
+        self.series.retain(|_, instances| !instances.expired(time));
+    }
+}
+
+// The counter shared by every `RoleInstances` wrapper built from `meter`.
+pub fn collision_counter(meter: &Meter) -> Counter<u64> {
+    meter
+        .u64_counter("fdb_exporter_series_collisions_total")
+        .with_description(
+            "Number of series found to receive events from several role instances, which are \
+             then labeled by ID",
+        )
+        .init()
+}
+
+// Wraps a metric of events of `trace_type` and detects label sets that several role instances
+// (e.g. two storage servers on one `fdbserver`) record under, which would otherwise be mixed into
+// one series. Such series get an `ID` label from then on. Events without an `ID` or a numeric
+// `Time` are recorded unchanged.
+pub struct RoleInstances {
+    metric: Arc<dyn FDBMetric>,
+    trace_type: String,
+    series: Mutex<InstanceSeries>,
+    collisions: Counter<u64>,
+}
+
+impl RoleInstances {
+    pub fn new(metric: Arc<dyn FDBMetric>, trace_type: String, collisions: Counter<u64>) -> Self {
+        Self {
+            metric,
+            trace_type,
+            series: Mutex::new(InstanceSeries::default()),
+            collisions,
+        }
+    }
+
+    fn label(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Option<KeyValue> {
+        let field = |name: &str| trace_event.get(name).and_then(|value| value.as_str());
+        if field("Type") != Some(self.trace_type.as_str()) {
+            return None;
+        }
+        let id = field("ID")?;
+        let time = field("Time")?.parse::<f64>().ok()?;
+
+        let mut series = self.series.lock().expect("role instance lock poisoned");
+        series.sweep(time);
+        let instances = series
+            .series
+            .entry(LabelKey::from_labels(labels))
+            .or_insert_with(|| SeriesInstances::new(id, time));
+        if instances.observe(id, time) {
+            let mut collision_labels = labels.to_vec();
+            collision_labels.push(KeyValue::new("Type", self.trace_type.clone()));
+            self.collisions.add(1, &collision_labels);
+        }
+        instances.label(id)
+    }
+}
+
+impl FDBMetric for RoleInstances {
+    fn record(&self, trace_event: &HashMap<String, Value>, labels: &[KeyValue]) -> Result<()> {
+        match self.label(trace_event, labels) {
+            Some(label) => {
+                let mut labels = labels.to_vec();
+                labels.push(label);
+                self.metric.record(trace_event, &labels)
+            }
+            None => self.metric.record(trace_event, labels),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_helpers::metrics::{find_metric, prometheus_meter};
+    use crate::test_helpers::recording::RecordingMetric;
+
+    fn storage_metrics(id: &str, time: f64) -> HashMap<String, Value> {
+        HashMap::from([
+            ("Type".to_string(), Value::String("StorageMetrics".into())),
+            ("ID".to_string(), Value::String(id.into())),
+            ("Time".to_string(), Value::String(time.to_string())),
+        ])
+    }
+
+    fn storage_instances(meter: &Meter) -> (Arc<RecordingMetric>, RoleInstances) {
+        let recorded = Arc::new(RecordingMetric::default());
+        let instances = RoleInstances::new(
+            recorded.clone(),
+            "StorageMetrics".into(),
+            collision_counter(meter),
+        );
+        (recorded, instances)
+    }
+
+    fn labeled_id(instances: &RoleInstances, event: &HashMap<String, Value>) -> Option<String> {
+        let labels = [KeyValue::new("machine", "10.0.0.1:4500")];
+        instances
+            .label(event, &labels)
+            .map(|label| label.value.to_string())
+    }
+
+    #[test]
+    fn keys_series_by_id_once_instances_collide() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let (_recorded, instances) = storage_instances(&meter);
+
+        assert_eq!(labeled_id(&instances, &storage_metrics("a", 0.0)), None);
+        assert_eq!(labeled_id(&instances, &storage_metrics("a", 5.0)), None);
+        assert_eq!(
+            labeled_id(&instances, &storage_metrics("b", 5.1)).as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            labeled_id(&instances, &storage_metrics("a", 10.0)).as_deref(),
+            Some("a")
+        );
+
+        let collisions = find_metric(
+            &registry,
+            "fdb_exporter_series_collisions_total",
+            "Type",
+            "StorageMetrics",
+        )
+        .expect("collision counter");
+        assert_eq!(collisions.get_counter().get_value(), 1.0);
+    }
+
+    #[test]
+    fn replaced_instance_does_not_collide() {
+        let (_provider, meter, registry) = prometheus_meter();
+        let (_recorded, instances) = storage_instances(&meter);
+
+        assert_eq!(labeled_id(&instances, &storage_metrics("a", 0.0)), None);
+        assert_eq!(labeled_id(&instances, &storage_metrics("b", 30.0)), None);
+        assert_eq!(labeled_id(&instances, &storage_metrics("b", 35.0)), None);
+
+        assert!(find_metric(
+            &registry,
+            "fdb_exporter_series_collisions_total",
+            "Type",
+            "StorageMetrics",
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn keeps_keyed_ids_when_series_overflows() {
+        let (_provider, meter, _registry) = prometheus_meter();
+        let (_recorded, instances) = storage_instances(&meter);
+
+        assert_eq!(labeled_id(&instances, &storage_metrics("a", 0.0)), None);
+        assert_eq!(
+            labeled_id(&instances, &storage_metrics("b", 0.5)).as_deref(),
+            Some("b")
+        );
+        for index in 0..MAX_INSTANCES_PER_SERIES {
+            labeled_id(&instances, &storage_metrics(&index.to_string(), 1.0));
+        }
+
+        assert_eq!(
+            labeled_id(&instances, &storage_metrics("a", 2.0)).as_deref(),
+            Some("a")
+        );
+        assert_eq!(
+            labeled_id(&instances, &storage_metrics("b", 2.0)).as_deref(),
+            Some("b")
+        );
+        assert_eq!(
+            labeled_id(&instances, &storage_metrics("0", 2.0)).as_deref(),
+            Some("0")
+        );
+        assert_eq!(
+            labeled_id(&instances, &storage_metrics("7", 2.0)).as_deref(),
+            Some("other")
+        );
+    }
+
+    #[test]
+    fn ignores_events_of_other_types() {
+        let (_provider, meter, _registry) = prometheus_meter();
+        let (recorded, instances) = storage_instances(&meter);
+        let mut event = storage_metrics("a", 0.0);
+        event.insert("Type".into(), Value::String("TLogMetrics".into()));
+
+        instances
+            .record(&event, &[])
+            .expect("record should succeed");
+        assert_eq!(recorded.len(), 1);
+        assert!(instances.series.lock().unwrap().series.is_empty());
+    }
+
+    #[test]
+    fn evicts_series_idle_past_the_interval() {
+        let (_provider, meter, _registry) = prometheus_meter();
+        let (_recorded, instances) = storage_instances(&meter);
+        let machine = |machine: &str| [KeyValue::new("machine", machine.to_string())];
+
+        instances.label(&storage_metrics("a", 0.0), &machine("10.0.0.1:4500"));
+        instances.label(&storage_metrics("b", 0.5), &machine("10.0.0.1:4500"));
+        instances.label(&storage_metrics("c", 1.0), &machine("10.0.0.2:4500"));
+        instances.label(&storage_metrics("d", 10.0), &machine("10.0.0.3:4500"));
+        assert_eq!(instances.series.lock().unwrap().series.len(), 2);
+
+        instances.label(&storage_metrics("d", 400.0), &machine("10.0.0.3:4500"));
+        assert_eq!(instances.series.lock().unwrap().series.len(), 1);
+    }
+}
@@ -2,7 +2,10 @@ pub mod metrics {
     use opentelemetry::metrics::{Meter, MeterProvider};
     use opentelemetry_prometheus::exporter as prometheus_exporter;
     use opentelemetry_sdk::metrics::SdkMeterProvider;
-    use prometheus::{proto::Metric, Registry};
+    use prometheus::{
+        proto::{Metric, MetricFamily},
+        Registry,
+    };
 
     pub fn prometheus_meter() -> (SdkMeterProvider, Meter, Registry) {
         let registry = Registry::new();
@@ -21,7 +24,17 @@ pub mod metrics {
         label_name: &str,
         label_value: &str,
     ) -> Option<Metric> {
-        let families = registry.gather();
+        find_metric_in(&registry.gather(), name, label_name, label_value)
+    }
+
+    // Look up a metric in an already gathered snapshot. Gauges only appear in the first gather
+    // after they are recorded, so tests checking several gauges should gather once.
+    pub fn find_metric_in(
+        families: &[MetricFamily],
+        name: &str,
+        label_name: &str,
+        label_value: &str,
+    ) -> Option<Metric> {
         let mut family = families.iter().find(|mf| mf.get_name() == name);
         if family.is_none() {
             let fallback = format!("{name}_total");
@@ -41,3 +54,61 @@ pub mod metrics {
         })
     }
 }
+
+pub mod recording {
+    use crate::{fdb_metric::FDBMetric, log_metrics::TraceEvent};
+    use anyhow::Result;
+    use opentelemetry::KeyValue;
+    use std::sync::{Arc, Mutex};
+
+    // A metric that keeps every event it is asked to record, and the labels it was recorded under,
+    // for tests of the event sources.
+    #[derive(Clone, Default)]
+    pub struct RecordingMetric {
+        events: Arc<Mutex<Vec<TraceEvent>>>,
+        labels: Arc<Mutex<Vec<Vec<KeyValue>>>>,
+    }
+
+    impl RecordingMetric {
+        pub fn events(&self) -> Vec<TraceEvent> {
+            self.events.lock().unwrap().clone()
+        }
+
+        // The `Type` of each recorded event, in the order they were recorded.
+        pub fn types(&self) -> Vec<String> {
+            self.events
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|event| event["Type"].as_str().unwrap_or_default().to_string())
+                .collect()
+        }
+
+        // The value of label `key` on each recorded event, if it had one.
+        pub fn label_values(&self, key: &str) -> Vec<Option<String>> {
+            self.labels
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|labels| {
+                    labels
+                        .iter()
+                        .find(|label| label.key.as_str() == key)
+                        .map(|label| label.value.to_string())
+                })
+                .collect()
+        }
+
+        pub fn len(&self) -> usize {
+            self.events.lock().unwrap().len()
+        }
+    }
+
+    impl FDBMetric for RecordingMetric {
+        fn record(&self, trace_event: &TraceEvent, labels: &[KeyValue]) -> Result<()> {
+            self.events.lock().unwrap().push(trace_event.clone());
+            self.labels.lock().unwrap().push(labels.to_vec());
+            Ok(())
+        }
+    }
+}
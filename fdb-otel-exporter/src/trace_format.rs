@@ -0,0 +1,198 @@
+use crate::log_metrics::TraceEvent;
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::path::Path;
+
+// Encodings FDB can write trace files in (`--trace_format`). Either way each event is one line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceFormat {
+    Json,
+    Xml,
+}
+
+impl TraceFormat {
+    // Format implied by a trace file's extension, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "json" => Some(Self::Json),
+            "xml" => Some(Self::Xml),
+            _ => None,
+        }
+    }
+
+    // Guess the format from a line's content, for files whose name does not give it away.
+    pub fn sniff(line: &str) -> Option<Self> {
+        match line.trim_start().as_bytes().first()? {
+            b'{' => Some(Self::Json),
+            b'<' => Some(Self::Xml),
+            _ => None,
+        }
+    }
+
+    // Format to parse `line` with: `known` if set, otherwise sniffed from the line and remembered
+    // for the rest of the file. Lines that match no format are treated as JSON.
+    pub fn resolve(known: &mut Option<Self>, line: &str) -> Self {
+        if known.is_none() {
+            *known = Self::sniff(line);
+        }
+        known.unwrap_or(Self::Json)
+    }
+
+    // Parse one trimmed trace line. Returns `None` for lines that carry no event, such as the XML
+    // declaration and the `<Trace>` wrapper.
+    pub fn parse_line(&self, line: &str) -> Result<Option<TraceEvent>> {
+        match self {
+            Self::Json => Ok(Some(serde_json::from_str::<TraceEvent>(line)?)),
+            Self::Xml => parse_xml_line(line),
+        }
+    }
+}
+
+// FDB writes XML trace files as an XML declaration, `<Trace>`, one self-closing `<Event .../>`
+// element per line with every field as an attribute, and finally `</Trace>`.
+fn parse_xml_line(line: &str) -> Result<Option<TraceEvent>> {
+    if line.starts_with("<?xml") || line == "<Trace>" || line == "</Trace>" {
+        return Ok(None);
+    }
+
+    let attributes = line
+        .strip_prefix("<Event")
+        .filter(|rest| rest.starts_with(char::is_whitespace) || rest.starts_with('/'))
+        .and_then(|rest| rest.strip_suffix("/>"))
+        .context("expected a self-closing <Event .../> element")?;
+
+    let mut event = TraceEvent::new();
+    let mut remaining = attributes.trim_start();
+    while !remaining.is_empty() {
+        let (name, rest) = remaining
+            .split_once('=')
+            .with_context(|| format!("attribute without a value near {remaining:?}"))?;
+        let name = name.trim_end();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            bail!("invalid attribute name {name:?}");
+        }
+
+        let rest = rest.trim_start();
+        let quote = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => quote,
+            _ => bail!("attribute {name} value is not quoted"),
+        };
+        let rest = &rest[1..];
+        let end = rest
+            .find(quote)
+            .with_context(|| format!("attribute {name} value is not terminated"))?;
+
+        let value = unescape_xml(&rest[..end])
+            .with_context(|| format!("attribute {name} has an invalid escape"))?;
+        event.insert(name.to_string(), Value::String(value));
+        remaining = rest[end + 1..].trim_start();
+    }
+
+    Ok(Some(event))
+}
+
+// Replace the predefined XML entities and numeric character references in an attribute value.
+fn unescape_xml(value: &str) -> Result<String> {
+    let mut unescaped = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        unescaped.push_str(&rest[..start]);
+        let end = rest[start..].find(';').context("unterminated entity")? + start;
+        let entity = &rest[start + 1..end];
+        let character = match entity {
+            "amp" => '&',
+            "lt" => '<',
+            "gt" => '>',
+            "quot" => '"',
+            "apos" => '\'',
+            _ => {
+                let code = if let Some(hex) = entity.strip_prefix("#x") {
+                    u32::from_str_radix(hex, 16).ok()
+                } else if let Some(decimal) = entity.strip_prefix('#') {
+                    decimal.parse::<u32>().ok()
+                } else {
+                    None
+                };
+                code.and_then(char::from_u32)
+                    .with_context(|| format!("unknown entity &{entity};"))?
+            }
+        };
+        unescaped.push(character);
+        rest = &rest[end + 1..];
+    }
+    unescaped.push_str(rest);
+    Ok(unescaped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn field<'a>(event: &'a TraceEvent, name: &str) -> &'a str {
+        event
+            .get(name)
+            .and_then(Value::as_str)
+            .unwrap_or_else(|| panic!("missing field {name}"))
+    }
+
+    #[test]
+    fn xml_event_matches_json_event() {
+        let xml = r#"<Event Severity="10" Time="1700000000.123456" Type="StorageMetrics" ID="abc" Machine="10.0.0.1:4500" Roles="SS" BytesInput="1 2 3" />"#;
+        let json = r#"{"Severity":"10","Time":"1700000000.123456","Type":"StorageMetrics","ID":"abc","Machine":"10.0.0.1:4500","Roles":"SS","BytesInput":"1 2 3"}"#;
+
+        let from_xml = TraceFormat::Xml.parse_line(xml).unwrap().unwrap();
+        let from_json = TraceFormat::Json.parse_line(json).unwrap().unwrap();
+        assert_eq!(from_xml, from_json);
+    }
+
+    #[test]
+    fn xml_parser_unescapes_attribute_values() {
+        let line = r#"<Event Type="Error" Reason="a &lt; b &amp;&amp; &quot;c&quot; &#x41;&#66;" Note='it&apos;s'/>"#;
+        let event = TraceFormat::Xml.parse_line(line).unwrap().unwrap();
+        assert_eq!(field(&event, "Reason"), r#"a < b && "c" AB"#);
+        assert_eq!(field(&event, "Note"), "it's");
+    }
+
+    #[test]
+    fn xml_parser_skips_document_framing() {
+        for line in [r#"<?xml version="1.0"?>"#, "<Trace>", "</Trace>"] {
+            assert!(TraceFormat::Xml.parse_line(line).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn xml_parser_rejects_malformed_lines() {
+        for line in [
+            r#"<Event Type="Unclosed""#,
+            r#"<Event Type=Unquoted />"#,
+            r#"<Event Type="Open />"#,
+            r#"<Event Type="Bad &bogus;" />"#,
+            r#"<EventType="X" />"#,
+            "garbage",
+        ] {
+            assert!(
+                TraceFormat::Xml.parse_line(line).is_err(),
+                "expected error for {line}"
+            );
+        }
+    }
+
+    #[test]
+    fn format_is_detected_from_extension_or_content() {
+        assert_eq!(
+            TraceFormat::from_path(Path::new("trace.1.json")),
+            Some(TraceFormat::Json)
+        );
+        assert_eq!(
+            TraceFormat::from_path(Path::new("trace.1.xml")),
+            Some(TraceFormat::Xml)
+        );
+        assert_eq!(TraceFormat::from_path(Path::new("trace.1.log")), None);
+        assert_eq!(
+            TraceFormat::sniff("  {\"Type\":1}"),
+            Some(TraceFormat::Json)
+        );
+        assert_eq!(TraceFormat::sniff("<Event />"), Some(TraceFormat::Xml));
+        assert_eq!(TraceFormat::sniff("plain text"), None);
+    }
+}
@@ -1,167 +1,635 @@
 use crate::{
+    change_notifier::ChangeNotifier,
+    checkpoint::{hash_first_line, CheckpointStore, FileCheckpoint},
+    config::{BackfillOptions, PipelineOptions, StartMode, WatchMode},
     exporter_metrics::ExporterMetrics,
+    line_assembler::{Assembled, LineAssembler},
     log_metrics::{LogMetrics, TraceEvent},
+    log_sources::LogRoot,
+    pipeline::{EventPipeline, LineSender},
+    trace_format::TraceFormat,
 };
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use opentelemetry::metrics::MeterProvider;
-use opentelemetry_sdk::metrics::SdkMeterProvider;
-use std::collections::HashSet;
+use opentelemetry::metrics::Meter;
+use std::collections::{HashMap, HashSet};
 use std::io::SeekFrom;
+use std::os::unix::fs::MetadataExt;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::time::Duration;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tokio::fs;
-use tokio::io::{AsyncBufReadExt, AsyncSeekExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader};
+use tokio::sync::Notify;
+use tokio::task::JoinHandle;
 use tokio::time;
 
-// Discover JSON trace logs under `log_dir_path` and push their events through the configured gauges.
+// Settings shared by the directory watcher and the tailers it spawns.
+#[derive(Debug, Clone, Copy)]
+pub struct WatchOptions {
+    pub poll_interval: Duration,
+    pub start_mode: StartMode,
+    pub idle_timeout: Duration,
+    pub watch_mode: WatchMode,
+    pub max_line_bytes: usize,
+    pub pipeline: PipelineOptions,
+}
+
+// Discover JSON trace logs under each of `roots` and push their events through `log_metrics`.
 pub async fn watch_logs(
-    log_dir_path: &Path,
-    meter_provider: Arc<SdkMeterProvider>,
-    poll_interval: Duration,
+    roots: &[LogRoot],
+    log_metrics: LogMetrics,
+    exporter_metrics: ExporterMetrics,
+    meter: &Meter,
+    options: WatchOptions,
+    checkpoints: CheckpointStore,
 ) -> Result<()> {
     watch_logs_with_fs(
-        log_dir_path,
-        meter_provider,
-        poll_interval,
+        roots,
+        log_metrics,
+        exporter_metrics,
+        meter,
+        options,
+        checkpoints,
         RealTraceFileSystem,
     )
     .await
 }
 
 async fn watch_logs_with_fs<F>(
-    log_dir_path: &Path,
-    meter_provider: Arc<SdkMeterProvider>,
-    poll_interval: Duration,
+    roots: &[LogRoot],
+    log_metrics: LogMetrics,
+    exporter_metrics: ExporterMetrics,
+    meter: &Meter,
+    options: WatchOptions,
+    checkpoints: CheckpointStore,
     fs: F,
 ) -> Result<()>
 where
     F: TraceFileSystem,
 {
-    let meter = meter_provider.meter("fdb-otel-exporter");
-    let exporter_metrics = ExporterMetrics::new(&meter);
-    let log_metrics =
-        LogMetrics::new(&meter).with_context(|| "failed to load gauge configuration")?;
+    let pipeline = EventPipeline::start(options.pipeline, exporter_metrics.clone())?;
+    pipeline.observe_queue_depth(meter);
+    let backfill = BackfillLimiter::for_start_mode(options.start_mode);
 
-    fs.create_dir_all(log_dir_path)
-        .await
-        .with_context(|| format!("failed to create log directory {}", log_dir_path.display()))?;
-
-    let watcher_dir = log_dir_path.to_path_buf();
-    let dir_metrics = log_metrics.clone();
-    let directory_metrics = exporter_metrics.clone();
-    let dir_fs = fs.clone();
-    tokio::spawn(async move {
-        if let Err(error) = run_log_directory(
-            watcher_dir,
-            dir_metrics,
-            directory_metrics,
-            poll_interval,
-            dir_fs,
-        )
-        .await
-        {
-            tracing::error!(?error, "log directory watcher terminated");
-        }
-    });
+    for root in roots {
+        fs.create_dir_all(&root.path)
+            .await
+            .with_context(|| format!("failed to create log directory {}", root.path.display()))?;
+    }
+
+    for root in roots {
+        let watcher_root = root.clone();
+        let dir_metrics = log_metrics.with_static_labels(&root.labels);
+        let dir_pipeline = pipeline.clone();
+        let dir_backfill = backfill.clone();
+        let directory_metrics = exporter_metrics.clone();
+        let dir_checkpoints = checkpoints.clone();
+        let dir_fs = fs.clone();
+        tokio::spawn(async move {
+            let dir = watcher_root.path.clone();
+            if let Err(error) = run_log_directory(
+                watcher_root,
+                dir_metrics,
+                dir_pipeline,
+                directory_metrics,
+                options,
+                dir_backfill,
+                dir_checkpoints,
+                dir_fs,
+            )
+            .await
+            {
+                tracing::error!(?error, dir = %dir.display(), "log directory watcher terminated");
+            }
+        });
+    }
     Ok(())
 }
 
-// Poll the log directory, spawning a tail task for each new `trace.*.json` file encountered.
+// How long a tailer at EOF sleeps before checking its file again when polling.
+const TAILER_POLL_INTERVAL: Duration = Duration::from_millis(250);
+// With change notifications a tailer still rechecks its file this often, in case one was missed.
+const TAILER_NOTIFY_FALLBACK: Duration = Duration::from_secs(5);
+
+// A running tailer task and the signals it shares with the directory watcher.
+struct TailerHandle {
+    task: JoinHandle<()>,
+    signals: TailerSignals,
+}
+
+// The last time a tailer read anything, and the notification it waits on for new data when
+// change notifications are available.
+#[derive(Clone)]
+struct TailerSignals {
+    last_read: Arc<Mutex<Instant>>,
+    file_changed: Option<Arc<Notify>>,
+}
+
+impl TailerSignals {
+    fn polling() -> Self {
+        Self {
+            last_read: Arc::new(Mutex::new(Instant::now())),
+            file_changed: None,
+        }
+    }
+
+    fn notified(file_changed: Arc<Notify>) -> Self {
+        Self {
+            file_changed: Some(file_changed),
+            ..Self::polling()
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_read.lock().expect("tailer activity poisoned") = Instant::now();
+    }
+
+    fn idle_for(&self) -> Duration {
+        self.last_read
+            .lock()
+            .expect("tailer activity poisoned")
+            .elapsed()
+    }
+
+    // Wait at EOF until the file may have changed.
+    async fn wait_for_change(&self) {
+        match &self.file_changed {
+            Some(file_changed) => {
+                let _ = time::timeout(TAILER_NOTIFY_FALLBACK, file_changed.notified()).await;
+            }
+            None => time::sleep(TAILER_POLL_INTERVAL).await,
+        }
+    }
+}
+
+// Scan a log root, keeping one tail task per matching trace file that is still worth following,
+// and persist tailer checkpoints once per scan. FDB rolls trace files continually, so tailers are
+// retired once their file is deleted, or once a newer file from the same process exists and the
+// old one has been idle for `idle_timeout`. In notify mode a scan also runs as soon as a file is
+// created, renamed or deleted, and tailers wake on writes instead of polling; the poll interval
+// then only bounds how long a missed notification can go unnoticed.
+#[allow(clippy::too_many_arguments)]
 async fn run_log_directory(
-    dir: PathBuf,
+    root: LogRoot,
     metrics: LogMetrics,
+    pipeline: EventPipeline,
     exporter_metrics: ExporterMetrics,
-    poll_interval: Duration,
+    options: WatchOptions,
+    backfill: BackfillLimiter,
+    checkpoints: CheckpointStore,
     fs: impl TraceFileSystem,
 ) -> Result<()> {
-    let mut tailed: HashSet<PathBuf> = HashSet::new();
+    // Change notifications name files by absolute path, so list files under one too.
+    let root = LogRoot {
+        path: std::path::absolute(&root.path)
+            .with_context(|| format!("failed to resolve log directory {}", root.path.display()))?,
+        ..root
+    };
+    let mut tailers: HashMap<PathBuf, TailerHandle> = HashMap::new();
+    // Superseded files that stay on disk until FDB deletes them, and must not be tailed again.
+    let mut retired: HashSet<PathBuf> = HashSet::new();
+    let notifier = match options.watch_mode {
+        WatchMode::Notify => match fs.watch_changes(&root.path, root.recursive).await {
+            Ok(notifier) => Some(notifier),
+            Err(error) => {
+                tracing::warn!(?error, dir = %root.path.display(), "file change notifications unavailable, polling instead");
+                None
+            }
+        },
+        WatchMode::Poll => None,
+    };
+    let forget = |path: &Path| {
+        if let Some(notifier) = &notifier {
+            notifier.forget(path);
+        }
+    };
 
     loop {
-        match fs.read_dir(&dir).await {
-            Ok(entries) => {
-                for path in entries {
-                    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        match list_trace_files(&fs, &root).await {
+            Ok(trace_files) => {
+                let present: HashSet<&PathBuf> = trace_files.iter().collect();
+                let superseded = superseded_trace_files(&trace_files);
+
+                tailers.retain(|path, handle| {
+                    if present.contains(path) {
+                        return true;
+                    }
+                    tracing::info!(file = %path.display(), "log file removed, retiring tailer");
+                    handle.task.abort();
+                    exporter_metrics.record_tailer_retired("deleted");
+                    exporter_metrics.forget_file(path);
+                    checkpoints.remove(path);
+                    forget(path);
+                    false
+                });
+                retired.retain(|path| present.contains(path));
+
+                for path in superseded.iter() {
+                    let Some(handle) = tailers.get(*path) else {
                         continue;
                     };
+                    if handle.signals.idle_for() < options.idle_timeout {
+                        continue;
+                    }
+                    tracing::info!(file = %path.display(), "log file superseded, retiring tailer");
+                    handle.task.abort();
+                    exporter_metrics.record_tailer_retired("superseded");
+                    exporter_metrics.forget_file(path);
+                    forget(path);
+                    tailers.remove(*path);
+                    retired.insert((*path).clone());
+                }
 
-                    if !should_tail_file(file_name) {
+                for path in trace_files.iter() {
+                    if tailers.contains_key(path) || retired.contains(path) {
                         continue;
                     }
 
-                    if tailed.insert(path.clone()) {
-                        tracing::info!(file = %path.display(), "starting log tailer");
-                        let task_metrics = metrics.clone();
-                        let task_exporter_metrics = exporter_metrics.clone();
-                        let task_path = path.clone();
-                        let task_fs = fs.clone();
-                        tokio::spawn(async move {
-                            if let Err(error) = run_log_tailer(
-                                task_path.clone(),
-                                task_metrics,
-                                task_exporter_metrics,
-                                task_fs,
-                            )
-                            .await
-                            {
-                                tracing::error!(?error, file = %path.display(), "log tailer exited");
-                            }
-                        });
+                    // Without a checkpoint, tail mode would start at the end of a file that the
+                    // process has already moved on from, so there is nothing to read.
+                    if superseded.contains(path)
+                        && matches!(options.start_mode, StartMode::Tail)
+                        && checkpoints.get(path).is_none()
+                    {
+                        retired.insert(path.clone());
+                        continue;
                     }
+
+                    tracing::info!(file = %path.display(), "starting log tailer");
+                    let signals = match &notifier {
+                        Some(notifier) => TailerSignals::notified(notifier.file(path)),
+                        None => TailerSignals::polling(),
+                    };
+                    let progress = exporter_metrics.track_file(path, &trace_file_process(path));
+                    let task_lines = pipeline.sender(path, metrics.clone(), progress);
+                    let task_exporter_metrics = exporter_metrics.clone();
+                    let task_path = path.clone();
+                    let task_checkpoints = checkpoints.clone();
+                    let task_signals = signals.clone();
+                    let task_fs = fs.clone();
+                    let task_backfill = backfill.clone();
+                    let task = tokio::spawn(async move {
+                        if let Err(error) = run_log_tailer(
+                            task_path.clone(),
+                            task_lines,
+                            task_exporter_metrics,
+                            task_checkpoints,
+                            TailerOptions {
+                                start_mode: options.start_mode,
+                                format: root.format,
+                                max_line_bytes: options.max_line_bytes,
+                                backfill: task_backfill,
+                            },
+                            task_signals,
+                            task_fs,
+                        )
+                        .await
+                        {
+                            tracing::error!(?error, file = %task_path.display(), "log tailer exited");
+                        }
+                    });
+                    tailers.insert(path.clone(), TailerHandle { task, signals });
                 }
+
+                exporter_metrics.record_active_tailers(&root.path, tailers.len() as u64);
             }
             Err(error) => {
-                tracing::warn!(?error, dir = %dir.display(), "failed to read log directory");
+                tracing::warn!(?error, dir = %root.path.display(), "failed to read log directory");
+            }
+        }
+
+        if let Err(error) = checkpoints.flush_in_background().await {
+            tracing::warn!(?error, "failed to persist log checkpoints");
+        }
+
+        match &notifier {
+            Some(notifier) => {
+                let _ = time::timeout(options.poll_interval, notifier.directory_changed()).await;
+            }
+            None => time::sleep(options.poll_interval).await,
+        }
+    }
+}
+
+// List the files under `root` that its filter selects, descending into subdirectories when the root
+// is recursive. An unreadable subdirectory is skipped rather than failing the whole scan.
+async fn list_trace_files(fs: &impl TraceFileSystem, root: &LogRoot) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut pending = vec![root.path.clone()];
+
+    while let Some(dir) = pending.pop() {
+        let entries = match fs.read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(error) if dir != root.path => {
+                tracing::warn!(?error, dir = %dir.display(), "failed to read log subdirectory");
+                continue;
+            }
+            Err(error) => return Err(error),
+        };
+
+        for entry in entries {
+            if entry.is_dir {
+                if root.recursive {
+                    pending.push(entry.path);
+                }
+                continue;
+            }
+            let relative = entry.path.strip_prefix(&root.path).unwrap_or(&entry.path);
+            if root.filter.matches(relative) {
+                files.push(entry.path);
             }
         }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+// FDB names trace files `trace.<ip>.<port>.<timestamp>.<id>.<index>.json`. The address identifies
+// the process that wrote the file, and the numeric parts after it order that process's files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct TraceFileName {
+    process: String,
+    sequence: Vec<u64>,
+}
+
+// Parse a trace file name into its process address and ordering key, or `None` if the name does
+// not follow FDB's convention (such files are never considered superseded).
+fn parse_trace_file_name(file_name: &str) -> Option<TraceFileName> {
+    let stem = file_name.strip_prefix("trace.")?;
+    let stem = stem.rsplit_once('.').map(|(stem, _extension)| stem)?;
+    let parts: Vec<&str> = stem.split('.').collect();
+
+    // The timestamp is the first long all-digit component after at least an address and port.
+    let timestamp_index = parts.iter().enumerate().skip(2).find_map(|(index, part)| {
+        (part.len() >= 9 && part.bytes().all(|byte| byte.is_ascii_digit())).then_some(index)
+    })?;
+
+    let (port, address) = parts[..timestamp_index].split_last()?;
+    let sequence = parts[timestamp_index..]
+        .iter()
+        .filter_map(|part| part.parse::<u64>().ok())
+        .collect();
+
+    Some(TraceFileName {
+        process: format!("{}:{}", address.join("."), port),
+        sequence,
+    })
+}
+
+// Address of the process that wrote a trace file, for labeling its per-file metrics.
+pub(crate) fn trace_file_process(path: &Path) -> String {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .and_then(parse_trace_file_name)
+        .map(|name| name.process)
+        .unwrap_or_else(|| String::from("unknown"))
+}
 
-        time::sleep(poll_interval).await;
+// Return every trace file for which the same process has written a newer file.
+fn superseded_trace_files(paths: &[PathBuf]) -> HashSet<&PathBuf> {
+    let mut newest: HashMap<String, (&PathBuf, Vec<u64>)> = HashMap::new();
+    let mut superseded = HashSet::new();
+
+    for path in paths {
+        let Some(name) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_trace_file_name)
+        else {
+            continue;
+        };
+
+        match newest.get_mut(&name.process) {
+            Some((current, sequence)) if *sequence < name.sequence => {
+                superseded.insert(*current);
+                *current = path;
+                *sequence = name.sequence;
+            }
+            Some(_) => {
+                superseded.insert(path);
+            }
+            None => {
+                newest.insert(name.process, (path, name.sequence));
+            }
+        }
     }
+
+    superseded
+}
+
+// How a tailer starts reading its file, which trace format the file is in (`None` to detect it
+// from the file name or, failing that, the first event line), the longest line it accepts and the
+// limiter that paces its catch-up in backfill mode.
+#[derive(Clone)]
+struct TailerOptions {
+    start_mode: StartMode,
+    format: Option<TraceFormat>,
+    max_line_bytes: usize,
+    backfill: BackfillLimiter,
 }
 
-// Tail a single trace file and forward each JSON line to the metrics recorder.
+// Tail a single trace file and forward each event line to the metrics recorder. The tailer tracks
+// the identity of the open file and its read offset so truncation or replacement (rename-based
+// rotation, or a recreated file with a new inode) is noticed and the file is reread from the start.
+// A saved checkpoint for the file takes the place of the initial seek to the end; in backfill mode
+// a file without a checkpoint is read from the start, throttled until it has caught up. Once caught
+// up, the delay between each event's `Time` and it being recorded is reported as event latency.
+// A line is only handled once its newline has been written; the saved offset stays at the start of
+// an unfinished line so a reopen reads it again in full.
 async fn run_log_tailer(
     path: PathBuf,
-    metrics: LogMetrics,
+    lines: LineSender,
     exporter_metrics: ExporterMetrics,
+    checkpoints: CheckpointStore,
+    options: TailerOptions,
+    signals: TailerSignals,
     fs: impl TraceFileSystem,
 ) -> Result<()> {
+    let start_mode = options.start_mode;
+    let tailer_backfill = options.backfill;
+    let mut format = options.format.or_else(|| TraceFormat::from_path(&path));
+    let mut position: Option<TailPosition> = match checkpoints.get(&path) {
+        Some(checkpoint) => Some(restore_position(&fs, &path, checkpoint).await),
+        None => None,
+    };
+    let mut catch_up_pending = matches!(start_mode, StartMode::Backfill(_));
+    let mut catch_up: Option<CatchUp> = None;
+
     loop {
         match fs.open_reader(&path).await {
             Ok(mut reader) => {
-                if let Err(error) = reader
-                    .seek_to_end()
-                    .await
+                let opened = match reader.metadata().await {
+                    Ok(metadata) => metadata,
+                    Err(error) => {
+                        tracing::warn!(?error, log_path = %path.display(), "unable to stat log file, retrying");
+                        time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+
+                let seek_result = match position {
+                    None => match start_mode {
+                        StartMode::Tail => reader.seek_to_end().await,
+                        StartMode::Backfill(_) => reader.seek(0).await,
+                    },
+                    Some(previous) => {
+                        match detect_file_change(&previous.file, &opened, previous.offset) {
+                            None => reader.seek(previous.offset).await,
+                            Some(change) => {
+                                tracing::info!(
+                                    log_path = %path.display(),
+                                    change = change.as_str(),
+                                    "log file rotated, reading from start"
+                                );
+                                exporter_metrics.record_file_rotation(change.as_str());
+                                if let Some(abandoned) = catch_up.take() {
+                                    lines
+                                        .progress()
+                                        .record_backfill(abandoned.target, abandoned.target);
+                                }
+                                position = Some(TailPosition {
+                                    file: opened,
+                                    offset: 0,
+                                });
+                                reader.seek(0).await
+                            }
+                        }
+                    }
+                };
+
+                let mut offset = match seek_result
                     .with_context(|| format!("failed to seek log file {}", path.display()))
                 {
-                    tracing::warn!(?error, "unable to initialize log tail, retrying");
-                    time::sleep(Duration::from_secs(1)).await;
-                    continue;
+                    Ok(offset) => offset,
+                    Err(error) => {
+                        tracing::warn!(?error, "unable to initialize log tail, retrying");
+                        time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+
+                let mut first_line_hash = if checkpoints.is_enabled() {
+                    read_first_line_hash(&fs, &path).await
+                } else {
+                    None
+                };
+                checkpoints.update(&path, opened.checkpoint(offset, first_line_hash));
+                lines.progress().record_position(offset, opened.len);
+
+                if catch_up_pending {
+                    catch_up_pending = false;
+                    if let StartMode::Backfill(options) = start_mode {
+                        if offset < opened.len {
+                            tracing::info!(log_path = %path.display(), bytes = opened.len - offset, "backfilling log file");
+                            lines.progress().record_backfill(offset, opened.len);
+                            catch_up =
+                                Some(CatchUp::new(options, opened.len, tailer_backfill.clone()));
+                        }
+                    }
                 }
 
-                let mut line = String::new();
+                // `offset` only advances past complete lines; `read_offset` is how far the reader
+                // has got, including any unfinished line held by the assembler.
+                let mut read_offset = offset;
+                let mut assembler = LineAssembler::new(options.max_line_bytes);
+                let mut chunk = Vec::new();
 
-                loop {
-                    line.clear();
-                    match reader.read_line(&mut line).await {
+                let resume_offset = loop {
+                    chunk.clear();
+                    match reader.read_line(&mut chunk, assembler.read_limit()).await {
                         Ok(0) => {
-                            time::sleep(Duration::from_millis(250)).await;
+                            // At EOF, compare the open file against whatever currently lives at the
+                            // path; a different inode or a shorter file means it was rotated.
+                            let rotated = match fs.metadata(&path).await {
+                                Ok(current) => {
+                                    let change = detect_file_change(&opened, &current, read_offset);
+                                    if change.is_none() {
+                                        lines.progress().record_position(read_offset, current.len);
+                                    }
+                                    change.is_some()
+                                }
+                                Err(_) => false,
+                            };
+                            if rotated {
+                                if assembler.abandon() {
+                                    tracing::warn!(log_path = %path.display(), "dropping unfinished line from rotated log file");
+                                    exporter_metrics.record_partial_line_dropped("abandoned");
+                                }
+                                break read_offset;
+                            }
+                            signals.wait_for_change().await;
                         }
-                        Ok(_) => {
-                            let trimmed = line.trim();
-                            if trimmed.is_empty() {
-                                continue;
+                        Ok(bytes) => {
+                            signals.touch();
+                            read_offset += bytes as u64;
+                            lines.progress().record_read(bytes as u64, read_offset);
+                            let line_start = offset;
+                            let assembled = assembler.push(&chunk);
+                            if assembler.at_line_boundary() {
+                                offset = read_offset;
+                                if line_start == 0 && first_line_hash.is_none() {
+                                    if let Assembled::Line(line) = &assembled {
+                                        first_line_hash = Some(hash_first_line(line));
+                                    }
+                                }
+                                checkpoints
+                                    .update(&path, opened.checkpoint(offset, first_line_hash));
+                            }
+
+                            let line = match assembled {
+                                Assembled::Line(line) => {
+                                    lines.progress().record_line();
+                                    line
+                                }
+                                Assembled::Oversized => {
+                                    tracing::warn!(
+                                        log_path = %path.display(),
+                                        max_line_bytes = options.max_line_bytes,
+                                        "dropping oversized log line"
+                                    );
+                                    exporter_metrics.record_partial_line_dropped("oversized");
+                                    continue;
+                                }
+                                Assembled::Waiting => continue,
+                            };
+
+                            let text = String::from_utf8_lossy(&line);
+                            let trimmed = text.trim();
+                            if !trimmed.is_empty() {
+                                lines
+                                    .send(
+                                        trimmed.to_string(),
+                                        TraceFormat::resolve(&mut format, trimmed),
+                                        catch_up.as_ref().and_then(|state| state.since),
+                                        catch_up.is_none(),
+                                    )
+                                    .await?;
+                            }
+
+                            if let Some(state) = catch_up.as_mut() {
+                                if read_offset >= state.target {
+                                    tracing::info!(log_path = %path.display(), "backfill complete");
+                                    lines.progress().record_backfill(read_offset, state.target);
+                                    catch_up = None;
+                                } else if state.throttle().await {
+                                    lines.progress().record_backfill(read_offset, state.target);
+                                }
                             }
-                            handle_log_line(trimmed, &metrics, &exporter_metrics);
                         }
                         Err(error) => {
                             tracing::warn!(?error, "log tailer read error, reopening file");
                             time::sleep(Duration::from_secs(1)).await;
-                            break;
+                            break offset;
                         }
                     }
-                }
+                };
+
+                position = Some(TailPosition {
+                    file: opened,
+                    offset: resume_offset,
+                });
             }
             Err(error) => {
                 tracing::warn!(?error, log_path = %path.display(), "log file unavailable, retrying");
@@ -171,14 +639,206 @@ async fn run_log_tailer(
     }
 }
 
-fn should_tail_file(file_name: &str) -> bool {
-    file_name.starts_with("trace.") && file_name.ends_with(".json")
+const BACKFILL_TICK: Duration = Duration::from_millis(100);
+
+// Paces the initial read of existing file contents in backfill mode. One limiter is shared by every
+// tailer, so the configured rate bounds the catch-up of all files together and backfilling many
+// files at once does not starve live tailers on the runtime.
+#[derive(Clone)]
+struct BackfillLimiter {
+    lines_per_tick: u64,
+    tick: Arc<Mutex<BackfillTick>>,
+}
+
+struct BackfillTick {
+    started: Instant,
+    lines: u64,
+}
+
+impl BackfillLimiter {
+    fn new(max_lines_per_sec: f64) -> Self {
+        Self {
+            lines_per_tick: ((max_lines_per_sec * BACKFILL_TICK.as_secs_f64()).ceil() as u64)
+                .max(1),
+            tick: Arc::new(Mutex::new(BackfillTick {
+                started: Instant::now(),
+                lines: 0,
+            })),
+        }
+    }
+
+    // Tail mode never catches up, so its limiter is never used.
+    fn for_start_mode(start_mode: StartMode) -> Self {
+        match start_mode {
+            StartMode::Backfill(options) => Self::new(options.max_lines_per_sec),
+            StartMode::Tail => Self::new(f64::INFINITY),
+        }
+    }
+
+    // Take one line from the current tick's budget, waiting for the next tick once it is used up.
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut tick = self.tick.lock().expect("backfill limiter poisoned");
+                let elapsed = tick.started.elapsed();
+                if elapsed >= BACKFILL_TICK {
+                    tick.started = Instant::now();
+                    tick.lines = 0;
+                }
+                if tick.lines < self.lines_per_tick {
+                    tick.lines += 1;
+                    return;
+                }
+                BACKFILL_TICK - elapsed
+            };
+            time::sleep(wait).await;
+        }
+    }
+}
+
+// Progress of the initial read of a file's existing contents in backfill mode. Lines are paced by
+// the shared limiter, and progress is reported after every tick's worth of this file's lines.
+struct CatchUp {
+    target: u64,
+    since: Option<f64>,
+    limiter: BackfillLimiter,
+    lines_since_progress: u64,
+}
+
+impl CatchUp {
+    fn new(options: BackfillOptions, target: u64, limiter: BackfillLimiter) -> Self {
+        Self {
+            target,
+            since: options.since,
+            limiter,
+            lines_since_progress: 0,
+        }
+    }
+
+    // Count one line against the shared budget, pausing while it is used up. Returns true once a
+    // tick's worth of lines has been read from this file, which is when callers report progress.
+    async fn throttle(&mut self) -> bool {
+        self.limiter.acquire().await;
+        self.lines_since_progress += 1;
+        if self.lines_since_progress < self.limiter.lines_per_tick {
+            return false;
+        }
+
+        tokio::task::yield_now().await;
+        self.lines_since_progress = 0;
+        true
+    }
+}
+
+// Identity and size of a trace file, used to detect rotation between reads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TraceFileMetadata {
+    device: u64,
+    inode: u64,
+    len: u64,
+}
+
+impl TraceFileMetadata {
+    fn checkpoint(&self, offset: u64, first_line_hash: Option<u64>) -> FileCheckpoint {
+        FileCheckpoint {
+            device: self.device,
+            inode: self.inode,
+            offset,
+            first_line_hash,
+        }
+    }
+}
+
+// Where a tailer left off in a particular file, so a reopen can resume from the same byte.
+#[derive(Debug, Clone, Copy)]
+struct TailPosition {
+    file: TraceFileMetadata,
+    offset: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FileChange {
+    Truncated,
+    Replaced,
+}
+
+impl FileChange {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Truncated => "truncated",
+            Self::Replaced => "replaced",
+        }
+    }
+}
+
+// Compare the file a tailer was reading with the file now at the same path.
+fn detect_file_change(
+    previous: &TraceFileMetadata,
+    current: &TraceFileMetadata,
+    offset: u64,
+) -> Option<FileChange> {
+    if previous.device != current.device || previous.inode != current.inode {
+        Some(FileChange::Replaced)
+    } else if current.len < offset {
+        Some(FileChange::Truncated)
+    } else {
+        None
+    }
+}
+
+// Turn a saved checkpoint into a starting position. The usual rotation checks then apply when the
+// file is opened; in addition, a first line that no longer matches means the inode was reused by a
+// different file, so it is read from the start.
+async fn restore_position(
+    fs: &impl TraceFileSystem,
+    path: &Path,
+    checkpoint: FileCheckpoint,
+) -> TailPosition {
+    let file = TraceFileMetadata {
+        device: checkpoint.device,
+        inode: checkpoint.inode,
+        len: checkpoint.offset,
+    };
+
+    let offset = match (
+        checkpoint.first_line_hash,
+        read_first_line_hash(fs, path).await,
+    ) {
+        (Some(saved), Some(current)) if saved != current => {
+            tracing::info!(log_path = %path.display(), "checkpoint does not match log file, reading from start");
+            0
+        }
+        _ => checkpoint.offset,
+    };
+
+    TailPosition { file, offset }
+}
+
+// Hash the first complete line of `path`, or `None` if the file does not have one yet.
+async fn read_first_line_hash(fs: &impl TraceFileSystem, path: &Path) -> Option<u64> {
+    let mut reader = fs.open_reader(path).await.ok()?;
+    let mut line = Vec::new();
+    reader.read_line(&mut line, usize::MAX).await.ok()?;
+    line.ends_with(b"\n").then(|| hash_first_line(&line))
 }
 
-fn handle_log_line(trimmed: &str, metrics: &LogMetrics, exporter_metrics: &ExporterMetrics) {
-    match serde_json::from_str::<TraceEvent>(trimmed) {
-        Ok(record) => match metrics.record(&record) {
-            Ok(()) => exporter_metrics.record_processed(),
+// Parse and record one trace line, returning the event's `Time` if it was recorded. Events whose
+// `Time` is before `since` are skipped, as are lines that carry no event.
+pub(crate) fn handle_log_line(
+    trimmed: &str,
+    format: TraceFormat,
+    metrics: &LogMetrics,
+    exporter_metrics: &ExporterMetrics,
+    since: Option<f64>,
+) -> Option<f64> {
+    match format.parse_line(trimmed) {
+        Ok(None) => None,
+        Ok(Some(record)) if is_before(&record, since) => None,
+        Ok(Some(record)) => match metrics.record(&record) {
+            Ok(()) => {
+                exporter_metrics.record_processed();
+                trace_time(&record)
+            }
             Err(error) => {
                 exporter_metrics.record_record_error();
                 tracing::warn!(
@@ -186,19 +846,52 @@ fn handle_log_line(trimmed: &str, metrics: &LogMetrics, exporter_metrics: &Expor
                     raw_line = %trimmed,
                     "failed to record log line"
                 );
+                None
             }
         },
         Err(error) => {
             exporter_metrics.record_parse_error();
             tracing::warn!(?error, raw_line = %trimmed, "failed to parse log line");
+            None
         }
     }
 }
 
+pub(crate) fn trace_time(trace_event: &TraceEvent) -> Option<f64> {
+    trace_event
+        .get("Time")
+        .and_then(|value| value.as_str())
+        .and_then(|time| time.parse::<f64>().ok())
+}
+
+fn is_before(trace_event: &TraceEvent, since: Option<f64>) -> bool {
+    let Some(cutoff) = since else {
+        return false;
+    };
+    trace_time(trace_event).is_some_and(|time| time < cutoff)
+}
+
+pub(crate) fn unix_now() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs_f64())
+        .unwrap_or_default()
+}
+
 #[async_trait]
 trait TraceFileReader {
-    async fn seek_to_end(&mut self) -> Result<()>;
-    async fn read_line(&mut self, buf: &mut String) -> Result<usize>;
+    async fn metadata(&self) -> Result<TraceFileMetadata>;
+    async fn seek(&mut self, offset: u64) -> Result<u64>;
+    async fn seek_to_end(&mut self) -> Result<u64>;
+    // Append bytes up to and including the next newline, reading at most `limit` bytes.
+    async fn read_line(&mut self, buf: &mut Vec<u8>, limit: usize) -> Result<usize>;
+}
+
+// A file or subdirectory found while listing a log directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct DirEntry {
+    path: PathBuf,
+    is_dir: bool,
 }
 
 #[async_trait]
@@ -206,8 +899,11 @@ trait TraceFileSystem: Clone + Send + Sync + 'static {
     type Reader: TraceFileReader + Send;
 
     async fn create_dir_all(&self, dir: &Path) -> Result<()>;
-    async fn read_dir(&self, dir: &Path) -> Result<Vec<PathBuf>>;
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<DirEntry>>;
     async fn open_reader(&self, path: &Path) -> Result<Self::Reader>;
+    async fn metadata(&self, path: &Path) -> Result<TraceFileMetadata>;
+    // Subscribe to create, write and delete notifications for files in `dir`.
+    async fn watch_changes(&self, dir: &Path, recursive: bool) -> Result<ChangeNotifier>;
 }
 
 #[derive(Clone, Default)]
@@ -219,13 +915,24 @@ struct RealTraceFileReader {
 
 #[async_trait]
 impl TraceFileReader for RealTraceFileReader {
-    async fn seek_to_end(&mut self) -> Result<()> {
-        self.reader.get_mut().seek(SeekFrom::End(0)).await?;
-        Ok(())
+    async fn metadata(&self) -> Result<TraceFileMetadata> {
+        let metadata = self.reader.get_ref().metadata().await?;
+        Ok(TraceFileMetadata::from(&metadata))
+    }
+
+    async fn seek(&mut self, offset: u64) -> Result<u64> {
+        Ok(self.reader.seek(SeekFrom::Start(offset)).await?)
     }
 
-    async fn read_line(&mut self, buf: &mut String) -> Result<usize> {
-        let bytes = self.reader.read_line(buf).await?;
+    async fn seek_to_end(&mut self) -> Result<u64> {
+        Ok(self.reader.seek(SeekFrom::End(0)).await?)
+    }
+
+    async fn read_line(&mut self, buf: &mut Vec<u8>, limit: usize) -> Result<usize> {
+        let bytes = (&mut self.reader)
+            .take(limit as u64)
+            .read_until(b'\n', buf)
+            .await?;
         Ok(bytes)
     }
 }
@@ -240,13 +947,16 @@ impl TraceFileSystem for RealTraceFileSystem {
             .with_context(|| format!("failed to create log directory {}", dir.display()))
     }
 
-    async fn read_dir(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+    async fn read_dir(&self, dir: &Path) -> Result<Vec<DirEntry>> {
         let mut entries = fs::read_dir(dir).await?;
         let mut paths = Vec::new();
         while let Some(entry) = entries.next_entry().await? {
             let metadata = entry.metadata().await?;
-            if metadata.is_file() {
-                paths.push(entry.path());
+            if metadata.is_file() || metadata.is_dir() {
+                paths.push(DirEntry {
+                    path: entry.path(),
+                    is_dir: metadata.is_dir(),
+                });
             }
         }
         Ok(paths)
@@ -258,13 +968,37 @@ impl TraceFileSystem for RealTraceFileSystem {
             reader: BufReader::new(file),
         })
     }
+
+    async fn metadata(&self, path: &Path) -> Result<TraceFileMetadata> {
+        let metadata = fs::metadata(path).await?;
+        Ok(TraceFileMetadata::from(&metadata))
+    }
+
+    async fn watch_changes(&self, dir: &Path, recursive: bool) -> Result<ChangeNotifier> {
+        ChangeNotifier::watch_directory(dir, recursive)
+    }
+}
+
+impl From<&std::fs::Metadata> for TraceFileMetadata {
+    fn from(metadata: &std::fs::Metadata) -> Self {
+        Self {
+            device: metadata.dev(),
+            inode: metadata.ino(),
+            len: metadata.len(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::change_notifier::ChangeKind;
+    use crate::config::OverflowPolicy;
     use crate::fdb_metric::FDBMetric;
+    use crate::log_sources::FileFilter;
+    use crate::test_helpers::metrics::{find_metric, find_metric_in, prometheus_meter};
     use anyhow::{anyhow, Result};
+    use opentelemetry::metrics::MeterProvider;
     use opentelemetry_sdk::metrics::{ManualReader, SdkMeterProvider};
     use serde_json::json;
     use std::collections::{HashMap, VecDeque};
@@ -273,6 +1007,49 @@ mod tests {
     use tokio::time::{timeout, Duration as TokioDuration};
     use vfs::{MemoryFS, VfsFileType, VfsPath};
 
+    fn test_watch_options(poll_interval: TokioDuration) -> WatchOptions {
+        WatchOptions {
+            poll_interval,
+            start_mode: StartMode::Tail,
+            idle_timeout: TokioDuration::from_secs(60),
+            watch_mode: WatchMode::Poll,
+            max_line_bytes: 1024,
+            pipeline: test_pipeline_options(),
+        }
+    }
+
+    fn test_pipeline_options() -> PipelineOptions {
+        PipelineOptions {
+            workers: 2,
+            queue_capacity: 64,
+            overflow: OverflowPolicy::Block,
+        }
+    }
+
+    fn test_pipeline(exporter_metrics: &ExporterMetrics) -> EventPipeline {
+        EventPipeline::start(test_pipeline_options(), exporter_metrics.clone())
+            .expect("start trace workers")
+    }
+
+    // Queue lines for `metrics` through a dedicated worker pool.
+    fn test_lines(metrics: LogMetrics, exporter_metrics: &ExporterMetrics) -> LineSender {
+        let path = Path::new("/test");
+        test_pipeline(exporter_metrics).sender(
+            path,
+            metrics,
+            exporter_metrics.track_file(path, "test"),
+        )
+    }
+
+    fn tail_options() -> TailerOptions {
+        TailerOptions {
+            start_mode: StartMode::Tail,
+            format: None,
+            max_line_bytes: 1024,
+            backfill: BackfillLimiter::for_start_mode(StartMode::Tail),
+        }
+    }
+
     fn test_meter_provider() -> Arc<SdkMeterProvider> {
         let reader = ManualReader::builder().build();
         Arc::new(SdkMeterProvider::builder().with_reader(reader).build())
@@ -300,29 +1077,55 @@ mod tests {
         }
     }
 
-    #[test]
-    fn should_tail_file_filters_trace_logs() {
-        assert!(should_tail_file("trace.1.json"));
-        assert!(should_tail_file("trace.some_process.json"));
-        assert!(!should_tail_file("trace.1.xml"));
-        assert!(!should_tail_file("random.log"));
-        assert!(!should_tail_file("tracejson"));
+    #[tokio::test]
+    async fn list_trace_files_applies_root_rules() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_trace_file(&log_dir.join("trace.1.json"))?;
+        fs.create_regular_file(&log_dir.join("trace.1.xml"))?;
+        fs.create_regular_file(&log_dir.join("random.log"))?;
+        fs.create_trace_file(&log_dir.join("4500/trace.2.json"))?;
+        fs.create_trace_file(&log_dir.join("old/trace.3.json"))?;
+
+        let flat = LogRoot::new(log_dir.clone());
+        assert_eq!(
+            list_trace_files(&fs, &flat).await?,
+            vec![log_dir.join("trace.1.json"), log_dir.join("trace.1.xml")]
+        );
+
+        let recursive = LogRoot {
+            recursive: true,
+            filter: FileFilter::new(&["trace.*.json"], &["old/**"])?,
+            ..LogRoot::new(log_dir.clone())
+        };
+        assert_eq!(
+            list_trace_files(&fs, &recursive).await?,
+            vec![
+                log_dir.join("4500/trace.2.json"),
+                log_dir.join("trace.1.json")
+            ]
+        );
+
+        Ok(())
     }
 
     #[tokio::test]
     async fn watch_logs_creates_missing_directory() {
         let fs = MemoryTraceFileSystem::new();
         let log_dir = PathBuf::from("/logs");
-        let provider = test_meter_provider();
+        let meter = test_meter_provider().meter("watch_logs_creates_missing_directory");
         assert!(
             !fs.exists(&log_dir),
             "log dir should not exist before watch_logs"
         );
 
         watch_logs_with_fs(
-            &log_dir,
-            provider,
-            TokioDuration::from_millis(50),
+            &[LogRoot::new(log_dir.clone())],
+            LogMetrics::from_metrics(Vec::new()),
+            ExporterMetrics::new(&meter),
+            &meter,
+            test_watch_options(TokioDuration::from_millis(50)),
+            CheckpointStore::disabled(),
             fs.clone(),
         )
         .await
@@ -342,11 +1145,19 @@ mod tests {
         let fs = MemoryTraceFileSystem::new();
         fs.fail_next_create_dir(anyhow!("boom"));
         let log_dir = PathBuf::from("/logs");
-        let provider = test_meter_provider();
-
-        let error = watch_logs_with_fs(&log_dir, provider, TokioDuration::from_millis(50), fs)
-            .await
-            .expect_err("create_dir errors should bubble up");
+        let meter = test_meter_provider().meter("watch_logs_surfaces_directory_creation_errors");
+
+        let error = watch_logs_with_fs(
+            &[LogRoot::new(log_dir.clone())],
+            LogMetrics::from_metrics(Vec::new()),
+            ExporterMetrics::new(&meter),
+            &meter,
+            test_watch_options(TokioDuration::from_millis(50)),
+            CheckpointStore::disabled(),
+            fs,
+        )
+        .await
+        .expect_err("create_dir errors should bubble up");
 
         assert!(
             error.to_string().contains("failed to create log directory"),
@@ -378,10 +1189,13 @@ mod tests {
         let poll_interval = TokioDuration::from_millis(20);
 
         let handle = tokio::spawn(run_log_directory(
-            log_dir.clone(),
+            LogRoot::new(log_dir.clone()),
             log_metrics,
+            test_pipeline(&exporter_metrics),
             exporter_metrics,
-            poll_interval,
+            test_watch_options(poll_interval),
+            BackfillLimiter::for_start_mode(StartMode::Tail),
+            CheckpointStore::disabled(),
             fs.clone(),
         ));
 
@@ -428,10 +1242,13 @@ mod tests {
         let log_metrics = LogMetrics::from_metrics(Vec::<Arc<dyn FDBMetric>>::new());
 
         let handle = tokio::spawn(run_log_directory(
-            log_dir.clone(),
+            LogRoot::new(log_dir.clone()),
             log_metrics,
+            test_pipeline(&exporter_metrics),
             exporter_metrics,
-            TokioDuration::from_millis(20),
+            test_watch_options(TokioDuration::from_millis(20)),
+            BackfillLimiter::for_start_mode(StartMode::Tail),
+            CheckpointStore::disabled(),
             fs.clone(),
         ));
 
@@ -464,7 +1281,13 @@ mod tests {
             "Type": "TestTrace"
         });
         let payload = serde_json::to_string(&event).expect("serialize event");
-        handle_log_line(&payload, &log_metrics, &exporter_metrics);
+        handle_log_line(
+            &payload,
+            TraceFormat::Json,
+            &log_metrics,
+            &exporter_metrics,
+            None,
+        );
 
         let recorded = events.lock().unwrap();
         assert_eq!(
@@ -495,8 +1318,11 @@ mod tests {
         let fs_clone = fs.clone();
         let handle = tokio::spawn(run_log_tailer(
             path_clone,
-            log_metrics,
+            test_lines(log_metrics, &exporter_metrics),
             exporter_metrics,
+            CheckpointStore::disabled(),
+            tail_options(),
+            TailerSignals::polling(),
             fs_clone,
         ));
 
@@ -550,8 +1376,11 @@ mod tests {
         let fs_clone = fs.clone();
         let handle = tokio::spawn(run_log_tailer(
             path_clone,
-            log_metrics,
+            test_lines(log_metrics, &exporter_metrics),
             exporter_metrics,
+            CheckpointStore::disabled(),
+            tail_options(),
+            TailerSignals::polling(),
             fs_clone,
         ));
 
@@ -605,8 +1434,11 @@ mod tests {
         let fs_clone = fs.clone();
         let handle = tokio::spawn(run_log_tailer(
             path_clone,
-            log_metrics,
+            test_lines(log_metrics, &exporter_metrics),
             exporter_metrics,
+            CheckpointStore::disabled(),
+            tail_options(),
+            TailerSignals::polling(),
             fs_clone,
         ));
 
@@ -639,35 +1471,1080 @@ mod tests {
         Ok(())
     }
 
-    #[derive(Clone)]
-    struct MemoryTraceFileSystem {
-        root: VfsPath,
-        files: Arc<Mutex<HashMap<String, Arc<MemoryTraceFile>>>>,
-        failures: Arc<Mutex<MemoryFsFailures>>,
-    }
+    #[test]
+    fn detect_file_change_reports_truncation_and_replacement() {
+        let opened = TraceFileMetadata {
+            device: 1,
+            inode: 10,
+            len: 100,
+        };
 
-    impl MemoryTraceFileSystem {
-        fn new() -> Self {
-            Self {
-                root: VfsPath::new(MemoryFS::new()),
-                files: Arc::new(Mutex::new(HashMap::new())),
-                failures: Arc::new(Mutex::new(MemoryFsFailures::default())),
-            }
-        }
+        let grown = TraceFileMetadata { len: 150, ..opened };
+        assert_eq!(detect_file_change(&opened, &grown, 100), None);
 
-        fn to_vfs_path(&self, path: &Path) -> Result<VfsPath> {
-            let normalized = normalize_path(path)?;
-            if normalized.is_empty() {
-                Ok(self.root.clone())
-            } else {
-                self.root.join(&normalized).map_err(|error| anyhow!(error))
-            }
-        }
+        let truncated = TraceFileMetadata { len: 20, ..opened };
+        assert_eq!(
+            detect_file_change(&opened, &truncated, 100),
+            Some(FileChange::Truncated)
+        );
 
-        fn exists(&self, path: &Path) -> bool {
-            match self.to_vfs_path(path) {
-                Ok(vpath) => vpath.exists().unwrap_or(false),
-                Err(_) => false,
+        let replaced = TraceFileMetadata {
+            inode: 11,
+            ..opened
+        };
+        assert_eq!(
+            detect_file_change(&opened, &replaced, 100),
+            Some(FileChange::Replaced)
+        );
+
+        let other_device = TraceFileMetadata {
+            device: 2,
+            ..opened
+        };
+        assert_eq!(
+            detect_file_change(&opened, &other_device, 100),
+            Some(FileChange::Replaced)
+        );
+    }
+
+    async fn wait_for_events(events: &Arc<Mutex<Vec<TraceEvent>>>, expected: usize) {
+        for _ in 0..80 {
+            if events.lock().unwrap().len() >= expected {
+                break;
+            }
+            tokio::time::sleep(TokioDuration::from_millis(20)).await;
+        }
+    }
+
+    fn rotation_count(registry: &prometheus::Registry, reason: &str) -> f64 {
+        find_metric(
+            registry,
+            "fdb_exporter_file_rotations_total",
+            "reason",
+            reason,
+        )
+        .map(|metric| metric.get_counter().get_value())
+        .unwrap_or(0.0)
+    }
+
+    #[tokio::test]
+    async fn run_log_tailer_rereads_truncated_file() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let trace_path = log_dir.join("trace.10.json");
+        fs.create_trace_file(&trace_path)?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let handle = tokio::spawn(run_log_tailer(
+            trace_path.clone(),
+            test_lines(log_metrics, &exporter_metrics),
+            exporter_metrics,
+            CheckpointStore::disabled(),
+            tail_options(),
+            TailerSignals::polling(),
+            fs.clone(),
+        ));
+
+        tokio::time::sleep(TokioDuration::from_millis(50)).await;
+
+        let before = json!({
+            "Machine": "machine-truncate",
+            "Roles": "storage",
+            "Type": "BeforeTruncate"
+        });
+        fs.append_line(
+            &trace_path,
+            &format!("{}\n", serde_json::to_string(&before)?),
+        )?;
+        wait_for_events(&events, 1).await;
+
+        fs.truncate(&trace_path)?;
+        tokio::time::sleep(TokioDuration::from_millis(300)).await;
+
+        let after = json!({
+            "Machine": "machine-truncate",
+            "Roles": "storage",
+            "Type": "AfterTruncate"
+        });
+        fs.append_line(
+            &trace_path,
+            &format!("{}\n", serde_json::to_string(&after)?),
+        )?;
+        wait_for_events(&events, 2).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        let recorded = events.lock().unwrap();
+        assert_eq!(
+            recorded.len(),
+            2,
+            "expected events before and after truncation"
+        );
+        assert_eq!(recorded[1]["Type"], "AfterTruncate");
+
+        provider.force_flush()?;
+        assert_eq!(rotation_count(&registry, "truncated"), 1.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_tailer_follows_replaced_file() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let trace_path = log_dir.join("trace.11.json");
+        fs.create_trace_file(&trace_path)?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let handle = tokio::spawn(run_log_tailer(
+            trace_path.clone(),
+            test_lines(log_metrics, &exporter_metrics),
+            exporter_metrics,
+            CheckpointStore::disabled(),
+            tail_options(),
+            TailerSignals::polling(),
+            fs.clone(),
+        ));
+
+        tokio::time::sleep(TokioDuration::from_millis(50)).await;
+
+        // Data already in the replacement file must be read, not skipped by a seek to the end.
+        fs.replace_trace_file(&trace_path)?;
+        let event = json!({
+            "Machine": "machine-replace",
+            "Roles": "storage",
+            "Type": "AfterReplace"
+        });
+        fs.append_line(
+            &trace_path,
+            &format!("{}\n", serde_json::to_string(&event)?),
+        )?;
+        wait_for_events(&events, 1).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        let recorded = events.lock().unwrap();
+        assert_eq!(
+            recorded.len(),
+            1,
+            "expected event from the replacement file"
+        );
+        assert_eq!(recorded[0]["Type"], "AfterReplace");
+
+        provider.force_flush()?;
+        assert_eq!(rotation_count(&registry, "replaced"), 1.0);
+        assert_eq!(rotation_count(&registry, "truncated"), 0.0);
+
+        Ok(())
+    }
+
+    fn checkpoint_test_lines() -> (String, String) {
+        let first = json!({
+            "Machine": "machine-checkpoint",
+            "Roles": "storage",
+            "Type": "BeforeRestart"
+        });
+        let second = json!({
+            "Machine": "machine-checkpoint",
+            "Roles": "storage",
+            "Type": "WhileStopped"
+        });
+        (
+            format!("{}\n", serde_json::to_string(&first).unwrap()),
+            format!("{}\n", serde_json::to_string(&second).unwrap()),
+        )
+    }
+
+    #[tokio::test]
+    async fn run_log_tailer_resumes_from_checkpoint() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let trace_path = log_dir.join("trace.12.json");
+        fs.create_trace_file(&trace_path)?;
+
+        let (first, second) = checkpoint_test_lines();
+        fs.append_line(&trace_path, &first)?;
+        fs.append_line(&trace_path, &second)?;
+
+        let state_dir = tempfile::tempdir()?;
+        let checkpoints = CheckpointStore::load(state_dir.path())?;
+        let metadata = fs.metadata(&trace_path).await?;
+        checkpoints.update(
+            &trace_path,
+            metadata.checkpoint(first.len() as u64, Some(hash_first_line(first.as_bytes()))),
+        );
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let provider = test_meter_provider();
+        let meter = provider.meter("run_log_tailer_resumes_from_checkpoint");
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let handle = tokio::spawn(run_log_tailer(
+            trace_path.clone(),
+            test_lines(log_metrics, &exporter_metrics),
+            exporter_metrics,
+            checkpoints.clone(),
+            tail_options(),
+            TailerSignals::polling(),
+            fs.clone(),
+        ));
+
+        wait_for_events(&events, 1).await;
+        tokio::time::sleep(TokioDuration::from_millis(50)).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        let recorded = events.lock().unwrap();
+        assert_eq!(
+            recorded.len(),
+            1,
+            "only the unread event should be recorded"
+        );
+        assert_eq!(recorded[0]["Type"], "WhileStopped");
+
+        let saved = checkpoints
+            .get(&trace_path)
+            .expect("checkpoint should be kept up to date");
+        assert_eq!(saved.offset, (first.len() + second.len()) as u64);
+        assert_eq!(
+            saved.first_line_hash,
+            Some(hash_first_line(first.as_bytes()))
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_tailer_rereads_file_when_checkpoint_is_stale() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let trace_path = log_dir.join("trace.13.json");
+        fs.create_trace_file(&trace_path)?;
+
+        let (first, second) = checkpoint_test_lines();
+        fs.append_line(&trace_path, &first)?;
+        fs.append_line(&trace_path, &second)?;
+
+        // Same inode and a plausible offset, but the first line belongs to a different file.
+        let state_dir = tempfile::tempdir()?;
+        let checkpoints = CheckpointStore::load(state_dir.path())?;
+        let metadata = fs.metadata(&trace_path).await?;
+        checkpoints.update(
+            &trace_path,
+            metadata.checkpoint(first.len() as u64, Some(hash_first_line(b"other\n"))),
+        );
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let provider = test_meter_provider();
+        let meter = provider.meter("run_log_tailer_rereads_file_when_checkpoint_is_stale");
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let handle = tokio::spawn(run_log_tailer(
+            trace_path.clone(),
+            test_lines(log_metrics, &exporter_metrics),
+            exporter_metrics,
+            checkpoints,
+            tail_options(),
+            TailerSignals::polling(),
+            fs.clone(),
+        ));
+
+        wait_for_events(&events, 2).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        let recorded = events.lock().unwrap();
+        assert_eq!(recorded.len(), 2, "stale checkpoint should reread the file");
+        assert_eq!(recorded[0]["Type"], "BeforeRestart");
+
+        Ok(())
+    }
+
+    #[test]
+    fn is_before_compares_trace_time_with_cutoff() {
+        let mut event = TraceEvent::new();
+        assert!(
+            !is_before(&event, Some(10.0)),
+            "events without Time are kept"
+        );
+
+        event.insert("Time".into(), json!("9.5"));
+        assert!(is_before(&event, Some(10.0)));
+        assert!(!is_before(&event, Some(9.5)));
+        assert!(!is_before(&event, None));
+    }
+
+    #[tokio::test]
+    async fn catch_up_throttle_limits_lines_per_tick() {
+        let mut catch_up = CatchUp::new(
+            BackfillOptions {
+                since: None,
+                max_lines_per_sec: 20.0,
+            },
+            1_000,
+            BackfillLimiter::new(20.0),
+        );
+        assert_eq!(catch_up.limiter.lines_per_tick, 2);
+
+        let started = Instant::now();
+        assert!(!catch_up.throttle().await);
+        assert!(catch_up.throttle().await, "second line completes the tick");
+        assert!(!catch_up.throttle().await);
+        assert!(
+            started.elapsed() >= BACKFILL_TICK,
+            "a full tick should pause until the tick elapses"
+        );
+    }
+
+    #[tokio::test]
+    async fn catch_up_throttle_shares_one_budget_across_files() {
+        let options = BackfillOptions {
+            since: None,
+            max_lines_per_sec: 20.0,
+        };
+        let limiter = BackfillLimiter::new(options.max_lines_per_sec);
+        let mut first = CatchUp::new(options, 1_000, limiter.clone());
+        let mut second = CatchUp::new(options, 1_000, limiter);
+
+        let started = Instant::now();
+        first.throttle().await;
+        second.throttle().await;
+        assert!(started.elapsed() < BACKFILL_TICK);
+        first.throttle().await;
+        assert!(
+            started.elapsed() >= BACKFILL_TICK,
+            "the third line across both files should wait for the next tick"
+        );
+    }
+
+    #[tokio::test]
+    async fn run_log_tailer_reads_xml_trace_files() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        // One file named for its format, and one whose format has to be sniffed from its content.
+        let xml_path = log_dir.join("trace.13.xml");
+        let unnamed_path = log_dir.join("trace.14.log");
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let mut handles = Vec::new();
+        for path in [&xml_path, &unnamed_path] {
+            fs.create_trace_file(path)?;
+            fs.append_line(path, "<?xml version=\"1.0\"?>\n<Trace>\n")?;
+            handles.push(tokio::spawn(run_log_tailer(
+                path.clone(),
+                test_lines(log_metrics.clone(), &exporter_metrics.clone()),
+                exporter_metrics.clone(),
+                CheckpointStore::disabled(),
+                TailerOptions {
+                    start_mode: StartMode::Backfill(BackfillOptions {
+                        since: None,
+                        max_lines_per_sec: 1_000.0,
+                    }),
+                    format: None,
+                    max_line_bytes: 1024,
+                    backfill: BackfillLimiter::new(1_000.0),
+                },
+                TailerSignals::polling(),
+                fs.clone(),
+            )));
+        }
+
+        for path in [&xml_path, &unnamed_path] {
+            fs.append_line(
+                path,
+                "<Event Severity=\"10\" Type=\"TestTrace\" Machine=\"10.0.0.1:4500\" Roles=\"SS\" />\n",
+            )?;
+        }
+        wait_for_events(&events, 2).await;
+
+        for handle in handles {
+            handle.abort();
+            let _ = handle.await;
+        }
+
+        let recorded = events.lock().unwrap();
+        assert_eq!(recorded.len(), 2);
+        assert!(recorded
+            .iter()
+            .all(|event| event.get("Type") == Some(&json!("TestTrace"))));
+        provider.force_flush()?;
+        assert!(find_metric(
+            &registry,
+            "fdb_exporter_parse_errors_total",
+            "otel_scope_name",
+            "test"
+        )
+        .is_none());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_tailer_waits_for_complete_lines() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let trace_path = log_dir.join("trace.15.json");
+        fs.create_trace_file(&trace_path)?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let handle = tokio::spawn(run_log_tailer(
+            trace_path.clone(),
+            test_lines(log_metrics, &exporter_metrics),
+            exporter_metrics,
+            CheckpointStore::disabled(),
+            TailerOptions {
+                max_line_bytes: 128,
+                ..tail_options()
+            },
+            TailerSignals::polling(),
+            fs.clone(),
+        ));
+        tokio::time::sleep(TokioDuration::from_millis(50)).await;
+
+        // The tailer reaches EOF in the middle of the first line before the rest is flushed.
+        fs.append_line(&trace_path, r#"{"Type":"Split","Machine":"#)?;
+        tokio::time::sleep(TokioDuration::from_millis(400)).await;
+        fs.append_line(&trace_path, "\"10.0.0.1:4500\"}\n")?;
+        fs.append_line(
+            &trace_path,
+            &format!("{{\"Type\":\"{}\"}}\n", "x".repeat(200)),
+        )?;
+        fs.append_line(
+            &trace_path,
+            "{\"Type\":\"After\",\"Machine\":\"10.0.0.1:4500\"}\n",
+        )?;
+        wait_for_events(&events, 2).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        let recorded = events.lock().unwrap();
+        let types: Vec<_> = recorded.iter().map(|event| event["Type"].clone()).collect();
+        assert_eq!(types, [json!("Split"), json!("After")]);
+
+        provider.force_flush()?;
+        let families = registry.gather();
+        assert!(find_metric_in(
+            &families,
+            "fdb_exporter_parse_errors_total",
+            "otel_scope_name",
+            "test"
+        )
+        .is_none());
+        let dropped = find_metric_in(
+            &families,
+            "fdb_exporter_partial_lines_dropped_total",
+            "reason",
+            "oversized",
+        )
+        .expect("oversized line counter");
+        assert_eq!(dropped.get_counter().get_value(), 1.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_tailer_reports_per_file_progress() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let trace_path = log_dir.join("trace.10.0.0.1.4500.1700000000.AbCdEf.0.1.json");
+        fs.create_trace_file(&trace_path)?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+        let progress = exporter_metrics.track_file(&trace_path, &trace_file_process(&trace_path));
+        let lines = test_pipeline(&exporter_metrics).sender(&trace_path, log_metrics, progress);
+
+        let contents = [
+            "{\"Type\":\"First\",\"Machine\":\"10.0.0.1:4500\",\"Time\":\"100.000000\"}\n",
+            "{\"Type\":\"Second\",\"Machine\":\"10.0.0.1:4500\",\"Time\":\"160.500000\"}\n",
+        ];
+        let handle = tokio::spawn(run_log_tailer(
+            trace_path.clone(),
+            lines,
+            exporter_metrics,
+            CheckpointStore::disabled(),
+            tail_options(),
+            TailerSignals::polling(),
+            fs.clone(),
+        ));
+        tokio::time::sleep(TokioDuration::from_millis(50)).await;
+        for line in contents {
+            fs.append_line(&trace_path, line)?;
+        }
+        wait_for_events(&events, 2).await;
+        tokio::time::sleep(TokioDuration::from_millis(100)).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        provider.force_flush()?;
+        let families = registry.gather();
+        let file = trace_path.display().to_string();
+        let series =
+            |name: &str| {
+                let metric = find_metric_in(&families, name, "file", &file)
+                    .unwrap_or_else(|| panic!("{name} series"));
+                assert!(metric
+                    .get_label()
+                    .iter()
+                    .any(|label| label.get_name() == "process"
+                        && label.get_value() == "10.0.0.1:4500"));
+                metric
+            };
+        let total_bytes: usize = contents.iter().map(|line| line.len()).sum();
+        assert_eq!(
+            series("fdb_exporter_file_bytes_read_total")
+                .get_counter()
+                .get_value(),
+            total_bytes as f64
+        );
+        assert_eq!(
+            series("fdb_exporter_file_lines_read_total")
+                .get_counter()
+                .get_value(),
+            2.0
+        );
+        assert_eq!(
+            series("fdb_exporter_file_unread_bytes")
+                .get_gauge()
+                .get_value(),
+            0.0
+        );
+        assert_eq!(
+            series("fdb_exporter_file_last_event_time_seconds")
+                .get_gauge()
+                .get_value(),
+            160.5
+        );
+        assert!(
+            series("fdb_exporter_file_lag_seconds")
+                .get_gauge()
+                .get_value()
+                > 0.0
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_tailer_backfills_existing_lines_since_cutoff() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let trace_path = log_dir.join("trace.14.json");
+        fs.create_trace_file(&trace_path)?;
+
+        for (time, trace_type) in [("100.0", "TooOld"), ("200.0", "Backfilled")] {
+            let event = json!({
+                "Machine": "machine-backfill",
+                "Roles": "storage",
+                "Time": time,
+                "Type": trace_type
+            });
+            fs.append_line(
+                &trace_path,
+                &format!("{}\n", serde_json::to_string(&event)?),
+            )?;
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let lines = test_pipeline(&exporter_metrics).sender(
+            &trace_path,
+            log_metrics,
+            exporter_metrics.track_file(&trace_path, "machine-backfill"),
+        );
+        let handle = tokio::spawn(run_log_tailer(
+            trace_path.clone(),
+            lines,
+            exporter_metrics,
+            CheckpointStore::disabled(),
+            TailerOptions {
+                start_mode: StartMode::Backfill(BackfillOptions {
+                    since: Some(150.0),
+                    max_lines_per_sec: 1_000.0,
+                }),
+                format: None,
+                max_line_bytes: 1024,
+                backfill: BackfillLimiter::new(1_000.0),
+            },
+            TailerSignals::polling(),
+            fs.clone(),
+        ));
+
+        wait_for_events(&events, 1).await;
+
+        let live = json!({
+            "Machine": "machine-backfill",
+            "Roles": "storage",
+            "Time": "50.0",
+            "Type": "Live"
+        });
+        fs.append_line(&trace_path, &format!("{}\n", serde_json::to_string(&live)?))?;
+        wait_for_events(&events, 2).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        let recorded = events.lock().unwrap();
+        let types: Vec<&str> = recorded
+            .iter()
+            .map(|event| event["Type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            vec!["Backfilled", "Live"],
+            "cutoff only applies while catching up"
+        );
+
+        provider.force_flush()?;
+        let file_label = trace_path.display().to_string();
+        let families = registry.gather();
+        let progress = find_metric_in(
+            &families,
+            "fdb_exporter_backfill_progress_ratio",
+            "file",
+            &file_label,
+        )
+        .expect("backfill progress should be reported");
+        assert_eq!(progress.get_gauge().get_value(), 1.0);
+        let remaining = find_metric_in(
+            &families,
+            "fdb_exporter_backfill_remaining_bytes",
+            "file",
+            &file_label,
+        )
+        .expect("backfill remaining bytes should be reported");
+        assert_eq!(remaining.get_gauge().get_value(), 0.0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_trace_file_name_extracts_process_and_sequence() {
+        let parsed = parse_trace_file_name("trace.10.0.0.1.4500.1700000000.AbCdEf.0.3.json")
+            .expect("FDB trace file name should parse");
+        assert_eq!(parsed.process, "10.0.0.1:4500");
+        assert_eq!(parsed.sequence, vec![1_700_000_000, 0, 3]);
+
+        assert!(parse_trace_file_name("trace.1.json").is_none());
+        assert!(parse_trace_file_name("trace.some_process.json").is_none());
+    }
+
+    #[test]
+    fn superseded_trace_files_keeps_newest_file_per_process() {
+        let paths: Vec<PathBuf> = [
+            "/logs/trace.10.0.0.1.4500.1700000000.AbCdEf.0.2.json",
+            "/logs/trace.10.0.0.1.4500.1700000000.AbCdEf.0.1.json",
+            "/logs/trace.10.0.0.1.4500.1700000500.GhIjKl.0.1.json",
+            "/logs/trace.10.0.0.2.4500.1700000000.MnOpQr.0.1.json",
+            "/logs/trace.1.json",
+        ]
+        .into_iter()
+        .map(PathBuf::from)
+        .collect();
+
+        let superseded = superseded_trace_files(&paths);
+        assert_eq!(superseded.len(), 2);
+        assert!(superseded.contains(&paths[0]));
+        assert!(superseded.contains(&paths[1]));
+    }
+
+    fn retired_count(registry: &prometheus::Registry, reason: &str) -> f64 {
+        find_metric(
+            registry,
+            "fdb_exporter_tailers_retired_total",
+            "reason",
+            reason,
+        )
+        .map(|metric| metric.get_counter().get_value())
+        .unwrap_or(0.0)
+    }
+
+    #[tokio::test]
+    async fn run_log_directory_retires_tailers_for_deleted_files() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let trace_path = log_dir.join("trace.10.0.0.1.4500.1700000000.AbCdEf.0.1.json");
+        fs.create_trace_file(&trace_path)?;
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+        let log_metrics = LogMetrics::from_metrics(Vec::<Arc<dyn FDBMetric>>::new());
+
+        let handle = tokio::spawn(run_log_directory(
+            LogRoot::new(log_dir.clone()),
+            log_metrics,
+            test_pipeline(&exporter_metrics),
+            exporter_metrics,
+            test_watch_options(TokioDuration::from_millis(20)),
+            BackfillLimiter::for_start_mode(StartMode::Tail),
+            CheckpointStore::disabled(),
+            fs.clone(),
+        ));
+
+        tokio::time::sleep(TokioDuration::from_millis(60)).await;
+        fs.remove_file(&trace_path)?;
+        tokio::time::sleep(TokioDuration::from_millis(60)).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        provider.force_flush()?;
+        assert_eq!(retired_count(&registry, "deleted"), 1.0);
+        assert_eq!(retired_count(&registry, "superseded"), 0.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_directory_retires_idle_superseded_tailers() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        let old_path = log_dir.join("trace.10.0.0.1.4500.1700000000.AbCdEf.0.1.json");
+        let new_path = log_dir.join("trace.10.0.0.1.4500.1700000000.AbCdEf.0.2.json");
+        fs.create_trace_file(&old_path)?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let options = WatchOptions {
+            idle_timeout: TokioDuration::from_millis(100),
+            ..test_watch_options(TokioDuration::from_millis(20))
+        };
+        let handle = tokio::spawn(run_log_directory(
+            LogRoot::new(log_dir.clone()),
+            log_metrics,
+            test_pipeline(&exporter_metrics),
+            exporter_metrics,
+            options,
+            BackfillLimiter::for_start_mode(StartMode::Tail),
+            CheckpointStore::disabled(),
+            fs.clone(),
+        ));
+
+        tokio::time::sleep(TokioDuration::from_millis(60)).await;
+        fs.create_trace_file(&new_path)?;
+        tokio::time::sleep(TokioDuration::from_millis(250)).await;
+
+        // The retired tailer must not pick up late writes to the superseded file.
+        let event = json!({
+            "Machine": "machine-rolled",
+            "Roles": "storage",
+            "Type": "LateWrite"
+        });
+        fs.append_line(&old_path, &format!("{}\n", serde_json::to_string(&event)?))?;
+        tokio::time::sleep(TokioDuration::from_millis(350)).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        assert!(events.lock().unwrap().is_empty());
+        provider.force_flush()?;
+        assert_eq!(retired_count(&registry, "superseded"), 1.0);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_directory_reacts_to_change_notifications() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let (provider, meter, registry) = prometheus_meter();
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        // A poll interval far longer than the test, so only notifications can drive it.
+        let options = WatchOptions {
+            watch_mode: WatchMode::Notify,
+            ..test_watch_options(TokioDuration::from_secs(60))
+        };
+        let handle = tokio::spawn(run_log_directory(
+            LogRoot::new(log_dir.clone()),
+            log_metrics,
+            test_pipeline(&exporter_metrics),
+            exporter_metrics,
+            options,
+            BackfillLimiter::for_start_mode(StartMode::Tail),
+            CheckpointStore::disabled(),
+            fs.clone(),
+        ));
+
+        tokio::time::sleep(TokioDuration::from_millis(50)).await;
+        let trace_path = log_dir.join("trace.11.json");
+        fs.create_trace_file(&trace_path)?;
+        tokio::time::sleep(TokioDuration::from_millis(50)).await;
+
+        let event = json!({
+            "Machine": "machine-notify",
+            "Roles": "storage",
+            "Type": "TestTrace",
+            "Time": format!("{:.6}", unix_now()),
+        });
+        fs.append_line(
+            &trace_path,
+            &format!("{}\n", serde_json::to_string(&event)?),
+        )?;
+        timeout(TokioDuration::from_secs(1), async {
+            while events.lock().unwrap().is_empty() {
+                tokio::time::sleep(TokioDuration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("notification should wake the tailer well before the fallback poll");
+
+        handle.abort();
+        let _ = handle.await;
+
+        provider.force_flush()?;
+        let latency = find_metric(
+            &registry,
+            "fdb_exporter_event_latency_seconds",
+            "otel_scope_name",
+            "test",
+        )
+        .expect("event latency histogram");
+        assert_eq!(latency.get_histogram().get_sample_count(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_directory_wakes_tailers_under_relative_log_dir() -> Result<()> {
+        // A directory relative to the working directory, like the default `LOG_DIR=logs`.
+        let dir = tempfile::Builder::new().tempdir_in(".")?;
+        let log_dir = PathBuf::from(".").join(dir.path().file_name().expect("tempdir name"));
+        assert!(log_dir.is_relative());
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let provider = test_meter_provider();
+        let meter = provider.meter("run_log_directory_wakes_tailers_under_relative_log_dir");
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let options = WatchOptions {
+            watch_mode: WatchMode::Notify,
+            ..test_watch_options(TokioDuration::from_secs(60))
+        };
+        let handle = tokio::spawn(run_log_directory(
+            LogRoot::new(log_dir.clone()),
+            log_metrics,
+            test_pipeline(&exporter_metrics),
+            exporter_metrics,
+            options,
+            BackfillLimiter::for_start_mode(StartMode::Tail),
+            CheckpointStore::disabled(),
+            RealTraceFileSystem,
+        ));
+
+        tokio::time::sleep(TokioDuration::from_millis(100)).await;
+        let trace_path = dir.path().join("trace.13.json");
+        std::fs::write(&trace_path, "")?;
+        tokio::time::sleep(TokioDuration::from_millis(200)).await;
+
+        let event = json!({
+            "Machine": "machine-relative",
+            "Roles": "storage",
+            "Type": "TestTrace"
+        });
+        let mut file = std::fs::OpenOptions::new().append(true).open(&trace_path)?;
+        std::io::Write::write_all(
+            &mut file,
+            format!("{}\n", serde_json::to_string(&event)?).as_bytes(),
+        )?;
+        timeout(TokioDuration::from_secs(2), async {
+            while events.lock().unwrap().is_empty() {
+                tokio::time::sleep(TokioDuration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("write notification should wake the tailer before the fallback poll");
+
+        handle.abort();
+        let _ = handle.await;
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn run_log_directory_polls_when_notifications_fail() -> Result<()> {
+        let fs = MemoryTraceFileSystem::new();
+        let log_dir = PathBuf::from("/logs");
+        fs.create_dir_all(&log_dir).await?;
+        fs.fail_next_watch(anyhow!("inotify unavailable"));
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let metrics: Vec<Arc<dyn FDBMetric>> = vec![Arc::new(RecordingGauge::new(events.clone()))];
+        let log_metrics = LogMetrics::from_metrics(metrics);
+
+        let provider = test_meter_provider();
+        let meter = provider.meter("run_log_directory_polls_when_notifications_fail");
+        let exporter_metrics = ExporterMetrics::new(&meter);
+
+        let options = WatchOptions {
+            watch_mode: WatchMode::Notify,
+            ..test_watch_options(TokioDuration::from_millis(20))
+        };
+        let handle = tokio::spawn(run_log_directory(
+            LogRoot::new(log_dir.clone()),
+            log_metrics,
+            test_pipeline(&exporter_metrics),
+            exporter_metrics,
+            options,
+            BackfillLimiter::for_start_mode(StartMode::Tail),
+            CheckpointStore::disabled(),
+            fs.clone(),
+        ));
+
+        tokio::time::sleep(TokioDuration::from_millis(50)).await;
+        let trace_path = log_dir.join("trace.12.json");
+        fs.create_trace_file(&trace_path)?;
+        tokio::time::sleep(TokioDuration::from_millis(60)).await;
+
+        let event = json!({
+            "Machine": "machine-poll",
+            "Roles": "storage",
+            "Type": "TestTrace"
+        });
+        fs.append_line(
+            &trace_path,
+            &format!("{}\n", serde_json::to_string(&event)?),
+        )?;
+        wait_for_events(&events, 1).await;
+
+        handle.abort();
+        let _ = handle.await;
+
+        assert_eq!(events.lock().unwrap().len(), 1);
+        assert!(fs.failures.lock().unwrap().watch.is_empty());
+
+        Ok(())
+    }
+
+    #[derive(Clone)]
+    struct MemoryTraceFileSystem {
+        root: VfsPath,
+        files: Arc<Mutex<HashMap<String, Arc<MemoryTraceFile>>>>,
+        failures: Arc<Mutex<MemoryFsFailures>>,
+        next_inode: Arc<Mutex<u64>>,
+        notifier: Arc<Mutex<Option<ChangeNotifier>>>,
+    }
+
+    impl MemoryTraceFileSystem {
+        fn new() -> Self {
+            Self {
+                root: VfsPath::new(MemoryFS::new()),
+                files: Arc::new(Mutex::new(HashMap::new())),
+                failures: Arc::new(Mutex::new(MemoryFsFailures::default())),
+                next_inode: Arc::new(Mutex::new(1)),
+                notifier: Arc::new(Mutex::new(None)),
+            }
+        }
+
+        // Deliver a change notification the way the inotify watcher would, once one is installed.
+        fn notify(&self, path: &Path, kind: ChangeKind) {
+            if let Some(notifier) = self.notifier.lock().unwrap().as_ref() {
+                notifier.notify_path(path, kind);
+            }
+        }
+
+        fn allocate_file(&self) -> Arc<MemoryTraceFile> {
+            let mut next_inode = self.next_inode.lock().unwrap();
+            let inode = *next_inode;
+            *next_inode += 1;
+            Arc::new(MemoryTraceFile {
+                inode,
+                data: Mutex::new(Vec::new()),
+            })
+        }
+
+        fn lookup_file(&self, path: &Path) -> Result<Arc<MemoryTraceFile>> {
+            let key = normalize_path(path)?;
+            self.files
+                .lock()
+                .unwrap()
+                .get(&key)
+                .cloned()
+                .with_context(|| format!("virtual file {} not found", path.display()))
+        }
+
+        fn to_vfs_path(&self, path: &Path) -> Result<VfsPath> {
+            let normalized = normalize_path(path)?;
+            if normalized.is_empty() {
+                Ok(self.root.clone())
+            } else {
+                self.root.join(&normalized).map_err(|error| anyhow!(error))
+            }
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            match self.to_vfs_path(path) {
+                Ok(vpath) => vpath.exists().unwrap_or(false),
+                Err(_) => false,
             }
         }
 
@@ -678,11 +2555,12 @@ mod tests {
                 .create_dir_all()
                 .map_err(|error| anyhow!(error))?;
             drop(vpath.create_file().map_err(|error| anyhow!(error))?);
-            let file = Arc::new(MemoryTraceFile::default());
+            let file = self.allocate_file();
             self.files
                 .lock()
                 .unwrap()
                 .insert(normalize_path(path)?, file);
+            self.notify(path, ChangeKind::Listing);
             Ok(())
         }
 
@@ -693,20 +2571,44 @@ mod tests {
                 .create_dir_all()
                 .map_err(|error| anyhow!(error))?;
             drop(vpath.create_file().map_err(|error| anyhow!(error))?);
+            self.notify(path, ChangeKind::Listing);
             Ok(())
         }
 
         fn append_line(&self, path: &Path, contents: &str) -> Result<()> {
-            let key = normalize_path(path)?;
-            let file = self
-                .files
-                .lock()
-                .unwrap()
-                .get(&key)
-                .cloned()
-                .with_context(|| format!("virtual file {} not found", path.display()))?;
+            let file = self.lookup_file(path)?;
             let mut data = file.data.lock().unwrap();
             data.extend_from_slice(contents.as_bytes());
+            drop(data);
+            self.notify(path, ChangeKind::Contents);
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> Result<()> {
+            self.to_vfs_path(path)?
+                .remove_file()
+                .map_err(|error| anyhow!(error))?;
+            self.files.lock().unwrap().remove(&normalize_path(path)?);
+            self.notify(path, ChangeKind::Listing);
+            Ok(())
+        }
+
+        // Truncate the file in place, keeping its inode.
+        fn truncate(&self, path: &Path) -> Result<()> {
+            let file = self.lookup_file(path)?;
+            file.data.lock().unwrap().clear();
+            self.notify(path, ChangeKind::Contents);
+            Ok(())
+        }
+
+        // Swap a fresh, empty file with a new inode in at `path`, as a rename-based rotation would.
+        fn replace_trace_file(&self, path: &Path) -> Result<()> {
+            let file = self.allocate_file();
+            self.files
+                .lock()
+                .unwrap()
+                .insert(normalize_path(path)?, file);
+            self.notify(path, ChangeKind::Listing);
             Ok(())
         }
 
@@ -741,6 +2643,10 @@ mod tests {
         fn fail_next_read(&self, error: impl Into<anyhow::Error>) {
             self.failures.lock().unwrap().read.push_back(error.into());
         }
+
+        fn fail_next_watch(&self, error: impl Into<anyhow::Error>) {
+            self.failures.lock().unwrap().watch.push_back(error.into());
+        }
     }
 
     #[async_trait]
@@ -756,7 +2662,7 @@ mod tests {
             Ok(())
         }
 
-        async fn read_dir(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        async fn read_dir(&self, dir: &Path) -> Result<Vec<DirEntry>> {
             if let Some(error) = self.failures.lock().unwrap().read_dir.pop_front() {
                 return Err(error);
             }
@@ -765,9 +2671,10 @@ mod tests {
             let mut paths = Vec::new();
             for entry in entries {
                 let metadata = entry.metadata().map_err(|error| anyhow!(error))?;
-                if metadata.file_type == VfsFileType::File {
-                    paths.push(dir.join(entry.filename()));
-                }
+                paths.push(DirEntry {
+                    path: dir.join(entry.filename()),
+                    is_dir: metadata.file_type == VfsFileType::Directory,
+                });
             }
             Ok(paths)
         }
@@ -776,27 +2683,43 @@ mod tests {
             if let Some(error) = self.failures.lock().unwrap().open_reader.pop_front() {
                 return Err(error);
             }
-            let key = normalize_path(path)?;
-            let file = self
-                .files
-                .lock()
-                .unwrap()
-                .get(&key)
-                .cloned()
-                .with_context(|| format!("virtual file {} not found", path.display()))?;
+            let file = self.lookup_file(path)?;
             Ok(MemoryTraceFileReader {
                 file,
                 offset: 0,
                 failures: Arc::clone(&self.failures),
             })
         }
+
+        async fn metadata(&self, path: &Path) -> Result<TraceFileMetadata> {
+            Ok(self.lookup_file(path)?.metadata())
+        }
+
+        async fn watch_changes(&self, _dir: &Path, _recursive: bool) -> Result<ChangeNotifier> {
+            if let Some(error) = self.failures.lock().unwrap().watch.pop_front() {
+                return Err(error);
+            }
+            let notifier = ChangeNotifier::default();
+            *self.notifier.lock().unwrap() = Some(notifier.clone());
+            Ok(notifier)
+        }
     }
 
-    #[derive(Default)]
     struct MemoryTraceFile {
+        inode: u64,
         data: Mutex<Vec<u8>>,
     }
 
+    impl MemoryTraceFile {
+        fn metadata(&self) -> TraceFileMetadata {
+            TraceFileMetadata {
+                device: 0,
+                inode: self.inode,
+                len: self.data.lock().unwrap().len() as u64,
+            }
+        }
+    }
+
     struct MemoryTraceFileReader {
         file: Arc<MemoryTraceFile>,
         offset: usize,
@@ -805,16 +2728,29 @@ mod tests {
 
     #[async_trait]
     impl TraceFileReader for MemoryTraceFileReader {
-        async fn seek_to_end(&mut self) -> Result<()> {
+        async fn metadata(&self) -> Result<TraceFileMetadata> {
+            Ok(self.file.metadata())
+        }
+
+        async fn seek(&mut self, offset: u64) -> Result<u64> {
+            if let Some(error) = self.failures.lock().unwrap().seek.pop_front() {
+                return Err(error);
+            }
+            let data = self.file.data.lock().unwrap();
+            self.offset = (offset as usize).min(data.len());
+            Ok(self.offset as u64)
+        }
+
+        async fn seek_to_end(&mut self) -> Result<u64> {
             if let Some(error) = self.failures.lock().unwrap().seek.pop_front() {
                 return Err(error);
             }
             let data = self.file.data.lock().unwrap();
             self.offset = data.len();
-            Ok(())
+            Ok(self.offset as u64)
         }
 
-        async fn read_line(&mut self, buf: &mut String) -> Result<usize> {
+        async fn read_line(&mut self, buf: &mut Vec<u8>, limit: usize) -> Result<usize> {
             if let Some(error) = self.failures.lock().unwrap().read.pop_front() {
                 return Err(error);
             }
@@ -828,15 +2764,15 @@ mod tests {
                 let end = match newline_pos {
                     Some(idx) => self.offset + idx + 1,
                     None => data.len(),
-                };
+                }
+                .min(self.offset + limit);
                 let bytes = data[self.offset..end].to_vec();
                 self.offset = end;
                 bytes
             };
 
-            let line = String::from_utf8(bytes)?;
-            buf.push_str(&line);
-            Ok(line.len())
+            buf.extend_from_slice(&bytes);
+            Ok(bytes.len())
         }
     }
 
@@ -864,5 +2800,6 @@ mod tests {
         open_reader: VecDeque<anyhow::Error>,
         seek: VecDeque<anyhow::Error>,
         read: VecDeque<anyhow::Error>,
+        watch: VecDeque<anyhow::Error>,
     }
 }